@@ -0,0 +1,573 @@
+//! Claim audits: reconciles the distributors of a dump of the program's accounts with their receipts,
+//! referral records and reward vaults, and flags what does not add up.
+//! Receipts only hold their PDA seeds, so they are matched to a distributor by deriving the receipt address of
+//! candidate mints: the attestation and metadata mints of the dump and any mints given.
+//! Allowlist amounts replace the reward of a mint, so the receipts of a distributor with a merkle root are only
+//! checked against the allowlist of that root.
+
+use crate::{dump::DumpedAccount, merkle::MerkleTree};
+use serde::{Serialize, Serializer};
+use solana_nft_claim_tokens::{
+    client::{decode_attestation, decode_distributor, decode_proof_of_receipt, decode_referral_record, decode_wallet_claim_record, find_receipt_address},
+    state::{AccountKey, DistributorAccount, ProofOfReceiptAccount},
+};
+use solana_program::{program_pack::Pack, pubkey::Pubkey};
+use spl_token::state::Account as TokenAccount;
+use spl_token_metadata::state::Key as MetadataKey;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+fn as_string<S: Serializer>(pubkey: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(pubkey)
+}
+
+fn as_strings<S: Serializer>(pubkeys: &[Pubkey], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(pubkeys.iter().map(Pubkey::to_string))
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Discrepancy {
+    // amount_claimed is over reward_amount_total
+    OverClaimed,
+    // amount_claimed less the referral bonuses is not what the receipts were paid
+    ClaimedMismatch { claimed_rewards: u64, expected_min: u64, expected_max: u64 },
+    // the vault holds less than the unclaimed pool
+    VaultUnderfunded { vault_balance: u64, remaining: u64 },
+    // wallet claim records, kept by distributors with a wallet limit, do not count the receipts
+    WalletClaimsMismatch { wallet_claims: u64, receipts: u64 },
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Discrepancy::OverClaimed => write!(f, "amount claimed exceeds reward amount total"),
+            Discrepancy::ClaimedMismatch { claimed_rewards, expected_min, expected_max } if expected_min == expected_max => {
+                write!(f, "claimed rewards {} but receipts were paid {}", claimed_rewards, expected_min)
+            }
+            Discrepancy::ClaimedMismatch { claimed_rewards, expected_min, expected_max } => {
+                write!(f, "claimed rewards {} but receipts were paid {} to {}", claimed_rewards, expected_min, expected_max)
+            }
+            Discrepancy::VaultUnderfunded { vault_balance, remaining } => {
+                write!(f, "vault balance {} below remaining pool {}", vault_balance, remaining)
+            }
+            Discrepancy::WalletClaimsMismatch { wallet_claims, receipts } => {
+                write!(f, "wallet claim records count {} claims for {} receipts", wallet_claims, receipts)
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DistributorReport {
+    #[serde(serialize_with = "as_string")]
+    pub distributor: Pubkey,
+    pub version: u8,
+    #[serde(serialize_with = "as_string")]
+    pub reward_mint: Pubkey,
+    pub reward_amount_total: u64,
+    pub amount_claimed: u64,
+    pub receipts: u64,
+    pub referral_paid: u64,
+    pub wallet_claims: u64,
+    // None when the vault is not in the dump
+    pub vault_balance: Option<u64>,
+    // more claims than matched receipts while some receipts matched no distributor, those may be this one's:
+    // the claims are not reported as mismatched, pass the mints of the unattributed receipts to check them
+    pub unmatched: bool,
+    // the distributor has a merkle root and the allowlist of that root was not given: the receipts were paid
+    // allowlist amounts, claimed rewards are not checked
+    pub rewards_unchecked: bool,
+    pub discrepancies: Vec<Discrepancy>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AuditReport {
+    pub distributors: Vec<DistributorReport>,
+    // receipts no candidate mint derives for any distributor of the dump
+    #[serde(serialize_with = "as_strings")]
+    pub unattributed_receipts: Vec<Pubkey>,
+    pub attestations: u64,
+    // program accounts of no known layout
+    #[serde(serialize_with = "as_strings")]
+    pub unknown_accounts: Vec<Pubkey>,
+}
+
+impl AuditReport {
+    pub fn discrepancy_count(&self) -> usize {
+        self.distributors.iter().map(|report| report.discrepancies.len()).sum::<usize>() + self.unattributed_receipts.len()
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// One line per distributor, discrepancies joined by `; `
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("distributor,version,reward_mint,reward_amount_total,amount_claimed,receipts,referral_paid,wallet_claims,vault_balance,unmatched,rewards_unchecked,discrepancies\n");
+        for report in &self.distributors {
+            let discrepancies: Vec<String> = report.discrepancies.iter().map(Discrepancy::to_string).collect();
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{},{},{},\"{}\"\n",
+                report.distributor,
+                report.version,
+                report.reward_mint,
+                report.reward_amount_total,
+                report.amount_claimed,
+                report.receipts,
+                report.referral_paid,
+                report.wallet_claims,
+                report.vault_balance.map(|balance| balance.to_string()).unwrap_or_default(),
+                report.unmatched,
+                report.rewards_unchecked,
+                discrepancies.join("; "),
+            ));
+        }
+        csv
+    }
+}
+
+// the mint of a metadata account, right after its key and update authority
+fn metadata_mint(account: &DumpedAccount) -> Option<Pubkey> {
+    if account.owner != spl_token_metadata::ID || account.data.first() != Some(&(MetadataKey::MetadataV1 as u8)) {
+        return None;
+    }
+    account.data.get(33..65).map(Pubkey::new)
+}
+
+/// Audits the distributors of the program in the dump. `mints` adds candidate mints for matching receipts, the mints of
+/// `allowlist` too, and its amounts are the rewards of the distributors whose merkle root is the allowlist's.
+pub fn audit(program_id: &Pubkey, accounts: &[DumpedAccount], mints: &[Pubkey], allowlist: &[(Pubkey, u64)]) -> AuditReport {
+    let mut distributors = BTreeMap::new();
+    let mut receipts = BTreeSet::new();
+    let mut referral_paid: BTreeMap<Pubkey, u64> = BTreeMap::new();
+    let mut wallet_claims: BTreeMap<Pubkey, u64> = BTreeMap::new();
+    let mut candidate_mints: BTreeSet<Pubkey> = mints.iter().chain(allowlist.iter().map(|(mint, _amount)| mint)).copied().collect();
+    let allowlist_root = MerkleTree::new(allowlist.to_vec()).ok().map(|tree| tree.root());
+    let allowlist: BTreeMap<Pubkey, u64> = allowlist.iter().copied().collect();
+    let mut vaults = BTreeMap::new();
+    let mut attestations = 0;
+    let mut unknown_accounts = vec![];
+
+    for account in accounts {
+        if account.owner == spl_token::ID {
+            if let Ok(token_account) = TokenAccount::unpack(&account.data) {
+                vaults.insert(account.pubkey, token_account);
+            }
+            continue;
+        }
+        if account.owner != *program_id {
+            candidate_mints.extend(metadata_mint(account));
+            continue;
+        }
+
+        // accounts from before versioning have no account key: receipts are told apart by their size, and distributors
+        // start with is_initialized, which reads as the distributor key
+        let data = &account.data[..];
+        let key = data.first().copied();
+        if data.len() == ProofOfReceiptAccount::V0_LEN || key == Some(AccountKey::ProofOfReceipt as u8) {
+            if let Ok(receipt) = decode_proof_of_receipt(data) {
+                if receipt.received_tokens {
+                    receipts.insert(account.pubkey);
+                }
+                continue;
+            }
+        } else if key == Some(AccountKey::Distributor as u8) {
+            if let Ok(distributor) = decode_distributor(data) {
+                distributors.insert(account.pubkey, distributor);
+                continue;
+            }
+        } else if key == Some(AccountKey::AttributeAttestation as u8) {
+            if let Ok(attestation) = decode_attestation(data) {
+                candidate_mints.insert(attestation.mint);
+                attestations += 1;
+                continue;
+            }
+        } else if key == Some(AccountKey::WalletClaimRecord as u8) {
+            if let Ok(record) = decode_wallet_claim_record(data) {
+                // a dump is untrusted input, sums saturate rather than overflow
+                let claims = wallet_claims.entry(record.distributor).or_default();
+                *claims = claims.saturating_add(record.claims);
+                continue;
+            }
+        } else if key == Some(AccountKey::ReferralRecord as u8) {
+            if let Ok(record) = decode_referral_record(data) {
+                let paid = referral_paid.entry(record.distributor).or_default();
+                *paid = paid.saturating_add(record.amount_earned);
+                continue;
+            }
+        }
+        unknown_accounts.push(account.pubkey);
+    }
+
+    let mut unattributed_receipts = receipts.clone();
+    let claimed_mints: Vec<Vec<Pubkey>> = distributors
+        .keys()
+        .map(|distributor_state| {
+            candidate_mints
+                .iter()
+                .filter(|mint| {
+                    let (receipt, _bump_seed) = find_receipt_address(program_id, mint, distributor_state);
+                    unattributed_receipts.remove(&receipt);
+                    receipts.contains(&receipt)
+                })
+                .copied()
+                .collect()
+        })
+        .collect();
+    let no_allowlist = BTreeMap::new();
+    let reports = distributors
+        .iter()
+        .zip(claimed_mints)
+        .map(|((distributor_state, distributor), claimed_mints)| {
+            let allowlist_amounts = match distributor.merkle_root {
+                None => Some(&no_allowlist),
+                Some(merkle_root) if Some(merkle_root) == allowlist_root => Some(&allowlist),
+                Some(_) => None,
+            };
+            distributor_report(
+                distributor_state,
+                distributor,
+                &claimed_mints,
+                allowlist_amounts,
+                !unattributed_receipts.is_empty(),
+                referral_paid.get(distributor_state).copied().unwrap_or_default(),
+                wallet_claims.get(distributor_state).copied().unwrap_or_default(),
+                vaults.get(&distributor.reward_token_account).map(|vault| vault.amount),
+            )
+        })
+        .collect();
+
+    AuditReport {
+        distributors: reports,
+        unattributed_receipts: unattributed_receipts.into_iter().collect(),
+        attestations,
+        unknown_accounts,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn distributor_report(
+    distributor_state: &Pubkey,
+    distributor: &DistributorAccount,
+    claimed_mints: &[Pubkey],
+    allowlist_amounts: Option<&BTreeMap<Pubkey, u64>>,
+    has_unattributed_receipts: bool,
+    referral_paid: u64,
+    wallet_claims: u64,
+    vault_balance: Option<u64>,
+) -> DistributorReport {
+    let mut discrepancies = vec![];
+    if distributor.amount_claimed > distributor.reward_amount_total {
+        discrepancies.push(Discrepancy::OverClaimed);
+    }
+
+    // every receipt was paid its allowlist amount, or else the original or the print reward, the referral bonuses
+    // come from the same pool
+    let receipts = claimed_mints.len() as u64;
+    let claimed_rewards = distributor.amount_claimed.saturating_sub(referral_paid);
+    let reward_min = distributor.reward_amount(false).min(distributor.reward_amount(true));
+    let reward_max = distributor.reward_amount(false).max(distributor.reward_amount(true));
+    let (mut expected_min, mut expected_max) = (0u64, 0u64);
+    for mint in claimed_mints {
+        // an amount of 0 is no amount, the claim pays the reward
+        let (min, max) = match allowlist_amounts.and_then(|amounts| amounts.get(mint)) {
+            Some(&amount) if amount > 0 => (amount, amount),
+            _ => (reward_min, reward_max),
+        };
+        expected_min = expected_min.saturating_add(min);
+        expected_max = expected_max.saturating_add(max);
+    }
+    let rewards_unchecked = allowlist_amounts.is_none();
+    let wallet_limit = distributor.max_claims_per_wallet.is_some();
+    // missing receipts may be among the unattributed ones, extra receipts cannot be explained that way
+    let unmatched = has_unattributed_receipts
+        && ((claimed_rewards > expected_max && !rewards_unchecked) || (wallet_limit && wallet_claims > receipts));
+    if !rewards_unchecked && (claimed_rewards < expected_min || (claimed_rewards > expected_max && !unmatched)) {
+        discrepancies.push(Discrepancy::ClaimedMismatch { claimed_rewards, expected_min, expected_max });
+    }
+
+    if let Some(vault_balance) = vault_balance {
+        let remaining = distributor.reward_amount_total.saturating_sub(distributor.amount_claimed);
+        if vault_balance < remaining {
+            discrepancies.push(Discrepancy::VaultUnderfunded { vault_balance, remaining });
+        }
+    }
+
+    if wallet_limit && (wallet_claims < receipts || (wallet_claims > receipts && !unmatched)) {
+        discrepancies.push(Discrepancy::WalletClaimsMismatch { wallet_claims, receipts });
+    }
+
+    DistributorReport {
+        distributor: *distributor_state,
+        version: distributor.version,
+        reward_mint: distributor.reward_mint,
+        reward_amount_total: distributor.reward_amount_total,
+        amount_claimed: distributor.amount_claimed,
+        receipts,
+        referral_paid,
+        wallet_claims,
+        vault_balance,
+        unmatched,
+        rewards_unchecked,
+        discrepancies,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use borsh::BorshSerialize;
+    use solana_nft_claim_tokens::{
+        state::{
+            DistributorAccountV0, EditionPolicy, ReferralRecordAccount, WalletClaimRecordAccount, DISTRIBUTOR_VERSION, PROOF_OF_RECEIPT_VERSION,
+            REFERRAL_RECORD_DATA_LENGTH, REFERRAL_RECORD_VERSION, WALLET_CLAIM_RECORD_DATA_LENGTH, WALLET_CLAIM_RECORD_VERSION,
+        },
+        utils::puffed_out_symbol,
+    };
+    use spl_token::state::AccountState;
+
+    const REWARD_AMOUNT_TOTAL: u64 = 1_000;
+    const REWARD_AMOUNT_PER_NFT: u64 = 100;
+
+    fn distributor(reward_token_account: Pubkey, amount_claimed: u64) -> DistributorAccount {
+        DistributorAccount {
+            key: AccountKey::Distributor,
+            version: DISTRIBUTOR_VERSION,
+            is_initialized: true,
+            authority: Pubkey::new_unique(),
+            reward_token_account,
+            reward_mint: Pubkey::new_unique(),
+            reward_amount_total: REWARD_AMOUNT_TOTAL,
+            reward_amount_per_nft: REWARD_AMOUNT_PER_NFT,
+            amount_claimed,
+            start_ts: 0,
+            collection_symbol: puffed_out_symbol("SYMBOL").unwrap(),
+            collection_creator: Pubkey::new_unique(),
+            reward_amount_per_print: REWARD_AMOUNT_PER_NFT,
+            edition_policy: EditionPolicy::Both,
+            trait_filter: None,
+            name_prefix: None,
+            uri_prefix: None,
+            max_claims_per_wallet: None,
+            claim_fee_lamports: 0,
+            fee_treasury: Pubkey::new_unique(),
+            referral_bps: 500,
+            distributor_id: 0,
+            authority_bump: 255,
+            merkle_root: None,
+            paused: false,
+        }
+    }
+
+    fn program_account<T: BorshSerialize>(program_id: &Pubkey, pubkey: Pubkey, state: &T, len: usize) -> DumpedAccount {
+        let mut data = vec![0u8; len];
+        state.serialize(&mut &mut data[..]).unwrap();
+        DumpedAccount { pubkey, owner: *program_id, data }
+    }
+
+    fn receipt(program_id: &Pubkey, mint: &Pubkey, distributor_state: &Pubkey) -> DumpedAccount {
+        let (pubkey, bump) = find_receipt_address(program_id, mint, distributor_state);
+        let receipt = ProofOfReceiptAccount { key: AccountKey::ProofOfReceipt, version: PROOF_OF_RECEIPT_VERSION, received_tokens: true, bump };
+        program_account(program_id, pubkey, &receipt, ProofOfReceiptAccount::LEN)
+    }
+
+    fn vault(pubkey: Pubkey, amount: u64) -> DumpedAccount {
+        let mut data = vec![0u8; TokenAccount::LEN];
+        TokenAccount { mint: Pubkey::new_unique(), owner: Pubkey::new_unique(), amount, state: AccountState::Initialized, ..TokenAccount::default() }
+            .pack_into_slice(&mut data);
+        DumpedAccount { pubkey, owner: spl_token::ID, data }
+    }
+
+    /// A distributor with two receipts and a 5 token referral bonus
+    fn campaign(program_id: &Pubkey, amount_claimed: u64, vault_balance: u64) -> (Pubkey, Vec<Pubkey>, Vec<DumpedAccount>) {
+        let distributor_state = Pubkey::new_unique();
+        let reward_token_account = Pubkey::new_unique();
+        let mints = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+        let referral_record = ReferralRecordAccount {
+            key: AccountKey::ReferralRecord,
+            version: REFERRAL_RECORD_VERSION,
+            is_initialized: true,
+            distributor: distributor_state,
+            referrer: Pubkey::new_unique(),
+            referred_claims: 1,
+            amount_earned: 5,
+        };
+        let accounts = vec![
+            program_account(program_id, distributor_state, &distributor(reward_token_account, amount_claimed), DistributorAccount::LEN),
+            receipt(program_id, &mints[0], &distributor_state),
+            receipt(program_id, &mints[1], &distributor_state),
+            program_account(program_id, Pubkey::new_unique(), &referral_record, REFERRAL_RECORD_DATA_LENGTH),
+            vault(reward_token_account, vault_balance),
+        ];
+        (distributor_state, mints, accounts)
+    }
+
+    #[test]
+    fn test_audit_reconciles() {
+        let program_id = Pubkey::new_unique();
+        let claimed = 2 * REWARD_AMOUNT_PER_NFT + 5;
+        let (distributor_state, mints, accounts) = campaign(&program_id, claimed, REWARD_AMOUNT_TOTAL - claimed);
+
+        let report = audit(&program_id, &accounts, &mints, &[]);
+        assert_eq!(report.discrepancy_count(), 0);
+        assert_eq!(report.distributors.len(), 1);
+        let distributor_report = &report.distributors[0];
+        assert_eq!(distributor_report.distributor, distributor_state);
+        assert_eq!(distributor_report.receipts, 2);
+        assert_eq!(distributor_report.referral_paid, 5);
+        assert_eq!(distributor_report.vault_balance, Some(REWARD_AMOUNT_TOTAL - claimed));
+        assert!(report.unknown_accounts.is_empty());
+    }
+
+    #[test]
+    fn test_audit_flags_discrepancies() {
+        let program_id = Pubkey::new_unique();
+        // a third claim was recorded but has no receipt, and the vault is short
+        let claimed = 3 * REWARD_AMOUNT_PER_NFT + 5;
+        let (_distributor_state, mints, mut accounts) = campaign(&program_id, claimed, 100);
+        let garbage = DumpedAccount { pubkey: Pubkey::new_unique(), owner: program_id, data: vec![9; 10] };
+        accounts.push(garbage.clone());
+
+        let report = audit(&program_id, &accounts, &mints, &[]);
+        assert_eq!(
+            report.distributors[0].discrepancies,
+            vec![
+                Discrepancy::ClaimedMismatch { claimed_rewards: 300, expected_min: 200, expected_max: 200 },
+                Discrepancy::VaultUnderfunded { vault_balance: 100, remaining: REWARD_AMOUNT_TOTAL - claimed },
+            ]
+        );
+        assert!(!report.distributors[0].unmatched);
+        assert_eq!(report.unknown_accounts, vec![garbage.pubkey]);
+        assert_eq!(report.discrepancy_count(), 2);
+
+        let csv = report.to_csv();
+        let row = csv.lines().nth(1).unwrap();
+        assert!(row.ends_with(",false,false,\"claimed rewards 300 but receipts were paid 200; vault balance 100 below remaining pool 695\""));
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["distributors"][0]["discrepancies"][0]["kind"], "claimed_mismatch");
+
+        // the missing receipt may be one no candidate mint derives, the claims are then unmatched rather than mismatched
+        let stray_receipt = receipt(&program_id, &Pubkey::new_unique(), &Pubkey::new_unique());
+        accounts.push(stray_receipt.clone());
+        let report = audit(&program_id, &accounts, &mints, &[]);
+        assert!(report.distributors[0].unmatched);
+        assert_eq!(
+            report.distributors[0].discrepancies,
+            vec![Discrepancy::VaultUnderfunded { vault_balance: 100, remaining: REWARD_AMOUNT_TOTAL - claimed }]
+        );
+        assert_eq!(report.unattributed_receipts, vec![stray_receipt.pubkey]);
+        assert_eq!(report.discrepancy_count(), 2);
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["distributors"][0]["unmatched"], true);
+        assert_eq!(json["unattributed_receipts"][0], stray_receipt.pubkey.to_string());
+    }
+
+    #[test]
+    fn test_audit_checks_allowlist_amounts() {
+        let program_id = Pubkey::new_unique();
+        // the first mint is allowlisted for 40, the second has no amount and is paid the reward
+        let claimed = 40 + REWARD_AMOUNT_PER_NFT + 5;
+        let (distributor_state, mints, mut accounts) = campaign(&program_id, claimed, REWARD_AMOUNT_TOTAL - claimed);
+        let allowlist = vec![(mints[0], 40), (mints[1], 0)];
+        let mut distributor = decode_distributor(&accounts[0].data).unwrap();
+        distributor.merkle_root = Some(MerkleTree::new(allowlist.clone()).unwrap().root());
+        accounts[0] = program_account(&program_id, distributor_state, &distributor, DistributorAccount::LEN);
+
+        // the allowlist mints are candidate mints too
+        let report = audit(&program_id, &accounts, &[], &allowlist);
+        assert_eq!(report.discrepancy_count(), 0);
+        assert_eq!(report.distributors[0].receipts, 2);
+        assert!(!report.distributors[0].rewards_unchecked);
+
+        // without the allowlist of the root the amounts are unknown, the rewards are not checked rather than mismatched
+        let other_allowlist = [(mints[0], 50), (mints[1], 0)];
+        for allowlist in [&[][..], &other_allowlist[..]] {
+            let report = audit(&program_id, &accounts, &mints, allowlist);
+            assert_eq!(report.discrepancy_count(), 0);
+            assert!(report.distributors[0].rewards_unchecked);
+        }
+        let csv = report.to_csv();
+        assert!(csv.lines().nth(1).unwrap().ends_with(",false,false,\"\""));
+    }
+
+    #[test]
+    fn test_audit_without_mints_reports_unmatched() {
+        let program_id = Pubkey::new_unique();
+        let claimed = 2 * REWARD_AMOUNT_PER_NFT + 5;
+        let (_distributor_state, _mints, accounts) = campaign(&program_id, claimed, REWARD_AMOUNT_TOTAL - claimed);
+
+        // receipts are only matched through known mints, without them the claims cannot be checked
+        let report = audit(&program_id, &accounts, &[], &[]);
+        assert_eq!(report.distributors[0].receipts, 0);
+        assert!(report.distributors[0].unmatched);
+        assert!(report.distributors[0].discrepancies.is_empty());
+        assert_eq!(report.unattributed_receipts.len(), 2);
+    }
+
+    #[test]
+    fn test_audit_record_sums_saturate() {
+        let program_id = Pubkey::new_unique();
+        let (distributor_state, mints, mut accounts) = campaign(&program_id, 2 * REWARD_AMOUNT_PER_NFT + 5, 0);
+        for _ in 0..2 {
+            let referral_record = ReferralRecordAccount {
+                key: AccountKey::ReferralRecord,
+                version: REFERRAL_RECORD_VERSION,
+                is_initialized: true,
+                distributor: distributor_state,
+                referrer: Pubkey::new_unique(),
+                referred_claims: u64::MAX,
+                amount_earned: u64::MAX,
+            };
+            accounts.push(program_account(&program_id, Pubkey::new_unique(), &referral_record, REFERRAL_RECORD_DATA_LENGTH));
+            let wallet_claim_record = WalletClaimRecordAccount {
+                key: AccountKey::WalletClaimRecord,
+                version: WALLET_CLAIM_RECORD_VERSION,
+                is_initialized: true,
+                distributor: distributor_state,
+                claimant: Pubkey::new_unique(),
+                claims: u64::MAX,
+            };
+            accounts.push(program_account(&program_id, Pubkey::new_unique(), &wallet_claim_record, WALLET_CLAIM_RECORD_DATA_LENGTH));
+        }
+
+        let report = audit(&program_id, &accounts, &mints, &[]);
+        assert_eq!(report.distributors[0].referral_paid, u64::MAX);
+        assert_eq!(report.distributors[0].wallet_claims, u64::MAX);
+    }
+
+    #[test]
+    fn test_audit_reads_accounts_from_before_versioning() {
+        let program_id = Pubkey::new_unique();
+        let distributor_state = Pubkey::new_unique();
+        let reward_token_account = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let distributor = DistributorAccountV0 {
+            is_initialized: true,
+            authority: Pubkey::new_unique(),
+            reward_token_account,
+            reward_mint: Pubkey::new_unique(),
+            reward_amount_total: REWARD_AMOUNT_TOTAL,
+            reward_amount_per_nft: REWARD_AMOUNT_PER_NFT,
+            amount_claimed: REWARD_AMOUNT_PER_NFT,
+            start_ts: 0,
+            collection_symbol: "SYMBOL".to_string(),
+            collection_creator: Pubkey::new_unique(),
+        };
+        // a version 0 receipt is a single received_tokens byte, which reads as the distributor key
+        let receipt = DumpedAccount { pubkey: find_receipt_address(&program_id, &mint, &distributor_state).0, owner: program_id, data: vec![1] };
+        let accounts = vec![
+            program_account(&program_id, distributor_state, &distributor, DistributorAccountV0::LEN),
+            receipt,
+            vault(reward_token_account, REWARD_AMOUNT_TOTAL - REWARD_AMOUNT_PER_NFT),
+        ];
+
+        let report = audit(&program_id, &accounts, &[mint], &[]);
+        assert_eq!(report.discrepancy_count(), 0);
+        assert_eq!(report.distributors[0].distributor, distributor_state);
+        assert_eq!(report.distributors[0].receipts, 1);
+        assert!(report.unknown_accounts.is_empty());
+    }
+}
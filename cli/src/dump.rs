@@ -0,0 +1,127 @@
+//! Account dumps: `solana account --output json` files and `getProgramAccounts` RPC responses.
+
+use serde::Deserialize;
+use solana_program::pubkey::Pubkey;
+use std::{fs, io, path::Path, str::FromStr};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum DumpError {
+    #[error("cannot read dump: {0}")]
+    Io(#[from] io::Error),
+    #[error("invalid dump: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("invalid pubkey {0}")]
+    InvalidPubkey(String),
+    #[error("unsupported account data encoding {0}, dump with base64")]
+    UnsupportedEncoding(String),
+    #[error("invalid base64 account data: {0}")]
+    InvalidData(#[from] base64::DecodeError),
+}
+
+/// An account of a dump
+#[derive(Debug, Clone, PartialEq)]
+pub struct DumpedAccount {
+    pub pubkey: Pubkey,
+    pub owner: Pubkey,
+    pub data: Vec<u8>,
+}
+
+// the `{"pubkey", "account"}` objects of `solana account --output json` and RPC `getProgramAccounts`
+#[derive(Deserialize)]
+struct UiKeyedAccount {
+    pubkey: String,
+    account: UiAccount,
+}
+
+#[derive(Deserialize)]
+struct UiAccount {
+    owner: String,
+    // [data, encoding]
+    data: (String, String),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Dump {
+    RpcResponse { result: Vec<UiKeyedAccount> },
+    Accounts(Vec<UiKeyedAccount>),
+    Account(UiKeyedAccount),
+}
+
+fn parse_pubkey(pubkey: &str) -> Result<Pubkey, DumpError> {
+    Pubkey::from_str(pubkey).map_err(|_| DumpError::InvalidPubkey(pubkey.to_string()))
+}
+
+impl DumpedAccount {
+    fn from_ui(keyed_account: UiKeyedAccount) -> Result<Self, DumpError> {
+        let (data, encoding) = keyed_account.account.data;
+        if encoding != "base64" {
+            return Err(DumpError::UnsupportedEncoding(encoding));
+        }
+        Ok(DumpedAccount {
+            pubkey: parse_pubkey(&keyed_account.pubkey)?,
+            owner: parse_pubkey(&keyed_account.account.owner)?,
+            data: base64::decode(data)?,
+        })
+    }
+}
+
+/// Parses a JSON dump: one `solana account --output json` account, an array of them,
+/// or a `getProgramAccounts` RPC response with base64 encoded data
+pub fn parse_dump(json: &str) -> Result<Vec<DumpedAccount>, DumpError> {
+    let accounts = match serde_json::from_str(json)? {
+        Dump::RpcResponse { result } => result,
+        Dump::Accounts(accounts) => accounts,
+        Dump::Account(account) => vec![account],
+    };
+    accounts.into_iter().map(DumpedAccount::from_ui).collect()
+}
+
+/// Loads a JSON dump file, or every `.json` dump of a directory
+pub fn load_dump(path: &Path) -> Result<Vec<DumpedAccount>, DumpError> {
+    if !path.is_dir() {
+        return parse_dump(&fs::read_to_string(path)?);
+    }
+    let mut accounts = vec![];
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+        if path.extension().map_or(false, |extension| extension == "json") {
+            accounts.extend(parse_dump(&fs::read_to_string(&path)?)?);
+        }
+    }
+    Ok(accounts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(data: Vec<u8>) -> DumpedAccount {
+        DumpedAccount { pubkey: Pubkey::new_unique(), owner: Pubkey::new_unique(), data }
+    }
+
+    fn keyed_account_json(account: &DumpedAccount) -> String {
+        format!(
+            r#"{{"pubkey":"{}","account":{{"lamports":5616720,"data":["{}","base64"],"owner":"{}","executable":false,"rentEpoch":0}}}}"#,
+            account.pubkey,
+            base64::encode(&account.data),
+            account.owner
+        )
+    }
+
+    #[test]
+    fn test_parse_dump_formats() {
+        let first = account(vec![1, 2, 3]);
+        let second = account(vec![4; 64]);
+
+        assert_eq!(parse_dump(&keyed_account_json(&first)).unwrap(), vec![first.clone()]);
+        let array = format!("[{},{}]", keyed_account_json(&first), keyed_account_json(&second));
+        assert_eq!(parse_dump(&array).unwrap(), vec![first.clone(), second.clone()]);
+        let rpc_response = format!(r#"{{"jsonrpc":"2.0","result":{},"id":1}}"#, array);
+        assert_eq!(parse_dump(&rpc_response).unwrap(), vec![first, second]);
+
+        let base58 = r#"{"pubkey":"11111111111111111111111111111111","account":{"data":["1","base58"],"owner":"11111111111111111111111111111111"}}"#;
+        assert!(matches!(parse_dump(base58), Err(DumpError::UnsupportedEncoding(_))));
+    }
+}
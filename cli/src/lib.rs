@@ -0,0 +1,6 @@
+//! Off-chain tools for the NFT claim tokens program, used by `claim-tokens-cli`.
+
+pub mod audit;
+pub mod dump;
+pub mod merkle;
+pub mod snapshot;
@@ -0,0 +1,1055 @@
+//! Command line client to create, fund, inspect, pause, close, claim from and migrate NFT claim token distributors,
+//! to snapshot the NFTs eligible for a campaign, to build merkle allowlists and to audit claims.
+//! It talks to the banks server of a cluster, which `solana-test-validator` serves next to its RPC port.
+
+use clap::{crate_description, crate_name, crate_version, App, AppSettings, Arg, ArgMatches, SubCommand};
+use solana_banks_client::BanksClient;
+use solana_nft_claim_tokens::{
+    client,
+    instruction::{self, CreateTokenDistributorArgs},
+    state::{AccountKey, DistributorAccount, EditionPolicy, TraitFilter, DISTRIBUTOR_VERSION},
+    utils::{hash_trait, puffed_out_symbol, trim_null_padding},
+};
+use claim_tokens_cli::{audit, dump, merkle, snapshot};
+use solana_program::{hash::Hash, instruction::Instruction, message::Message, pubkey::Pubkey};
+use solana_sdk::{
+    signature::{read_keypair_file, Keypair, Signer},
+    transaction::Transaction,
+};
+use std::{error::Error, fmt::Display, path::Path, process::exit, str::FromStr};
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+const DEFAULT_URL: &str = "127.0.0.1:8901";
+
+/// A transaction signer, only its public key is needed to emit an unsigned transaction
+enum SignerArg {
+    Keypair(Keypair),
+    Pubkey(Pubkey),
+}
+
+impl SignerArg {
+    fn pubkey(&self) -> Pubkey {
+        match self {
+            SignerArg::Keypair(keypair) => keypair.pubkey(),
+            SignerArg::Pubkey(pubkey) => *pubkey,
+        }
+    }
+}
+
+struct Config {
+    program_id: Pubkey,
+    fee_payer: SignerArg,
+    // print transactions for multisig signing instead of sending them
+    unsigned: bool,
+}
+
+/// Reads a keypair file, or takes a bare public key when the transaction is not signed here
+fn parse_signer(value: &str, unsigned: bool) -> CliResult<SignerArg> {
+    if unsigned {
+        if let Ok(pubkey) = Pubkey::from_str(value) {
+            return Ok(SignerArg::Pubkey(pubkey));
+        }
+    }
+    read_keypair_file(value)
+        .map(SignerArg::Keypair)
+        .map_err(|err| format!("cannot read keypair file {}: {}", value, err).into())
+}
+
+fn value_of<T>(matches: &ArgMatches, name: &str) -> CliResult<Option<T>>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    matches
+        .value_of(name)
+        .map(|value| value.parse::<T>().map_err(|err| format!("invalid --{} {}: {}", name, value, err).into()))
+        .transpose()
+}
+
+fn required_value_of<T>(matches: &ArgMatches, name: &str) -> CliResult<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value_of(matches, name)?.ok_or_else(|| format!("missing --{}", name).into())
+}
+
+/// Signer named by the argument, the fee payer if it is not given
+fn signer_of(matches: &ArgMatches, name: &str, config: &Config) -> CliResult<Option<SignerArg>> {
+    matches.value_of(name).map(|value| parse_signer(value, config.unsigned)).transpose()
+}
+
+fn parse_edition_policy(matches: &ArgMatches) -> CliResult<EditionPolicy> {
+    if let Some(parent_mint) = value_of(matches, "parent_mint")? {
+        return Ok(EditionPolicy::PrintsWithParent(parent_mint));
+    }
+    match matches.value_of("edition_policy").unwrap_or("both") {
+        "originals" => Ok(EditionPolicy::OriginalsOnly),
+        "prints" => Ok(EditionPolicy::PrintsOnly),
+        "both" => Ok(EditionPolicy::Both),
+        other => Err(format!("invalid --edition-policy {}", other).into()),
+    }
+}
+
+fn parse_trait_filter(matches: &ArgMatches) -> CliResult<Option<TraitFilter>> {
+    let oracle = match value_of(matches, "trait_oracle")? {
+        Some(oracle) => oracle,
+        None => return Ok(None),
+    };
+    let required_trait = matches.value_of("trait").ok_or("missing --trait")?;
+    let (trait_type, value) = required_trait
+        .split_once(':')
+        .ok_or_else(|| format!("invalid --trait {}, expected TYPE:VALUE", required_trait))?;
+    Ok(Some(TraitFilter { oracle, trait_hash: hash_trait(trait_type, value) }))
+}
+
+/// Fetches and decodes the distributor, checking it belongs to the program
+async fn get_distributor(banks_client: &mut BanksClient, config: &Config, address: &Pubkey) -> CliResult<DistributorAccount> {
+    let account = banks_client
+        .get_account(*address)
+        .await?
+        .ok_or_else(|| format!("distributor {} not found", address))?;
+    if account.owner != config.program_id {
+        return Err(format!("{} is not owned by program {}", address, config.program_id).into());
+    }
+    Ok(client::decode_distributor(&account.data)?)
+}
+
+/// Signs and sends the instructions and returns the signature, or returns the base64 encoded unsigned
+/// transaction with --unsigned
+async fn send(
+    banks_client: &mut BanksClient,
+    config: &Config,
+    instructions: &[Instruction],
+    signers: &[&SignerArg],
+) -> CliResult<String> {
+    let recent_blockhash = banks_client.get_latest_blockhash().await?;
+    let mut message = Message::new(instructions, Some(&config.fee_payer.pubkey()));
+    message.recent_blockhash = recent_blockhash;
+    let mut transaction = Transaction::new_unsigned(message);
+    if config.unsigned {
+        return Ok(base64::encode(bincode::serialize(&transaction)?));
+    }
+
+    let mut keypairs: Vec<&Keypair> = vec![];
+    for signer in std::iter::once(&config.fee_payer).chain(signers.iter().copied()) {
+        match signer {
+            SignerArg::Keypair(keypair) => {
+                if !keypairs.iter().any(|signing| signing.pubkey() == keypair.pubkey()) {
+                    keypairs.push(keypair);
+                }
+            }
+            SignerArg::Pubkey(pubkey) => return Err(format!("a keypair file is needed to sign for {}", pubkey).into()),
+        }
+    }
+    transaction.try_sign(&keypairs, recent_blockhash)?;
+    let signature = transaction.signatures[0];
+    banks_client.process_transaction(transaction).await?;
+    Ok(signature.to_string())
+}
+
+async fn command_create(banks_client: &mut BanksClient, config: &Config, matches: &ArgMatches<'_>) -> CliResult<String> {
+    let authority = signer_of(matches, "authority", config)?;
+    let authority = authority.as_ref().unwrap_or(&config.fee_payer);
+    let distributor_id = value_of(matches, "distributor_id")?.unwrap_or(0);
+    let reward_amount_per_nft = required_value_of(matches, "reward_per_nft")?;
+    let args = CreateTokenDistributorArgs {
+        distributor_id,
+        reward_amount_total: required_value_of(matches, "reward_total")?,
+        reward_amount_per_nft,
+        start_ts: value_of(matches, "start_ts")?.unwrap_or(0),
+        collection_symbol: required_value_of(matches, "symbol")?,
+        reward_amount_per_print: value_of(matches, "reward_per_print")?.unwrap_or(reward_amount_per_nft),
+        edition_policy: parse_edition_policy(matches)?,
+        trait_filter: parse_trait_filter(matches)?,
+        name_prefix: value_of(matches, "name_prefix")?,
+        uri_prefix: value_of(matches, "uri_prefix")?,
+        max_claims_per_wallet: value_of(matches, "max_claims_per_wallet")?,
+        claim_fee_lamports: value_of(matches, "claim_fee_lamports")?.unwrap_or(0),
+        fee_treasury: value_of(matches, "fee_treasury")?.unwrap_or_else(|| authority.pubkey()),
+        referral_bps: value_of(matches, "referral_bps")?.unwrap_or(0),
+        merkle_root: value_of(matches, "merkle_root")?.map(Hash::to_bytes),
+    };
+    let instruction = client::create_distributor(
+        &config.program_id,
+        &authority.pubkey(),
+        &required_value_of(matches, "reward_token_account")?,
+        &required_value_of(matches, "collection_creator")?,
+        args,
+    );
+    let (distributor_state, _bump_seed) =
+        client::find_distributor_state_address(&config.program_id, &authority.pubkey(), distributor_id);
+    let output = send(banks_client, config, &[instruction], &[authority]).await?;
+    Ok(format!("Distributor: {}\n{}", distributor_state, output))
+}
+
+async fn command_fund(banks_client: &mut BanksClient, config: &Config, matches: &ArgMatches<'_>) -> CliResult<String> {
+    let distributor_state = required_value_of(matches, "distributor")?;
+    let distributor = get_distributor(banks_client, config, &distributor_state).await?;
+    let owner = signer_of(matches, "owner", config)?;
+    let owner = owner.as_ref().unwrap_or(&config.fee_payer);
+    let instruction = spl_token::instruction::transfer(
+        &spl_token::ID,
+        &required_value_of(matches, "source")?,
+        &distributor.reward_token_account,
+        &owner.pubkey(),
+        &[],
+        required_value_of(matches, "amount")?,
+    )?;
+    send(banks_client, config, &[instruction], &[owner]).await
+}
+
+/// Human readable distributor, with the addresses derived from it
+fn format_distributor(program_id: &Pubkey, distributor_state: &Pubkey, distributor: &DistributorAccount) -> String {
+    let symbol = String::from_utf8_lossy(&distributor.collection_symbol);
+    format!(
+        "Distributor: {}\nVault authority: {}\nCollection symbol: {}\nRemaining: {}\n{:#?}",
+        distributor_state,
+        client::find_distributor_authority(program_id, distributor_state).0,
+        trim_null_padding(&symbol),
+        distributor.reward_amount_total.saturating_sub(distributor.amount_claimed),
+        distributor,
+    )
+}
+
+async fn command_inspect(banks_client: &mut BanksClient, config: &Config, matches: &ArgMatches<'_>) -> CliResult<String> {
+    let distributor_state = required_value_of(matches, "distributor")?;
+    let distributor = get_distributor(banks_client, config, &distributor_state).await?;
+    Ok(format_distributor(&config.program_id, &distributor_state, &distributor))
+}
+
+async fn command_claim(banks_client: &mut BanksClient, config: &Config, matches: &ArgMatches<'_>) -> CliResult<String> {
+    let distributor_state = required_value_of(matches, "distributor")?;
+    let distributor = get_distributor(banks_client, config, &distributor_state).await?;
+    let claimant = signer_of(matches, "claimant", config)?;
+    let claimant = claimant.as_ref().unwrap_or(&config.fee_payer);
+    let referral = match value_of(matches, "referrer")? {
+        Some(referrer) => Some((referrer, required_value_of(matches, "referrer_reward_account")?)),
+        None => None,
+    };
+    let nft_mint = required_value_of(matches, "nft_mint")?;
+    let allowlist_entry = match matches.value_of("proof") {
+        Some(path) => Some(read_allowlist_entry(path, &distributor, &nft_mint)?),
+        None => None,
+    };
+    let instruction = client::claim_tokens(
+        &config.program_id,
+        &distributor_state,
+        &distributor,
+        &claimant.pubkey(),
+        &required_value_of(matches, "reward_account")?,
+        &required_value_of(matches, "nft_account")?,
+        &nft_mint,
+        referral,
+        allowlist_entry,
+    );
+    send(banks_client, config, &[instruction], &[claimant]).await
+}
+
+/// Amount and proof of a proof file written by merkle, which must be for the NFT mint and the distributor allowlist
+fn read_allowlist_entry(path: &str, distributor: &DistributorAccount, nft_mint: &Pubkey) -> CliResult<(u64, Vec<[u8; 32]>)> {
+    let proof_file: merkle::ProofFile = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    let (mint, root, proof) = proof_file.decode().ok_or_else(|| format!("invalid proof file {}", path))?;
+    if mint != *nft_mint {
+        return Err(format!("proof file {} is for mint {}", path, mint).into());
+    }
+    if distributor.merkle_root != Some(root) {
+        return Err(format!("proof file {} is not for the distributor allowlist", path).into());
+    }
+    Ok((proof_file.amount, proof))
+}
+
+/// The distributor create would initialize with the collection arguments, only its collection rules are set
+fn distributor_from_args(matches: &ArgMatches) -> CliResult<DistributorAccount> {
+    let reward_amount_per_nft = required_value_of(matches, "reward_per_nft")?;
+    Ok(DistributorAccount {
+        key: AccountKey::Distributor,
+        version: DISTRIBUTOR_VERSION,
+        is_initialized: true,
+        authority: Pubkey::default(),
+        reward_token_account: Pubkey::default(),
+        reward_mint: Pubkey::default(),
+        reward_amount_total: 0,
+        reward_amount_per_nft,
+        amount_claimed: 0,
+        start_ts: 0,
+        collection_symbol: puffed_out_symbol(matches.value_of("symbol").ok_or("missing --symbol")?)?,
+        collection_creator: required_value_of(matches, "collection_creator")?,
+        reward_amount_per_print: value_of(matches, "reward_per_print")?.unwrap_or(reward_amount_per_nft),
+        edition_policy: parse_edition_policy(matches)?,
+        trait_filter: parse_trait_filter(matches)?,
+        name_prefix: value_of(matches, "name_prefix")?,
+        uri_prefix: value_of(matches, "uri_prefix")?,
+        max_claims_per_wallet: None,
+        claim_fee_lamports: 0,
+        fee_treasury: Pubkey::default(),
+        referral_bps: 0,
+        distributor_id: 0,
+        authority_bump: 0,
+        merkle_root: value_of(matches, "merkle_root")?.map(Hash::to_bytes),
+        paused: false,
+    })
+}
+
+fn command_snapshot(matches: &ArgMatches) -> CliResult<String> {
+    let mut distributor = distributor_from_args(matches)?;
+    let allowlist = match matches.value_of("allowlist") {
+        Some(path) => merkle::parse_csv(&std::fs::read_to_string(path)?)?,
+        None => vec![],
+    };
+    // the allowlist decides which mints claim and what they are paid, the root alone cannot
+    if matches.is_present("allowlist") {
+        let root = merkle::MerkleTree::new(allowlist.clone())?.root();
+        if distributor.merkle_root.map_or(false, |merkle_root| merkle_root != root) {
+            return Err("the allowlist does not have the root --merkle-root".into());
+        }
+        distributor.merkle_root = Some(root);
+    } else if distributor.merkle_root.is_some() {
+        return Err("pass the allowlist of --merkle-root with --allowlist".into());
+    }
+    let accounts = dump::load_dump(Path::new(matches.value_of("dump").ok_or("missing --dump")?))?;
+    let snapshot = snapshot::snapshot(&distributor, &accounts, &allowlist);
+    if !snapshot.unknown_edition.is_empty() {
+        eprintln!("{} NFTs have no edition account in the dump and were left out:", snapshot.unknown_edition.len());
+        for mint in &snapshot.unknown_edition {
+            eprintln!("{}", mint);
+        }
+    }
+    let mut output = String::new();
+    for nft in &snapshot.eligible {
+        output.push_str(&format!("{} {} {}\n", nft.mint, if nft.is_print { "print" } else { "original" }, nft.reward));
+    }
+    output.push_str(&format!("Eligible: {}\nRequired total: {}", snapshot.eligible.len(), snapshot.required_total));
+    Ok(output)
+}
+
+fn command_merkle(matches: &ArgMatches) -> CliResult<String> {
+    let csv = std::fs::read_to_string(matches.value_of("csv").ok_or("missing --csv")?)?;
+    let tree = merkle::MerkleTree::new(merkle::parse_csv(&csv)?)?;
+    merkle::write_proofs(&tree, Path::new(matches.value_of("out_dir").ok_or("missing --out-dir")?))?;
+    Ok(format!(
+        "Root: {}\nRoot bytes: {:?}\nEntries: {}",
+        Hash::new_from_array(tree.root()),
+        tree.root(),
+        tree.entries().len()
+    ))
+}
+
+/// Mints at the start of each line, e.g. snapshot output or an allowlist CSV, other lines are skipped
+fn parse_mints(text: &str) -> Vec<Pubkey> {
+    text.lines()
+        .filter_map(|line| line.split(|c: char| c == ',' || c.is_whitespace()).next())
+        .filter_map(|field| Pubkey::from_str(field).ok())
+        .collect()
+}
+
+fn command_audit(matches: &ArgMatches) -> CliResult<String> {
+    let program_id = required_value_of(matches, "program_id")?;
+    let accounts = dump::load_dump(Path::new(matches.value_of("dump").ok_or("missing --dump")?))?;
+    let mints = match matches.value_of("mints") {
+        Some(path) => parse_mints(&std::fs::read_to_string(path)?),
+        None => vec![],
+    };
+    let allowlist = match matches.value_of("allowlist") {
+        Some(path) => merkle::parse_csv(&std::fs::read_to_string(path)?)?,
+        None => vec![],
+    };
+    let report = audit::audit(&program_id, &accounts, &mints, &allowlist);
+    if !report.unattributed_receipts.is_empty() {
+        eprintln!("{} receipts matched no distributor, pass their mints with --mints", report.unattributed_receipts.len());
+    }
+    let rewards_unchecked = report.distributors.iter().filter(|report| report.rewards_unchecked).count();
+    if rewards_unchecked > 0 {
+        eprintln!("{} distributors have a merkle root, pass the allowlist of the root with --allowlist to check their rewards", rewards_unchecked);
+    }
+    eprintln!("{} discrepancies", report.discrepancy_count());
+    match matches.value_of("format") {
+        Some("csv") => Ok(report.to_csv().trim_end().to_string()),
+        _ => Ok(report.to_json()?),
+    }
+}
+
+async fn command_migrate(banks_client: &mut BanksClient, config: &Config, matches: &ArgMatches<'_>) -> CliResult<String> {
+    let authority = signer_of(matches, "authority", config)?;
+    let authority = authority.as_ref().unwrap_or(&config.fee_payer);
+    // distributors created before version 1 are not at the distributor state PDA, they are given by address
+    let instruction = match value_of(matches, "distributor")? {
+        Some(distributor_state) => instruction::migrate_distributor(config.program_id, authority.pubkey(), distributor_state),
+        None => client::migrate_distributor(
+            &config.program_id,
+            &authority.pubkey(),
+            value_of(matches, "distributor_id")?.unwrap_or(0),
+        ),
+    };
+    send(banks_client, config, &[instruction], &[authority]).await
+}
+
+/// The authority signer of a distributor, which must be the distributor authority
+fn distributor_authority(matches: &ArgMatches, config: &Config, distributor: &DistributorAccount) -> CliResult<Option<SignerArg>> {
+    let authority = signer_of(matches, "authority", config)?;
+    let authority_pubkey = authority.as_ref().unwrap_or(&config.fee_payer).pubkey();
+    if authority_pubkey != distributor.authority {
+        return Err(format!("{} is not the distributor authority {}", authority_pubkey, distributor.authority).into());
+    }
+    Ok(authority)
+}
+
+async fn command_set_paused(banks_client: &mut BanksClient, config: &Config, matches: &ArgMatches<'_>, paused: bool) -> CliResult<String> {
+    let distributor_state = required_value_of(matches, "distributor")?;
+    let distributor = get_distributor(banks_client, config, &distributor_state).await?;
+    let authority = distributor_authority(matches, config, &distributor)?;
+    let authority = authority.as_ref().unwrap_or(&config.fee_payer);
+    let instruction = client::set_paused(&config.program_id, &distributor_state, &distributor, paused);
+    send(banks_client, config, &[instruction], &[authority]).await
+}
+
+async fn command_close(banks_client: &mut BanksClient, config: &Config, matches: &ArgMatches<'_>) -> CliResult<String> {
+    let distributor_state = required_value_of(matches, "distributor")?;
+    let distributor = get_distributor(banks_client, config, &distributor_state).await?;
+    let authority = distributor_authority(matches, config, &distributor)?;
+    let authority = authority.as_ref().unwrap_or(&config.fee_payer);
+    let instruction = client::close_distributor(
+        &config.program_id,
+        &distributor_state,
+        &distributor,
+        &required_value_of(matches, "destination")?,
+    );
+    send(banks_client, config, &[instruction], &[authority]).await
+}
+
+fn pubkey_arg<'a, 'b>(name: &'a str, long: &'a str, help: &'a str) -> Arg<'a, 'b> {
+    Arg::with_name(name).long(long).value_name("PUBKEY").takes_value(true).help(help)
+}
+
+fn signer_arg<'a, 'b>(name: &'a str, long: &'a str, help: &'a str) -> Arg<'a, 'b> {
+    Arg::with_name(name).long(long).value_name("KEYPAIR").takes_value(true).help(help)
+}
+
+fn value_arg<'a, 'b>(name: &'a str, long: &'a str, value_name: &'a str, help: &'a str) -> Arg<'a, 'b> {
+    Arg::with_name(name).long(long).value_name(value_name).takes_value(true).help(help)
+}
+
+/// Arguments of the collection rules, shared by create and snapshot
+fn collection_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        pubkey_arg("collection_creator", "collection-creator", "First verified creator of the collection").required(true),
+        value_arg("symbol", "symbol", "SYMBOL", "Collection symbol").required(true),
+        value_arg("reward_per_nft", "reward-per-nft", "AMOUNT", "Reward per original").required(true),
+        value_arg("reward_per_print", "reward-per-print", "AMOUNT", "Reward per print [default: reward per nft]"),
+        value_arg("edition_policy", "edition-policy", "POLICY", "Editions that can claim [default: both]")
+            .possible_values(&["originals", "prints", "both"]),
+        pubkey_arg("parent_mint", "parent-mint", "Only prints of this master edition can claim").conflicts_with("edition_policy"),
+        pubkey_arg("trait_oracle", "trait-oracle", "Oracle attesting the required trait").requires("trait"),
+        value_arg("trait", "trait", "TYPE:VALUE", "Trait required to claim").requires("trait_oracle"),
+        value_arg("name_prefix", "name-prefix", "PREFIX", "Metadata name must start with this"),
+        value_arg("uri_prefix", "uri-prefix", "PREFIX", "Metadata uri must start with this (ending with '/')"),
+    ]
+}
+
+fn app<'a, 'b>() -> App<'a, 'b> {
+    let distributor = pubkey_arg("distributor", "distributor", "Distributor state account").required(true);
+    App::new(crate_name!())
+        .about(crate_description!())
+        .version(crate_version!())
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(pubkey_arg("program_id", "program-id", "Claim tokens program").global(true))
+        .arg(value_arg("url", "url", "HOST:PORT", "Banks server of the cluster").global(true).default_value(DEFAULT_URL))
+        .arg(signer_arg("fee_payer", "fee-payer", "Fee payer, also the default for every other signer").global(true))
+        .arg(
+            Arg::with_name("unsigned")
+                .long("unsigned")
+                .global(true)
+                .help("Print the base64 encoded unsigned transaction instead of sending it, signers may be given as pubkeys"),
+        )
+        .subcommand(
+            SubCommand::with_name("create")
+                .about("Create a distributor")
+                .arg(signer_arg("authority", "authority", "Distributor authority"))
+                .arg(value_arg("distributor_id", "distributor-id", "ID", "Distinguishes the authority's distributors [default: 0]"))
+                .arg(pubkey_arg("reward_token_account", "reward-token-account", "Token account to pay rewards from").required(true))
+                .arg(value_arg("reward_total", "reward-total", "AMOUNT", "Reward pool size").required(true))
+                .args(&collection_args())
+                .arg(value_arg("max_claims_per_wallet", "max-claims-per-wallet", "COUNT", "Claims allowed per wallet"))
+                .arg(value_arg("claim_fee_lamports", "claim-fee-lamports", "LAMPORTS", "Fee paid on every claim [default: 0]"))
+                .arg(pubkey_arg("fee_treasury", "fee-treasury", "Receives the claim fee [default: authority]"))
+                .arg(value_arg("referral_bps", "referral-bps", "BPS", "Referrer bonus in basis points [default: 0]"))
+                .arg(value_arg("merkle_root", "merkle-root", "HASH", "Root printed by merkle, only the allowlisted mints can claim")),
+        )
+        .subcommand(
+            SubCommand::with_name("fund")
+                .about("Transfer reward tokens to a distributor")
+                .arg(distributor.clone())
+                .arg(pubkey_arg("source", "source", "Token account to transfer from").required(true))
+                .arg(signer_arg("owner", "owner", "Owner of the source account"))
+                .arg(value_arg("amount", "amount", "AMOUNT", "Amount to transfer").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("inspect")
+                .about("Print a distributor")
+                .arg(distributor.clone()),
+        )
+        .subcommand(
+            SubCommand::with_name("pause")
+                .about("Pause claims from a distributor")
+                .arg(distributor.clone())
+                .arg(signer_arg("authority", "authority", "Distributor authority")),
+        )
+        .subcommand(
+            SubCommand::with_name("resume")
+                .about("Resume claims from a paused distributor")
+                .arg(distributor.clone())
+                .arg(signer_arg("authority", "authority", "Distributor authority")),
+        )
+        .subcommand(
+            SubCommand::with_name("close")
+                .about("Close a distributor and its vault, returning the tokens left to the authority")
+                .arg(distributor.clone())
+                .arg(signer_arg("authority", "authority", "Distributor authority"))
+                .arg(pubkey_arg("destination", "destination", "Token account receiving the tokens left in the vault").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("claim")
+                .about("Claim tokens for an NFT")
+                .arg(distributor)
+                .arg(signer_arg("claimant", "claimant", "Owner of the NFT"))
+                .arg(pubkey_arg("nft_mint", "nft-mint", "Mint of the NFT").required(true))
+                .arg(pubkey_arg("nft_account", "nft-account", "Token account holding the NFT").required(true))
+                .arg(pubkey_arg("reward_account", "reward-account", "Token account receiving the reward").required(true))
+                .arg(pubkey_arg("referrer", "referrer", "Wallet that referred the claimant").requires("referrer_reward_account"))
+                .arg(pubkey_arg("referrer_reward_account", "referrer-reward-account", "Token account receiving the referral bonus").requires("referrer"))
+                .arg(value_arg("proof", "proof", "PATH", "Proof file of the NFT mint written by merkle, for distributors with an allowlist")),
+        )
+        .subcommand(
+            SubCommand::with_name("snapshot")
+                .about("List the NFTs of a metadata dump eligible under the collection rules, and the reward total they need")
+                .arg(value_arg("dump", "dump", "PATH", "JSON dump of metadata accounts, or a directory of them").required(true))
+                .args(&collection_args())
+                .arg(value_arg("merkle_root", "merkle-root", "HASH", "Root of the distributor allowlist, checked against --allowlist"))
+                .arg(value_arg("allowlist", "allowlist", "PATH", "Allowlist CSV of the distributor, only its mints can claim")),
+        )
+        .subcommand(
+            SubCommand::with_name("merkle")
+                .about("Build the merkle tree of a mint[,amount] allowlist, print its root and write a proof file per mint")
+                .arg(value_arg("csv", "csv", "PATH", "Allowlist CSV, mints without an amount are paid the reward").required(true))
+                .arg(value_arg("out_dir", "out-dir", "DIR", "Directory for the <mint>.json proof files").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("audit")
+                .about("Reconcile the distributors of a dump of the program's accounts with their receipts, referrals and vaults")
+                .arg(value_arg("dump", "dump", "PATH", "JSON dump of the program's accounts and vaults, or a directory of them").required(true))
+                .arg(value_arg("mints", "mints", "PATH", "File of the mints that may have claimed, one per line, to match receipts"))
+                .arg(value_arg("allowlist", "allowlist", "PATH", "Allowlist CSV of a distributor's merkle root, for the amounts its receipts were paid"))
+                .arg(value_arg("format", "format", "FORMAT", "Report format").possible_values(&["json", "csv"]).default_value("json")),
+        )
+        .subcommand(
+            SubCommand::with_name("migrate")
+                .about("Migrate a distributor to the current account version")
+                .arg(signer_arg("authority", "authority", "Distributor authority"))
+                .arg(value_arg("distributor_id", "distributor-id", "ID", "Distinguishes the authority's distributors [default: 0]"))
+                .arg(
+                    pubkey_arg("distributor", "distributor", "Distributor state account, for distributors created before version 1")
+                        .conflicts_with("distributor_id"),
+                ),
+        )
+}
+
+async fn process_command(banks_client: &mut BanksClient, config: &Config, matches: &ArgMatches<'_>) -> CliResult<String> {
+    match matches.subcommand() {
+        ("create", Some(matches)) => command_create(banks_client, config, matches).await,
+        ("fund", Some(matches)) => command_fund(banks_client, config, matches).await,
+        ("inspect", Some(matches)) => command_inspect(banks_client, config, matches).await,
+        ("pause", Some(matches)) => command_set_paused(banks_client, config, matches, true).await,
+        ("resume", Some(matches)) => command_set_paused(banks_client, config, matches, false).await,
+        ("close", Some(matches)) => command_close(banks_client, config, matches).await,
+        ("claim", Some(matches)) => command_claim(banks_client, config, matches).await,
+        ("migrate", Some(matches)) => command_migrate(banks_client, config, matches).await,
+        _ => unreachable!(),
+    }
+}
+
+fn default_keypair_path() -> String {
+    let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+    format!("{}/.config/solana/id.json", home)
+}
+
+async fn run(matches: &ArgMatches<'_>) -> CliResult<String> {
+    // global arguments are read from the subcommand, where clap propagates them
+    let (_name, command_matches) = matches.subcommand();
+    let command_matches = command_matches.ok_or("missing command")?;
+    // snapshot, merkle and audit only read local files
+    match matches.subcommand() {
+        ("snapshot", Some(matches)) => return command_snapshot(matches),
+        ("merkle", Some(matches)) => return command_merkle(matches),
+        ("audit", Some(matches)) => return command_audit(matches),
+        _ => {},
+    }
+    let unsigned = command_matches.is_present("unsigned");
+    let fee_payer = command_matches.value_of("fee_payer").map(str::to_string).unwrap_or_else(default_keypair_path);
+    let config = Config {
+        program_id: required_value_of(command_matches, "program_id")?,
+        fee_payer: parse_signer(&fee_payer, unsigned)?,
+        unsigned,
+    };
+    let url = command_matches.value_of("url").unwrap_or(DEFAULT_URL);
+    let mut banks_client = solana_banks_client::start_tcp_client(url)
+        .await
+        .map_err(|err| format!("cannot connect to {}: {}", url, err))?;
+    process_command(&mut banks_client, &config, matches).await
+}
+
+#[tokio::main]
+async fn main() {
+    match run(&app().get_matches()).await {
+        Ok(output) => println!("{}", output),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use borsh::BorshSerialize;
+    use solana_nft_claim_tokens::{processor::process_instruction, utils::puffed_out_string};
+    use solana_program::{
+        account_info::AccountInfo,
+        entrypoint::ProgramResult,
+        program_error::ProgramError,
+        program_option::COption,
+        program_pack::Pack,
+        program_stubs::{set_syscall_stubs, SyscallStubs},
+        system_instruction::{self, SystemInstruction},
+        system_program,
+    };
+    use solana_program_test::{processor, ProgramTest};
+    use solana_sdk::{account::Account, signature::Signature};
+    use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+    use spl_token_metadata::state::{
+        Creator, Data, Key as MetadataKey, MasterEditionV2, Metadata, MAX_MASTER_EDITION_LEN, MAX_METADATA_LEN, MAX_NAME_LENGTH,
+        MAX_SYMBOL_LENGTH, MAX_URI_LENGTH,
+    };
+    use std::sync::Once;
+
+    // the program runs natively for the create and claim commands
+    fn claim_program_id() -> Pubkey {
+        Pubkey::new_from_array([7u8; 32])
+    }
+
+    struct NoSyscallStubs;
+
+    impl SyscallStubs for NoSyscallStubs {}
+
+    /// The program-test syscall stubs, except that allocating an account assigns it to the program first, which then
+    /// grows its data itself: the program-test stubs refuse to resize an account on the way back from a CPI
+    struct NativeAllocate(Box<dyn SyscallStubs>);
+
+    impl SyscallStubs for NativeAllocate {
+        fn sol_log(&self, message: &str) {
+            self.0.sol_log(message)
+        }
+
+        fn sol_log_compute_units(&self) {
+            self.0.sol_log_compute_units()
+        }
+
+        fn sol_invoke_signed(&self, instruction: &Instruction, account_infos: &[AccountInfo], signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+            if instruction.program_id == system_program::ID {
+                if let Ok(SystemInstruction::Allocate { space }) = bincode::deserialize(&instruction.data) {
+                    let account = &instruction.accounts[0].pubkey;
+                    let info = account_infos.iter().find(|info| info.key == account).ok_or(ProgramError::NotEnoughAccountKeys)?;
+                    if !info.data_is_empty() {
+                        return Err(ProgramError::AccountAlreadyInitialized);
+                    }
+                    self.0.sol_invoke_signed(&system_instruction::assign(account, &claim_program_id()), account_infos, signers_seeds)?;
+                    // the account infos borrow their data, new space outlives the instruction
+                    *info.data.borrow_mut() = Box::leak(vec![0u8; space as usize].into_boxed_slice());
+                    return Ok(());
+                }
+            }
+            self.0.sol_invoke_signed(instruction, account_infos, signers_seeds)
+        }
+
+        fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+            self.0.sol_get_clock_sysvar(var_addr)
+        }
+
+        fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+            self.0.sol_get_epoch_schedule_sysvar(var_addr)
+        }
+
+        fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+            self.0.sol_get_fees_sysvar(var_addr)
+        }
+
+        fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+            self.0.sol_get_rent_sysvar(var_addr)
+        }
+
+        fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+            self.0.sol_get_return_data()
+        }
+
+        fn sol_set_return_data(&self, data: &[u8]) {
+            self.0.sol_set_return_data(data)
+        }
+
+        fn sol_log_data(&self, fields: &[&[u8]]) {
+            self.0.sol_log_data(fields)
+        }
+    }
+
+    // program-test installs its stubs once, when the first test starts
+    fn install_native_allocate() {
+        static INSTALL: Once = Once::new();
+        INSTALL.call_once(|| {
+            let program_test_stubs = set_syscall_stubs(Box::new(NoSyscallStubs));
+            set_syscall_stubs(Box::new(NativeAllocate(program_test_stubs)));
+        });
+    }
+
+    fn mint_account(mint_authority: Pubkey, supply: u64) -> Account {
+        let mut data = vec![0u8; Mint::LEN];
+        Mint { mint_authority: COption::Some(mint_authority), supply, decimals: 0, is_initialized: true, freeze_authority: COption::None }
+            .pack_into_slice(&mut data);
+        Account { lamports: 1_000_000_000, data, owner: spl_token::ID, executable: false, rent_epoch: 0 }
+    }
+
+    /// Metadata of a collection NFT and its master edition, as the metadata program writes them
+    fn nft_accounts(mint: Pubkey, creator: Pubkey) -> [(Pubkey, Account); 2] {
+        let mut metadata = vec![0u8; MAX_METADATA_LEN];
+        Metadata {
+            key: MetadataKey::MetadataV1,
+            update_authority: creator,
+            mint,
+            data: Data {
+                name: puffed_out_string("Collection #1", MAX_NAME_LENGTH),
+                symbol: puffed_out_string("SYMBOL", MAX_SYMBOL_LENGTH),
+                uri: puffed_out_string("https://example.com/1.json", MAX_URI_LENGTH),
+                seller_fee_basis_points: 0,
+                creators: Some(vec![Creator { address: creator, verified: true, share: 100 }]),
+            },
+            primary_sale_happened: false,
+            is_mutable: true,
+            edition_nonce: None,
+        }.serialize(&mut &mut metadata[..]).unwrap();
+        let mut master_edition = vec![0u8; MAX_MASTER_EDITION_LEN];
+        MasterEditionV2 { key: MetadataKey::MasterEditionV2, supply: 0, max_supply: Some(0) }.serialize(&mut &mut master_edition[..]).unwrap();
+        let account = |data| Account { lamports: 1_000_000_000, data, owner: spl_token_metadata::ID, executable: false, rent_epoch: 0 };
+        [(client::find_metadata_address(&mint).0, account(metadata)), (client::find_edition_address(&mint).0, account(master_edition))]
+    }
+
+    fn token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> Account {
+        let mut data = vec![0u8; TokenAccount::LEN];
+        TokenAccount { mint, owner, amount, state: AccountState::Initialized, ..TokenAccount::default() }.pack_into_slice(&mut data);
+        Account { lamports: 1_000_000_000, data, owner: spl_token::ID, executable: false, rent_epoch: 0 }
+    }
+
+    fn distributor(reward_token_account: Pubkey, reward_mint: Pubkey) -> DistributorAccount {
+        DistributorAccount {
+            key: AccountKey::Distributor,
+            version: DISTRIBUTOR_VERSION,
+            is_initialized: true,
+            authority: Pubkey::new_unique(),
+            reward_token_account,
+            reward_mint,
+            reward_amount_total: 1_000,
+            reward_amount_per_nft: 100,
+            amount_claimed: 300,
+            start_ts: 0,
+            collection_symbol: puffed_out_symbol("SYMBOL").unwrap(),
+            collection_creator: Pubkey::new_unique(),
+            reward_amount_per_print: 100,
+            edition_policy: EditionPolicy::Both,
+            trait_filter: None,
+            name_prefix: None,
+            uri_prefix: None,
+            max_claims_per_wallet: None,
+            claim_fee_lamports: 0,
+            fee_treasury: Pubkey::new_unique(),
+            referral_bps: 0,
+            distributor_id: 0,
+            authority_bump: 255,
+            merkle_root: None,
+            paused: false,
+        }
+    }
+
+    fn matches(args: &[&str]) -> ArgMatches<'static> {
+        app().get_matches_from_safe(std::iter::once("claim-tokens-cli").chain(args.iter().copied()).map(str::to_string)).unwrap()
+    }
+
+    #[test]
+    fn test_parse_signer() {
+        let pubkey = Pubkey::new_unique();
+        assert_eq!(parse_signer(&pubkey.to_string(), true).unwrap().pubkey(), pubkey);
+        // a pubkey cannot sign
+        assert!(parse_signer(&pubkey.to_string(), false).is_err());
+    }
+
+    #[test]
+    fn test_parse_mints() {
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+        let snapshot_output = format!("{} original 100\n{} print 10\nEligible: 2\nRequired total: 110", first, second);
+        assert_eq!(parse_mints(&snapshot_output), vec![first, second]);
+        assert_eq!(parse_mints(&format!("mint,amount\n{},5\n", first)), vec![first]);
+    }
+
+    #[test]
+    fn test_snapshot_takes_the_allowlist_of_the_root() {
+        let directory = std::env::temp_dir().join(format!("claim-tokens-cli-{}", Pubkey::new_unique()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let dump_path = directory.join("dump.json");
+        std::fs::write(&dump_path, "[]").unwrap();
+        let allowlist_path = directory.join("allowlist.csv");
+        std::fs::write(&allowlist_path, format!("mint,amount\n{},40\n", Pubkey::new_unique())).unwrap();
+        let allowlist = merkle::parse_csv(&std::fs::read_to_string(&allowlist_path).unwrap()).unwrap();
+        let root = Hash::new_from_array(merkle::MerkleTree::new(allowlist).unwrap().root());
+        let (dump_arg, allowlist_arg, root_arg, creator_arg) =
+            (dump_path.to_str().unwrap(), allowlist_path.to_str().unwrap(), root.to_string(), Pubkey::new_unique().to_string());
+        let snapshot_with = |extra_args: &[&str]| {
+            let mut args = vec!["snapshot", "--dump", dump_arg, "--collection-creator", &creator_arg, "--symbol", "SYMBOL", "--reward-per-nft", "100"];
+            args.extend_from_slice(extra_args);
+            let matches = matches(&args);
+            let matches = matches.subcommand_matches("snapshot").unwrap();
+            (distributor_from_args(matches).unwrap(), command_snapshot(matches))
+        };
+
+        // the root is passed through, and the allowlist it is the root of applies
+        let (distributor, output) = snapshot_with(&["--merkle-root", &root_arg, "--allowlist", allowlist_arg]);
+        assert_eq!(distributor.merkle_root, Some(root.to_bytes()));
+        assert_eq!(output.unwrap(), "Eligible: 0\nRequired total: 0");
+        assert!(snapshot_with(&["--allowlist", allowlist_arg]).1.is_ok());
+
+        // the root alone cannot tell which mints claim, and an allowlist of another root does not apply
+        assert!(snapshot_with(&["--merkle-root", &root_arg]).1.is_err());
+        let other_root = Hash::new_unique().to_string();
+        assert!(snapshot_with(&["--merkle-root", &other_root, "--allowlist", allowlist_arg]).1.is_err());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_fund_and_inspect() {
+        let program_id = Pubkey::new_unique();
+        let mut program_test = ProgramTest::default();
+        program_test.add_program("spl_token", spl_token::ID, processor!(spl_token::processor::Processor::process));
+
+        let owner = Keypair::new();
+        let reward_mint = Pubkey::new_unique();
+        let source = Pubkey::new_unique();
+        let reward_token_account = Pubkey::new_unique();
+        let distributor_state = Pubkey::new_unique();
+        let mut mint_data = vec![0u8; Mint::LEN];
+        Mint { mint_authority: COption::None, supply: 1_000, decimals: 0, is_initialized: true, freeze_authority: COption::None }
+            .pack_into_slice(&mut mint_data);
+        program_test.add_account(
+            reward_mint,
+            Account { lamports: 1_000_000_000, data: mint_data, owner: spl_token::ID, executable: false, rent_epoch: 0 },
+        );
+        program_test.add_account(source, token_account(reward_mint, owner.pubkey(), 1_000));
+        program_test.add_account(reward_token_account, token_account(reward_mint, Pubkey::new_unique(), 0));
+        let mut data = vec![0u8; DistributorAccount::LEN];
+        borsh::BorshSerialize::serialize(&distributor(reward_token_account, reward_mint), &mut &mut data[..]).unwrap();
+        program_test.add_account(
+            distributor_state,
+            Account { lamports: 1_000_000_000, data, owner: program_id, executable: false, rent_epoch: 0 },
+        );
+
+        let (mut banks_client, payer, _recent_blockhash) = program_test.start().await;
+        let config = Config { program_id, fee_payer: SignerArg::Keypair(payer), unsigned: false };
+
+        let distributor_arg = distributor_state.to_string();
+        let output = process_command(&mut banks_client, &config, &matches(&["inspect", "--distributor", &distributor_arg]))
+            .await
+            .unwrap();
+        assert!(output.contains("Collection symbol: SYMBOL\n"));
+        assert!(output.contains("Remaining: 700\n"));
+        assert!(output.contains(&format!("reward_token_account: {}", reward_token_account)));
+
+        let owner_path = std::env::temp_dir().join(format!("claim-tokens-cli-{}.json", owner.pubkey()));
+        solana_sdk::signature::write_keypair_file(&owner, &owner_path).unwrap();
+        let output = process_command(
+            &mut banks_client,
+            &config,
+            &matches(&[
+                "fund",
+                "--distributor", &distributor_arg,
+                "--source", &source.to_string(),
+                "--owner", owner_path.to_str().unwrap(),
+                "--amount", "400",
+            ]),
+        )
+        .await;
+        std::fs::remove_file(&owner_path).unwrap();
+        assert!(output.unwrap().parse::<Signature>().is_ok());
+        let vault = banks_client.get_packed_account_data::<TokenAccount>(reward_token_account).await.unwrap();
+        assert_eq!(vault.amount, 400);
+
+        // not a distributor of the program
+        let source_arg = source.to_string();
+        assert!(process_command(&mut banks_client, &config, &matches(&["inspect", "--distributor", &source_arg])).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_create_and_claim() {
+        let program_id = claim_program_id();
+        let mut program_test = ProgramTest::default();
+        program_test.add_program("solana_nft_claim_tokens", program_id, processor!(process_instruction));
+        program_test.add_program("spl_token", spl_token::ID, processor!(spl_token::processor::Processor::process));
+
+        // the claimant holds a collection NFT and pays for its claim
+        let claimant = Keypair::new();
+        let creator = Pubkey::new_unique();
+        let nft_mint = Pubkey::new_unique();
+        let nft_account = Pubkey::new_unique();
+        program_test.add_account(
+            claimant.pubkey(),
+            Account { lamports: 1_000_000_000, data: vec![], owner: system_program::ID, executable: false, rent_epoch: 0 },
+        );
+        program_test.add_account(nft_mint, mint_account(client::find_edition_address(&nft_mint).0, 1));
+        program_test.add_account(nft_account, token_account(nft_mint, claimant.pubkey(), 1));
+        for (address, account) in nft_accounts(nft_mint, creator) {
+            program_test.add_account(address, account);
+        }
+
+        let mut context = program_test.start_with_context().await;
+        install_native_allocate();
+        // the reward vault is the authority's until create hands it to the distributor
+        let authority = Keypair::from_bytes(&context.payer.to_bytes()).unwrap();
+        let reward_mint = Pubkey::new_unique();
+        let reward_token_account = Pubkey::new_unique();
+        let reward_account = Pubkey::new_unique();
+        context.set_account(&reward_mint, &mint_account(authority.pubkey(), 1_000).into());
+        context.set_account(&reward_token_account, &token_account(reward_mint, authority.pubkey(), 1_000).into());
+        context.set_account(&reward_account, &token_account(reward_mint, claimant.pubkey(), 0).into());
+        let mut banks_client = context.banks_client;
+
+        let config = Config { program_id, fee_payer: SignerArg::Keypair(authority), unsigned: false };
+        let output = process_command(
+            &mut banks_client,
+            &config,
+            &matches(&[
+                "create",
+                "--reward-token-account", &reward_token_account.to_string(),
+                "--collection-creator", &creator.to_string(),
+                "--symbol", "SYMBOL",
+                "--reward-total", "1000",
+                "--reward-per-nft", "100",
+            ]),
+        )
+        .await
+        .unwrap();
+        let (distributor_state, _bump_seed) = client::find_distributor_state_address(&program_id, &config.fee_payer.pubkey(), 0);
+        let mut lines = output.lines();
+        assert_eq!(lines.next(), Some(format!("Distributor: {}", distributor_state).as_str()));
+        assert!(lines.next().unwrap().parse::<Signature>().is_ok());
+        let distributor = get_distributor(&mut banks_client, &config, &distributor_state).await.unwrap();
+        assert_eq!(distributor.authority, config.fee_payer.pubkey());
+        assert_eq!(distributor.reward_amount_total, 1_000);
+        let vault = banks_client.get_packed_account_data::<TokenAccount>(reward_token_account).await.unwrap();
+        assert_eq!(vault.owner, client::find_distributor_authority(&program_id, &distributor_state).0);
+
+        let claimant_config = Config { program_id, fee_payer: SignerArg::Keypair(claimant), unsigned: false };
+        let output = process_command(
+            &mut banks_client,
+            &claimant_config,
+            &matches(&[
+                "claim",
+                "--distributor", &distributor_state.to_string(),
+                "--nft-mint", &nft_mint.to_string(),
+                "--nft-account", &nft_account.to_string(),
+                "--reward-account", &reward_account.to_string(),
+            ]),
+        )
+        .await
+        .unwrap();
+        assert!(output.parse::<Signature>().is_ok());
+        assert_eq!(banks_client.get_packed_account_data::<TokenAccount>(reward_account).await.unwrap().amount, 100);
+        assert_eq!(banks_client.get_packed_account_data::<TokenAccount>(reward_token_account).await.unwrap().amount, 900);
+        let (receipt, _bump_seed) = client::find_receipt_address(&program_id, &nft_mint, &distributor_state);
+        let receipt = banks_client.get_account(receipt).await.unwrap().unwrap();
+        assert_eq!(receipt.owner, program_id);
+        assert!(client::decode_proof_of_receipt(&receipt.data).unwrap().received_tokens);
+        assert_eq!(get_distributor(&mut banks_client, &config, &distributor_state).await.unwrap().amount_claimed, 100);
+    }
+
+    #[tokio::test]
+    async fn test_create_unsigned() {
+        let program_id = Pubkey::new_unique();
+        let (mut banks_client, _payer, _recent_blockhash) = ProgramTest::default().start().await;
+        let fee_payer = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let config = Config { program_id, fee_payer: SignerArg::Pubkey(fee_payer), unsigned: true };
+
+        let output = process_command(
+            &mut banks_client,
+            &config,
+            &matches(&[
+                "create",
+                "--authority", &authority.to_string(),
+                "--distributor-id", "3",
+                "--reward-token-account", &Pubkey::new_unique().to_string(),
+                "--collection-creator", &Pubkey::new_unique().to_string(),
+                "--symbol", "SYMBOL",
+                "--reward-total", "1000",
+                "--reward-per-nft", "100",
+            ]),
+        )
+        .await
+        .unwrap();
+
+        let (distributor_state, _bump_seed) = client::find_distributor_state_address(&program_id, &authority, 3);
+        let mut lines = output.lines();
+        assert_eq!(lines.next(), Some(format!("Distributor: {}", distributor_state).as_str()));
+        let transaction: Transaction = bincode::deserialize(&base64::decode(lines.next().unwrap()).unwrap()).unwrap();
+        assert_eq!(transaction.message.header.num_required_signatures, 2);
+        assert_eq!(&transaction.message.account_keys[..2], &[fee_payer, authority]);
+        assert!(transaction.message.account_keys.contains(&distributor_state));
+        assert!(transaction.signatures.iter().all(|signature| *signature == Signature::default()));
+    }
+
+    #[tokio::test]
+    async fn test_pause_and_close_unsigned() {
+        let program_id = Pubkey::new_unique();
+        let distributor_state = Pubkey::new_unique();
+        let reward_token_account = Pubkey::new_unique();
+        let distributor = distributor(reward_token_account, Pubkey::new_unique());
+        let mut data = vec![0u8; DistributorAccount::LEN];
+        borsh::BorshSerialize::serialize(&distributor, &mut &mut data[..]).unwrap();
+        let mut program_test = ProgramTest::default();
+        program_test.add_account(
+            distributor_state,
+            Account { lamports: 1_000_000_000, data, owner: program_id, executable: false, rent_epoch: 0 },
+        );
+        let (mut banks_client, _payer, _recent_blockhash) = program_test.start().await;
+        let fee_payer = Pubkey::new_unique();
+        let config = Config { program_id, fee_payer: SignerArg::Pubkey(fee_payer), unsigned: true };
+
+        let distributor_arg = distributor_state.to_string();
+        let authority_arg = distributor.authority.to_string();
+        let destination = Pubkey::new_unique();
+        let destination_arg = destination.to_string();
+        for (args, instruction) in [
+            (vec!["pause"], client::set_paused(&program_id, &distributor_state, &distributor, true)),
+            (vec!["resume"], client::set_paused(&program_id, &distributor_state, &distributor, false)),
+            (vec!["close", "--destination", &destination_arg], client::close_distributor(&program_id, &distributor_state, &distributor, &destination)),
+        ] {
+            // the fee payer is not the distributor authority
+            let mut command = args.clone();
+            command.extend(["--distributor", &distributor_arg]);
+            assert!(process_command(&mut banks_client, &config, &matches(&command)).await.is_err());
+
+            command.extend(["--authority", &authority_arg]);
+            let output = process_command(&mut banks_client, &config, &matches(&command)).await.unwrap();
+            let transaction: Transaction = bincode::deserialize(&base64::decode(output.lines().last().unwrap()).unwrap()).unwrap();
+            assert_eq!(&transaction.message.account_keys[..2], &[fee_payer, distributor.authority]);
+            let compiled = &transaction.message.instructions[0];
+            assert_eq!(compiled.data, instruction.data);
+            let accounts: Vec<Pubkey> = compiled.accounts.iter().map(|index| transaction.message.account_keys[*index as usize]).collect();
+            assert_eq!(accounts, instruction.accounts.iter().map(|meta| meta.pubkey).collect::<Vec<_>>());
+        }
+    }
+}
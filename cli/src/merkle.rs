@@ -0,0 +1,205 @@
+//! Merkle trees of `mint[,amount]` allowlists, with a JSON proof file per mint.
+//! Leaves and nodes are hashed by the program's `merkle` module, which also verifies the proofs.
+
+use serde::{Deserialize, Serialize};
+use solana_nft_claim_tokens::merkle::{hash_leaf, hash_nodes};
+use solana_program::{hash::Hash, pubkey::Pubkey};
+use std::{collections::BTreeMap, fs, io, path::Path, str::FromStr};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum MerkleError {
+    #[error("cannot read or write files: {0}")]
+    Io(#[from] io::Error),
+    #[error("cannot encode proof: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("line {0}: invalid mint {1}")]
+    InvalidMint(usize, String),
+    #[error("line {0}: invalid amount {1}")]
+    InvalidAmount(usize, String),
+    #[error("line {0}: an amount of 0 is no amount, the mint is paid the distributor reward: omit the amount instead")]
+    ZeroAmount(usize),
+    #[error("line {0}: duplicate mint {1}")]
+    DuplicateMint(usize, Pubkey),
+    #[error("the allowlist is empty")]
+    Empty,
+}
+
+/// Parses `mint[,amount]` lines, skipping blank lines, `#` comments and a `mint` header.
+/// A mint without an amount is paid the distributor reward, its leaf has the amount 0, so an explicit 0 is rejected.
+pub fn parse_csv(csv: &str) -> Result<Vec<(Pubkey, u64)>, MerkleError> {
+    let mut entries = BTreeMap::new();
+    for (index, line) in csv.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || (index == 0 && line.starts_with("mint")) {
+            continue;
+        }
+        let mut fields = line.split(',').map(str::trim);
+        let mint = fields.next().unwrap_or_default();
+        let mint = Pubkey::from_str(mint).map_err(|_| MerkleError::InvalidMint(line_number, mint.to_string()))?;
+        let amount = match fields.next() {
+            Some(amount) => match amount.parse() {
+                Ok(0) => return Err(MerkleError::ZeroAmount(line_number)),
+                Ok(amount) => amount,
+                Err(_) => return Err(MerkleError::InvalidAmount(line_number, amount.to_string())),
+            },
+            None => 0,
+        };
+        if fields.next().is_some() {
+            return Err(MerkleError::InvalidAmount(line_number, line.to_string()));
+        }
+        if entries.insert(mint, amount).is_some() {
+            return Err(MerkleError::DuplicateMint(line_number, mint));
+        }
+    }
+    Ok(entries.into_iter().collect())
+}
+
+pub struct MerkleTree {
+    // entries sorted by mint, so the tree doesn't depend on the CSV order
+    entries: Vec<(Pubkey, u64)>,
+    // levels[0] are the leaves, the last level is the root
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+impl MerkleTree {
+    pub fn new(mut entries: Vec<(Pubkey, u64)>) -> Result<Self, MerkleError> {
+        if entries.is_empty() {
+            return Err(MerkleError::Empty);
+        }
+        entries.sort();
+        let mut levels = vec![entries.iter().map(|(mint, amount)| hash_leaf(mint, *amount)).collect::<Vec<_>>()];
+        while levels.last().unwrap().len() > 1 {
+            // an odd node out is carried up unchanged
+            let level = levels.last().unwrap().chunks(2).map(|pair| match pair {
+                [left, right] => hash_nodes(left, right),
+                [node] => *node,
+                _ => unreachable!(),
+            }).collect();
+            levels.push(level);
+        }
+        Ok(MerkleTree { entries, levels })
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.levels.last().unwrap()[0]
+    }
+
+    /// Siblings of the mint's leaf from the bottom up, None if the mint is not in the tree
+    pub fn proof(&self, mint: &Pubkey) -> Option<Vec<[u8; 32]>> {
+        let mut index = self.entries.binary_search_by_key(mint, |(entry_mint, _amount)| *entry_mint).ok()?;
+        let mut proof = vec![];
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        Some(proof)
+    }
+
+    pub fn entries(&self) -> &[(Pubkey, u64)] {
+        &self.entries
+    }
+}
+
+/// Contents of `<mint>.json`, hashes are base58 encoded
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ProofFile {
+    pub mint: String,
+    pub amount: u64,
+    pub root: String,
+    pub proof: Vec<String>,
+}
+
+impl ProofFile {
+    /// The hashes as the verifier takes them
+    pub fn decode(&self) -> Option<(Pubkey, [u8; 32], Vec<[u8; 32]>)> {
+        let decode_hash = |hash: &str| Hash::from_str(hash).ok().map(Hash::to_bytes);
+        let proof = self.proof.iter().map(|hash| decode_hash(hash)).collect::<Option<Vec<_>>>()?;
+        Some((Pubkey::from_str(&self.mint).ok()?, decode_hash(&self.root)?, proof))
+    }
+}
+
+/// Writes the proof file of every mint of the tree to the directory
+pub fn write_proofs(tree: &MerkleTree, directory: &Path) -> Result<(), MerkleError> {
+    fs::create_dir_all(directory)?;
+    let root = Hash::new_from_array(tree.root()).to_string();
+    for (mint, amount) in tree.entries() {
+        let proof_file = ProofFile {
+            mint: mint.to_string(),
+            amount: *amount,
+            root: root.clone(),
+            proof: tree.proof(mint).unwrap().into_iter().map(|hash| Hash::new_from_array(hash).to_string()).collect(),
+        };
+        fs::write(directory.join(format!("{}.json", mint)), serde_json::to_string_pretty(&proof_file)?)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_nft_claim_tokens::merkle::verify_proof;
+
+    fn entries(count: u64) -> Vec<(Pubkey, u64)> {
+        (0..count).map(|amount| (Pubkey::new_unique(), amount)).collect()
+    }
+
+    #[test]
+    fn test_every_proof_verifies() {
+        for count in 1..=9 {
+            let entries = entries(count);
+            let tree = MerkleTree::new(entries.clone()).unwrap();
+            for (mint, amount) in &entries {
+                let proof = tree.proof(mint).unwrap();
+                assert!(verify_proof(&tree.root(), mint, *amount, &proof));
+                assert!(!verify_proof(&tree.root(), mint, amount + 1, &proof));
+            }
+            assert_eq!(tree.proof(&Pubkey::new_unique()), None);
+        }
+    }
+
+    #[test]
+    fn test_root_does_not_depend_on_order() {
+        let entries = entries(5);
+        let mut reversed = entries.clone();
+        reversed.reverse();
+        assert_eq!(MerkleTree::new(entries).unwrap().root(), MerkleTree::new(reversed).unwrap().root());
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+        let csv = format!("mint,amount\n{}, 50\n\n# allowlist only\n{}\n", first, second);
+        let mut expected = vec![(first, 50), (second, 0)];
+        expected.sort();
+        assert_eq!(parse_csv(&csv).unwrap(), expected);
+
+        assert!(matches!(parse_csv(&format!("{}\n{},1", first, first)), Err(MerkleError::DuplicateMint(2, _))));
+        assert!(matches!(parse_csv(&format!("{},ten", first)), Err(MerkleError::InvalidAmount(1, _))));
+        // 0 would read as an amount but pays the reward
+        assert!(matches!(parse_csv(&format!("mint,amount\n{},0", first)), Err(MerkleError::ZeroAmount(2))));
+        assert!(matches!(parse_csv("not a mint"), Err(MerkleError::InvalidMint(1, _))));
+    }
+
+    #[test]
+    fn test_write_proofs() {
+        let entries = entries(3);
+        let tree = MerkleTree::new(entries.clone()).unwrap();
+        let directory = std::env::temp_dir().join(format!("claim-tokens-cli-proofs-{}", Pubkey::new_unique()));
+        write_proofs(&tree, &directory).unwrap();
+
+        for (mint, amount) in &entries {
+            let json = fs::read_to_string(directory.join(format!("{}.json", mint))).unwrap();
+            let proof_file: ProofFile = serde_json::from_str(&json).unwrap();
+            assert_eq!(proof_file.amount, *amount);
+            let (proof_mint, root, proof) = proof_file.decode().unwrap();
+            assert_eq!(root, tree.root());
+            assert!(verify_proof(&root, &proof_mint, proof_file.amount, &proof));
+        }
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
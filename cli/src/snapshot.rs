@@ -0,0 +1,239 @@
+//! Eligibility snapshots: which NFTs of a dump of Metaplex metadata accounts can claim from a distributor,
+//! and the reward pool they need.
+//! The metadata and edition rules are the ones `process_claim_tokens` applies. Trait filters, wallet limits
+//! and existing receipts depend on other accounts, so they are not checked and the snapshot is an upper bound.
+//! A distributor with a merkle root only pays the mints of its allowlist, their amounts replacing the reward.
+
+use crate::dump::DumpedAccount;
+use solana_nft_claim_tokens::{
+    client::{find_edition_address, find_metadata_address, find_parent_edition_bump},
+    processor::{check_edition_eligibility, check_metadata_eligibility},
+    state::DistributorAccount,
+};
+use solana_program::pubkey::Pubkey;
+use spl_token_metadata::{
+    state::{Key as MetadataKey, Metadata, MAX_METADATA_LEN},
+    utils::try_from_slice_checked,
+};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EligibleNft {
+    pub mint: Pubkey,
+    pub is_print: bool,
+    pub reward: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    /// Sorted by mint
+    pub eligible: Vec<EligibleNft>,
+    /// reward_amount_total needed for every eligible NFT to claim
+    pub required_total: u64,
+    /// Sorted mints that pass the metadata rules but whose edition account is not in the dump,
+    /// claims need it, so they are neither eligible nor counted in required_total
+    pub unknown_edition: Vec<Pubkey>,
+}
+
+/// Finds the NFTs of the dump eligible to claim from the distributor.
+/// Claims check the edition account of the mint, NFTs whose edition is not in the dump are listed apart.
+/// `allowlist` is the allowlist of the distributor's merkle root, it is ignored for distributors without one.
+pub fn snapshot(distributor: &DistributorAccount, accounts: &[DumpedAccount], allowlist: &[(Pubkey, u64)]) -> Snapshot {
+    let by_address: BTreeMap<Pubkey, &DumpedAccount> = accounts
+        .iter()
+        .filter(|account| account.owner == spl_token_metadata::ID)
+        .map(|account| (account.pubkey, account))
+        .collect();
+
+    let allowlist: BTreeMap<Pubkey, u64> = allowlist.iter().copied().collect();
+    let parent_edition_bump = find_parent_edition_bump(distributor);
+    let mut eligible = BTreeMap::new();
+    let mut unknown_edition = vec![];
+    for account in by_address.values() {
+        if account.data.first() != Some(&(MetadataKey::MetadataV1 as u8)) {
+            continue;
+        }
+        let metadata: Metadata = match try_from_slice_checked(&account.data, MetadataKey::MetadataV1, MAX_METADATA_LEN) {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        // claims only accept the metadata PDA of the mint
+        if find_metadata_address(&metadata.mint).0 != account.pubkey
+            || check_metadata_eligibility(distributor, &metadata).is_err()
+        {
+            continue;
+        }
+        let allowlist_amount = match (distributor.merkle_root, allowlist.get(&metadata.mint)) {
+            (Some(_), None) => continue,
+            (Some(_), Some(&amount)) => amount,
+            (None, _) => 0,
+        };
+        let edition = match by_address.get(&find_edition_address(&metadata.mint).0) {
+            Some(edition) => edition,
+            None => {
+                unknown_edition.push(metadata.mint);
+                continue;
+            },
+        };
+        if let Ok(is_print) = check_edition_eligibility(distributor, &edition.data, parent_edition_bump) {
+            // an amount of 0 is no amount, the claim pays the reward
+            let reward = if allowlist_amount > 0 { allowlist_amount } else { distributor.reward_amount(is_print) };
+            eligible.insert(metadata.mint, EligibleNft { mint: metadata.mint, is_print, reward });
+        }
+    }
+
+    let eligible: Vec<EligibleNft> = eligible.into_values().collect();
+    let required_total = eligible.iter().fold(0u64, |total, nft| total.saturating_add(nft.reward));
+    unknown_edition.sort();
+    unknown_edition.dedup();
+    Snapshot { eligible, required_total, unknown_edition }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use borsh::BorshSerialize;
+    use solana_nft_claim_tokens::{
+        state::{AccountKey, EditionPolicy, DISTRIBUTOR_VERSION},
+        utils::{puffed_out_string, puffed_out_symbol},
+    };
+    use spl_token_metadata::state::{
+        Creator, Data, Edition, MasterEditionV2, MAX_EDITION_LEN, MAX_MASTER_EDITION_LEN, MAX_NAME_LENGTH, MAX_SYMBOL_LENGTH,
+        MAX_URI_LENGTH,
+    };
+
+    fn distributor(creator: Pubkey) -> DistributorAccount {
+        DistributorAccount {
+            key: AccountKey::Distributor,
+            version: DISTRIBUTOR_VERSION,
+            is_initialized: true,
+            authority: Pubkey::new_unique(),
+            reward_token_account: Pubkey::new_unique(),
+            reward_mint: Pubkey::new_unique(),
+            reward_amount_total: 0,
+            reward_amount_per_nft: 100,
+            amount_claimed: 0,
+            start_ts: 0,
+            collection_symbol: puffed_out_symbol("SYMBOL").unwrap(),
+            collection_creator: creator,
+            reward_amount_per_print: 10,
+            edition_policy: EditionPolicy::Both,
+            trait_filter: None,
+            name_prefix: None,
+            uri_prefix: None,
+            max_claims_per_wallet: None,
+            claim_fee_lamports: 0,
+            fee_treasury: Pubkey::new_unique(),
+            referral_bps: 0,
+            distributor_id: 0,
+            authority_bump: 255,
+            merkle_root: None,
+            paused: false,
+        }
+    }
+
+    fn metadata(mint: Pubkey, creator: Pubkey, symbol: &str) -> DumpedAccount {
+        let mut data = vec![0u8; MAX_METADATA_LEN];
+        Metadata {
+            key: MetadataKey::MetadataV1,
+            update_authority: creator,
+            mint,
+            data: Data {
+                name: puffed_out_string("Collection #1", MAX_NAME_LENGTH),
+                symbol: puffed_out_string(symbol, MAX_SYMBOL_LENGTH),
+                uri: puffed_out_string("https://example.com/1.json", MAX_URI_LENGTH),
+                seller_fee_basis_points: 0,
+                creators: Some(vec![Creator { address: creator, verified: true, share: 100 }]),
+            },
+            primary_sale_happened: false,
+            is_mutable: true,
+            edition_nonce: None,
+        }.serialize(&mut &mut data[..]).unwrap();
+        DumpedAccount { pubkey: find_metadata_address(&mint).0, owner: spl_token_metadata::ID, data }
+    }
+
+    fn print_edition(mint: Pubkey) -> DumpedAccount {
+        let mut data = vec![0u8; MAX_EDITION_LEN];
+        Edition { key: MetadataKey::EditionV1, parent: Pubkey::new_unique(), edition: 1 }
+            .serialize(&mut &mut data[..])
+            .unwrap();
+        DumpedAccount { pubkey: find_edition_address(&mint).0, owner: spl_token_metadata::ID, data }
+    }
+
+    fn master_edition(mint: Pubkey) -> DumpedAccount {
+        let mut data = vec![0u8; MAX_MASTER_EDITION_LEN];
+        MasterEditionV2 { key: MetadataKey::MasterEditionV2, supply: 0, max_supply: None }
+            .serialize(&mut &mut data[..])
+            .unwrap();
+        DumpedAccount { pubkey: find_edition_address(&mint).0, owner: spl_token_metadata::ID, data }
+    }
+
+    #[test]
+    fn test_snapshot_applies_claim_rules() {
+        let creator = Pubkey::new_unique();
+        let original = Pubkey::new_unique();
+        let print = Pubkey::new_unique();
+        let wrong_creator = Pubkey::new_unique();
+        let wrong_symbol = Pubkey::new_unique();
+        let mut not_a_pda = metadata(Pubkey::new_unique(), creator, "SYMBOL");
+        not_a_pda.pubkey = Pubkey::new_unique();
+        let no_edition = Pubkey::new_unique();
+        let accounts = vec![
+            metadata(original, creator, "SYMBOL"),
+            master_edition(original),
+            metadata(print, creator, "SYMBOL"),
+            print_edition(print),
+            metadata(wrong_creator, Pubkey::new_unique(), "SYMBOL"),
+            metadata(wrong_symbol, creator, "OTHER"),
+            not_a_pda,
+            metadata(no_edition, creator, "SYMBOL"),
+        ];
+
+        let result = snapshot(&distributor(creator), &accounts, &[]);
+        let mut expected = vec![
+            EligibleNft { mint: original, is_print: false, reward: 100 },
+            EligibleNft { mint: print, is_print: true, reward: 10 },
+        ];
+        expected.sort_by_key(|nft| nft.mint);
+        // the edition of an NFT whose edition account is not in the dump cannot be checked, it is listed apart and not counted
+        assert_eq!(result, Snapshot { eligible: expected, required_total: 110, unknown_edition: vec![no_edition] });
+
+        // the dumped edition is checked against the edition policy
+        let originals_only = DistributorAccount { edition_policy: EditionPolicy::OriginalsOnly, ..distributor(creator) };
+        let result = snapshot(&originals_only, &accounts, &[]);
+        assert_eq!(result.eligible, vec![EligibleNft { mint: original, is_print: false, reward: 100 }]);
+        assert_eq!(result.required_total, 100);
+    }
+
+    #[test]
+    fn test_snapshot_applies_allowlist() {
+        let creator = Pubkey::new_unique();
+        let original = Pubkey::new_unique();
+        let print = Pubkey::new_unique();
+        let not_allowlisted = Pubkey::new_unique();
+        let accounts = vec![
+            metadata(original, creator, "SYMBOL"),
+            master_edition(original),
+            metadata(print, creator, "SYMBOL"),
+            print_edition(print),
+            metadata(not_allowlisted, creator, "SYMBOL"),
+            master_edition(not_allowlisted),
+        ];
+        // the original is allowlisted for 40, the print without an amount is paid the print reward
+        let allowlist = [(original, 40), (print, 0)];
+
+        let with_root = DistributorAccount { merkle_root: Some([1; 32]), ..distributor(creator) };
+        let result = snapshot(&with_root, &accounts, &allowlist);
+        let mut expected = vec![
+            EligibleNft { mint: original, is_print: false, reward: 40 },
+            EligibleNft { mint: print, is_print: true, reward: 10 },
+        ];
+        expected.sort_by_key(|nft| nft.mint);
+        assert_eq!(result, Snapshot { eligible: expected, required_total: 50, unknown_edition: vec![] });
+
+        // without a merkle root every mint claims the reward
+        let result = snapshot(&distributor(creator), &accounts, &allowlist);
+        assert_eq!(result.eligible.len(), 3);
+        assert_eq!(result.required_total, 210);
+    }
+}
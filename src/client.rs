@@ -0,0 +1,230 @@
+//! Off-chain helpers: PDA derivation, account decoding and instruction builders that derive their own accounts.
+
+use solana_program::{
+    instruction::Instruction,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_program,
+    sysvar,
+};
+use spl_token_metadata::state::{EDITION, PREFIX as METADATA_PREFIX};
+use crate::{
+    instruction::{self, ClaimTokensAccounts, ClaimTokensArgs, CreateTokenDistributorAccounts, CreateTokenDistributorArgs, ReferralAccounts},
+    state::{AttributeAttestationAccount, DistributorAccount, EditionPolicy, ProofOfReceiptAccount, ReferralRecordAccount, WalletClaimRecordAccount},
+    utils::{ATTESTATION_PREFIX, PREFIX, RECEIPT_PREFIX, REFERRAL_PREFIX, WALLET_CLAIMS_PREFIX},
+};
+
+pub use crate::utils::find_distributor_state_address;
+
+/// Finds the PDA that owns the distributor reward token account
+pub fn find_distributor_authority(program_id: &Pubkey, distributor_state: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PREFIX.as_bytes(), distributor_state.as_ref()], program_id)
+}
+
+/// Finds the proof of receipt created when tokens are claimed for the NFT mint
+pub fn find_receipt_address(program_id: &Pubkey, nft_mint: &Pubkey, distributor_state: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[RECEIPT_PREFIX.as_bytes(), nft_mint.as_ref(), distributor_state.as_ref()], program_id)
+}
+
+/// Finds the Metaplex metadata account of the mint
+pub fn find_metadata_address(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[METADATA_PREFIX.as_bytes(), spl_token_metadata::ID.as_ref(), mint.as_ref()],
+        &spl_token_metadata::ID,
+    )
+}
+
+/// Finds the Metaplex master edition or edition account of the mint
+pub fn find_edition_address(mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[METADATA_PREFIX.as_bytes(), spl_token_metadata::ID.as_ref(), mint.as_ref(), EDITION.as_bytes()],
+        &spl_token_metadata::ID,
+    )
+}
+
+pub fn find_attestation_address(program_id: &Pubkey, oracle: &Pubkey, nft_mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[ATTESTATION_PREFIX.as_bytes(), oracle.as_ref(), nft_mint.as_ref()], program_id)
+}
+
+pub fn find_wallet_claim_record_address(program_id: &Pubkey, distributor_state: &Pubkey, claimant: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[WALLET_CLAIMS_PREFIX.as_bytes(), distributor_state.as_ref(), claimant.as_ref()], program_id)
+}
+
+pub fn find_referral_record_address(program_id: &Pubkey, distributor_state: &Pubkey, referrer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[REFERRAL_PREFIX.as_bytes(), distributor_state.as_ref(), referrer.as_ref()], program_id)
+}
+
+/// Finds the bump of the master edition a PrintsWithParent distributor takes prints of, 0 under other edition policies
+pub fn find_parent_edition_bump(distributor: &DistributorAccount) -> u8 {
+    match distributor.edition_policy {
+        EditionPolicy::PrintsWithParent(master_mint) => find_edition_address(&master_mint).1,
+        _ => 0,
+    }
+}
+
+/// Decodes distributor state account data, whatever version it was written with
+pub fn decode_distributor(data: &[u8]) -> Result<DistributorAccount, ProgramError> {
+    DistributorAccount::from_slice(data)
+}
+
+/// Decodes proof of receipt account data, whatever version it was written with
+pub fn decode_proof_of_receipt(data: &[u8]) -> Result<ProofOfReceiptAccount, ProgramError> {
+    ProofOfReceiptAccount::from_slice(data)
+}
+
+pub fn decode_attestation(data: &[u8]) -> Result<AttributeAttestationAccount, ProgramError> {
+    AttributeAttestationAccount::from_slice(data)
+}
+
+pub fn decode_wallet_claim_record(data: &[u8]) -> Result<WalletClaimRecordAccount, ProgramError> {
+    WalletClaimRecordAccount::from_slice(data)
+}
+
+pub fn decode_referral_record(data: &[u8]) -> Result<ReferralRecordAccount, ProgramError> {
+    ReferralRecordAccount::from_slice(data)
+}
+
+/// Creates a CreateTokenDistributor instruction, deriving the distributor state account
+pub fn create_distributor(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    reward_token_account: &Pubkey,
+    collection_creator: &Pubkey,
+    args: CreateTokenDistributorArgs,
+) -> Instruction {
+    let (distributor_state, _bump_seed) = find_distributor_state_address(program_id, authority, args.distributor_id);
+    let accounts = CreateTokenDistributorAccounts {
+        authority_account: *authority,
+        distributor_state_account: distributor_state,
+        reward_token_account: *reward_token_account,
+        collection_creator_account: *collection_creator,
+        rent_sysvar: sysvar::rent::id(),
+        token_program_account: spl_token::ID,
+        system_program_account: system_program::id(),
+    };
+    instruction::create_token_distributor(*program_id, accounts, args)
+}
+
+/// Creates a ClaimTokens instruction, deriving every account from the distributor state and the NFT mint.
+/// `referral` is the referrer and their reward token account, `allowlist_entry` the amount and merkle proof of the
+/// NFT mint for a distributor with an allowlist.
+#[allow(clippy::too_many_arguments)]
+pub fn claim_tokens(
+    program_id: &Pubkey,
+    distributor_state: &Pubkey,
+    distributor: &DistributorAccount,
+    claimant: &Pubkey,
+    claimant_reward_account: &Pubkey,
+    claimant_nft_account: &Pubkey,
+    nft_mint: &Pubkey,
+    referral: Option<(Pubkey, Pubkey)>,
+    allowlist_entry: Option<(u64, Vec<[u8; 32]>)>,
+) -> Instruction {
+    let (nft_metadata_account, metadata_bump) = find_metadata_address(nft_mint);
+    let (nft_edition_account, edition_bump) = find_edition_address(nft_mint);
+    let (allowlist_amount, merkle_proof) = allowlist_entry.unwrap_or_default();
+    let accounts = ClaimTokensAccounts {
+        claimant_main_account: *claimant,
+        distributor_state_account: *distributor_state,
+        distributor_reward_account: distributor.reward_token_account,
+        claimant_reward_account: *claimant_reward_account,
+        pda_account: find_distributor_authority(program_id, distributor_state).0,
+        claimant_nft_account: *claimant_nft_account,
+        nft_metadata_account,
+        nft_edition_account,
+        attribute_attestation_account: attestation_account(program_id, distributor, nft_mint),
+        proof_of_receipt_account: find_receipt_address(program_id, nft_mint, distributor_state).0,
+        wallet_claim_record_account: find_wallet_claim_record_address(program_id, distributor_state, claimant).0,
+        fee_treasury_account: distributor.fee_treasury,
+        clock_sysvar: sysvar::clock::id(),
+        rent_sysvar: sysvar::rent::id(),
+        token_program_account: spl_token::ID,
+        system_program_account: system_program::id(),
+        referral_accounts: referral.map(|(referrer, referrer_reward_account)| ReferralAccounts {
+            referrer_main_account: referrer,
+            referrer_reward_account,
+            referral_record_account: find_referral_record_address(program_id, distributor_state, &referrer).0,
+        }),
+    };
+    let args = ClaimTokensArgs {
+        metadata_bump,
+        edition_bump,
+        parent_edition_bump: find_parent_edition_bump(distributor),
+        allowlist_amount,
+        merkle_proof,
+    };
+    instruction::claim_tokens(*program_id, accounts, args)
+}
+
+/// Creates an AttestAttributes instruction, deriving the attestation account
+pub fn attest_attributes(program_id: &Pubkey, oracle: &Pubkey, nft_mint: &Pubkey, trait_hashes: Vec<[u8; 32]>) -> Instruction {
+    let (attestation, _bump_seed) = find_attestation_address(program_id, oracle, nft_mint);
+    instruction::attest_attributes(*program_id, *oracle, attestation, *nft_mint, trait_hashes)
+}
+
+/// Creates a MigrateDistributor instruction for the authority's distributor
+pub fn migrate_distributor(program_id: &Pubkey, authority: &Pubkey, distributor_id: u64) -> Instruction {
+    let (distributor_state, _bump_seed) = find_distributor_state_address(program_id, authority, distributor_id);
+    instruction::migrate_distributor(*program_id, *authority, distributor_state)
+}
+
+/// Creates a SetPaused instruction for the distributor, signed by its authority
+pub fn set_paused(program_id: &Pubkey, distributor_state: &Pubkey, distributor: &DistributorAccount, paused: bool) -> Instruction {
+    instruction::set_paused(*program_id, distributor.authority, *distributor_state, paused)
+}
+
+/// Creates a CloseDistributor instruction, the tokens left in the vault go to `authority_reward_account`
+pub fn close_distributor(
+    program_id: &Pubkey,
+    distributor_state: &Pubkey,
+    distributor: &DistributorAccount,
+    authority_reward_account: &Pubkey,
+) -> Instruction {
+    instruction::close_distributor(
+        *program_id,
+        distributor.authority,
+        *distributor_state,
+        distributor.reward_token_account,
+        *authority_reward_account,
+        find_distributor_authority(program_id, distributor_state).0,
+    )
+}
+
+/// Creates a GetClaimStatus instruction, deriving every account from the distributor state and the NFT mint.
+/// A holder's NFT account, when given, is checked to hold the NFT as claims check it, and `allowlist_entry`, the amount
+/// and merkle proof of the NFT mint, is checked against the distributor allowlist.
+pub fn get_claim_status(
+    program_id: &Pubkey,
+    distributor_state: &Pubkey,
+    distributor: &DistributorAccount,
+    nft_mint: &Pubkey,
+    claimant_nft_account: Option<&Pubkey>,
+    allowlist_entry: Option<(u64, Vec<[u8; 32]>)>,
+) -> Instruction {
+    let (nft_metadata_account, metadata_bump) = find_metadata_address(nft_mint);
+    let (nft_edition_account, edition_bump) = find_edition_address(nft_mint);
+    let (allowlist_amount, merkle_proof) = allowlist_entry.unwrap_or_default();
+    instruction::get_claim_status(
+        *program_id,
+        *distributor_state,
+        *nft_mint,
+        nft_metadata_account,
+        nft_edition_account,
+        attestation_account(program_id, distributor, nft_mint),
+        find_receipt_address(program_id, nft_mint, distributor_state).0,
+        claimant_nft_account.copied(),
+        metadata_bump,
+        edition_bump,
+        find_parent_edition_bump(distributor),
+        allowlist_amount,
+        merkle_proof,
+    )
+}
+
+// the attestation is only read if the distributor has a trait filter, any account does otherwise
+fn attestation_account(program_id: &Pubkey, distributor: &DistributorAccount, nft_mint: &Pubkey) -> Pubkey {
+    match &distributor.trait_filter {
+        Some(trait_filter) => find_attestation_address(program_id, &trait_filter.oracle, nft_mint).0,
+        None => Pubkey::default(),
+    }
+}
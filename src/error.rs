@@ -70,7 +70,119 @@ pub enum DistributorError {
 
     // Tokens already claimed
     #[error("Tokens already claimed")]
-    TokensAlreadyClaimed
+    TokensAlreadyClaimed,
+
+    // Invalid edition account
+    #[error("Invalid edition account")]
+    InvalidEditionAccount,
+
+    // Edition not eligible under the distributor edition policy
+    #[error("Edition not eligible")]
+    EditionNotEligible,
+
+    // Invalid attribute attestation account
+    #[error("Invalid attribute attestation account")]
+    InvalidAttestation,
+
+    // Too many traits in attribute attestation
+    #[error("Too many traits in attribute attestation")]
+    TooManyTraits,
+
+    // Required trait not attested for NFT
+    #[error("Required trait not attested")]
+    TraitNotAttested,
+
+    // Name or uri prefix longer than the metadata field, or uri prefix not ending with '/'
+    #[error("Invalid metadata prefix")]
+    InvalidMetadataPrefix,
+
+    // Incorrect name
+    #[error("Incorrect name in metadata")]
+    IncorrectName,
+
+    // Incorrect uri
+    #[error("Incorrect uri in metadata")]
+    IncorrectUri,
+
+    // Invalid wallet claim record account
+    #[error("Invalid wallet claim record account")]
+    InvalidWalletClaimRecord,
+
+    // Wallet has reached max_claims_per_wallet
+    #[error("Wallet claim limit reached")]
+    WalletClaimLimitReached,
+
+    // Invalid fee treasury account
+    #[error("Invalid fee treasury account")]
+    InvalidFeeTreasury,
+
+    // Referral basis points above 10000
+    #[error("Invalid referral basis points")]
+    InvalidReferralBps,
+
+    // Claimant named themselves as referrer
+    #[error("Self referral not allowed")]
+    SelfReferral,
+
+    // Invalid referral record account
+    #[error("Invalid referral record account")]
+    InvalidReferralRecord,
+
+    // Claim would take amount claimed above reward_amount_total
+    #[error("Reward pool exhausted")]
+    RewardPoolExhausted,
+
+    // Account written with an older layout version
+    #[error("Account not migrated to the current version")]
+    AccountNotMigrated,
+
+    // Account already initialized
+    #[error("Account already initialized")]
+    AlreadyInitialized,
+
+    // Account not initialized
+    #[error("Account not initialized")]
+    Uninitialized,
+
+    // Account written by the instruction passed read only
+    #[error("Account not writable")]
+    AccountNotWritable,
+
+    // Token program account is not the token program
+    #[error("Invalid token program")]
+    InvalidTokenProgram,
+
+    // Rent sysvar account is not the rent sysvar
+    #[error("Invalid rent sysvar")]
+    InvalidRentSysvar,
+
+    // Clock sysvar account is not the clock sysvar
+    #[error("Invalid clock sysvar")]
+    InvalidClockSysvar,
+
+    // Token account not owned by the token program
+    #[error("Invalid token account")]
+    InvalidTokenAccount,
+
+    // Collection symbol longer than the metadata symbol field or with control characters
+    #[error("Invalid collection symbol")]
+    InvalidSymbol,
+
+    // Claimant NFT account does not hold the NFT
+    #[error("NFT not held by the claimant NFT account")]
+    NftNotHeld,
+
+    // A claim counter would overflow
+    #[error("Claim count overflow")]
+    ClaimCountOverflow,
+
+    // NFT mint not in the distributor allowlist, or not with the amount given
+    #[error("NFT mint not in the allowlist")]
+    NotAllowlisted,
+
+    // Distributor paused by its authority
+    #[error("Distributor is paused")]
+    DistributorPaused
 }
 
 impl PrintProgramError for DistributorError {
@@ -0,0 +1,154 @@
+use borsh::{BorshSerialize, BorshDeserialize};
+use solana_program::{
+    log::sol_log_data,
+    pubkey::Pubkey,
+};
+use std::str::FromStr;
+
+/// First field of every event log, so events can be told apart from other `Program data:` logs
+pub const EVENT_TAG: &[u8] = b"nft_claim_tokens_event";
+/// Prefix the runtime puts in front of `sol_log_data` logs
+pub const PROGRAM_DATA_LOG_PREFIX: &str = "Program data: ";
+
+/// Events logged by the program, Borsh encoded then base64 encoded by the runtime
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+pub enum DistributorEvent {
+    DistributorCreated {
+        distributor: Pubkey,
+        authority: Pubkey,
+        reward_mint: Pubkey,
+        reward_amount_total: u64,
+        collection_creator: Pubkey,
+    },
+    TokensClaimed {
+        distributor: Pubkey,
+        claimant: Pubkey,
+        nft_mint: Pubkey,
+        amount: u64,
+        total_claimed: u64,
+    },
+    ReferralPaid {
+        distributor: Pubkey,
+        referrer: Pubkey,
+        claimant: Pubkey,
+        amount: u64,
+    },
+    AttributesAttested {
+        oracle: Pubkey,
+        nft_mint: Pubkey,
+        trait_count: u8,
+    },
+    DistributorMigrated {
+        distributor: Pubkey,
+        from_version: u8,
+        to_version: u8,
+    },
+    DistributorPauseSet {
+        distributor: Pubkey,
+        paused: bool,
+    },
+    DistributorClosed {
+        distributor: Pubkey,
+        authority: Pubkey,
+        remaining_amount: u64,
+    },
+}
+
+impl DistributorEvent {
+    /// Logs the event as `Program data: <tag> <event>`
+    pub fn emit(&self) {
+        // serializing to a vec cannot fail
+        sol_log_data(&[EVENT_TAG, &self.try_to_vec().unwrap()]);
+    }
+}
+
+/// Decodes a single log line, None if it is not an event of this program's layout.
+/// NOTE: any program can log the same bytes, use `decode_program_events` to only keep the ones this program logged.
+pub fn decode_event_log(log: &str) -> Option<DistributorEvent> {
+    let mut fields = log.strip_prefix(PROGRAM_DATA_LOG_PREFIX)?.split(' ');
+    let tag = base64::decode(fields.next()?).ok()?;
+    if tag != EVENT_TAG {
+        return None;
+    }
+    let data = base64::decode(fields.next()?).ok()?;
+    if fields.next().is_some() {
+        return None;
+    }
+    DistributorEvent::try_from_slice(&data).ok()
+}
+
+/// Decodes the events of a transaction's log messages that were logged by `program_id` itself,
+/// following the `Program <id> invoke [n]` / `Program <id> success|failed` lines of the runtime.
+pub fn decode_program_events(program_id: &Pubkey, logs: &[String]) -> Vec<DistributorEvent> {
+    let mut invocations: Vec<bool> = vec![];
+    let mut events = vec![];
+    for log in logs {
+        if let Some((program, status)) = log.strip_prefix("Program ").and_then(|rest| rest.split_once(' ')) {
+            if let Ok(program) = Pubkey::from_str(program) {
+                if status.starts_with("invoke [") {
+                    invocations.push(program == *program_id);
+                    continue;
+                }
+                if status == "success" || status.starts_with("failed") {
+                    invocations.pop();
+                    continue;
+                }
+            }
+        }
+        if invocations.last() == Some(&true) {
+            events.extend(decode_event_log(log));
+        }
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event_log(event: &DistributorEvent) -> String {
+        format!("{}{} {}", PROGRAM_DATA_LOG_PREFIX, base64::encode(EVENT_TAG), base64::encode(event.try_to_vec().unwrap()))
+    }
+
+    fn tokens_claimed() -> DistributorEvent {
+        DistributorEvent::TokensClaimed {
+            distributor: Pubkey::new_unique(),
+            claimant: Pubkey::new_unique(),
+            nft_mint: Pubkey::new_unique(),
+            amount: 10,
+            total_claimed: 30,
+        }
+    }
+
+    #[test]
+    fn test_decode_event_log() {
+        let event = tokens_claimed();
+        assert_eq!(decode_event_log(&event_log(&event)), Some(event));
+        assert_eq!(decode_event_log("Program log: Instruction: Claim Tokens"), None);
+        // data logged by another program without the event tag
+        let untagged = format!("{}{}", PROGRAM_DATA_LOG_PREFIX, base64::encode(tokens_claimed().try_to_vec().unwrap()));
+        assert_eq!(decode_event_log(&untagged), None);
+    }
+
+    #[test]
+    fn test_decode_program_events_skips_other_programs() {
+        let program_id = Pubkey::new_unique();
+        let other_program_id = Pubkey::new_unique();
+        let claimed = tokens_claimed();
+        let spoofed = tokens_claimed();
+        let logs = vec![
+            format!("Program {} invoke [1]", program_id),
+            "Program log: Instruction: Claim Tokens".to_string(),
+            format!("Program {} invoke [2]", other_program_id),
+            event_log(&spoofed),
+            format!("Program {} success", other_program_id),
+            event_log(&claimed),
+            format!("Program {} consumed 1000 of 200000 compute units", program_id),
+            format!("Program {} success", program_id),
+            format!("Program {} invoke [1]", other_program_id),
+            event_log(&spoofed),
+            format!("Program {} success", other_program_id),
+        ];
+        assert_eq!(decode_program_events(&program_id, &logs), vec![claimed]);
+    }
+}
@@ -1,109 +1,818 @@
 use borsh::{BorshSerialize, BorshDeserialize};
 use solana_program::{
+    account_info::AccountInfo,
     instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
     pubkey::Pubkey,
+    system_program,
     sysvar,
     // _msg
 };
+use std::convert::TryFrom;
+use crate::{
+    error::DistributorError,
+    state::{EditionPolicy, TraitFilter},
+    utils::{next_account_checked, AccountCheck::{Key, Owner, Signer, Writable}},
+};
 
 #[repr(C)]
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
 /// Args for create game
 pub struct CreateTokenDistributorArgs {
+    pub distributor_id: u64,
     pub reward_amount_total: u64,
     pub reward_amount_per_nft: u64,
     pub start_ts: i64,
     pub collection_symbol: String,
+    pub reward_amount_per_print: u64,
+    pub edition_policy: EditionPolicy,
+    pub trait_filter: Option<TraitFilter>,
+    pub name_prefix: Option<String>,
+    pub uri_prefix: Option<String>,
+    pub max_claims_per_wallet: Option<u64>,
+    pub claim_fee_lamports: u64,
+    pub fee_treasury: Pubkey,
+    pub referral_bps: u16,
+    pub merkle_root: Option<[u8; 32]>,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+/// Args for attest attributes
+pub struct AttestAttributesArgs {
+    pub trait_hashes: Vec<[u8; 32]>,
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+/// Args for claim tokens
+pub struct ClaimTokensArgs {
+    pub metadata_bump: u8, // bump seed of the nft_metadata_account PDA
+    pub edition_bump: u8, // bump seed of the nft_edition_account PDA
+    pub parent_edition_bump: u8, // bump seed of the master edition PDA of a PrintsWithParent policy, unused for other policies
+    pub allowlist_amount: u64, // amount of the NFT mint's allowlist entry, unused without merkle_root
+    pub merkle_proof: Vec<[u8; 32]>, // siblings of the allowlist entry from the leaf up, unused without merkle_root
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+/// Args for get claim status
+pub struct GetClaimStatusArgs {
+    pub metadata_bump: u8, // bump seed of the nft_metadata_account PDA
+    pub edition_bump: u8, // bump seed of the nft_edition_account PDA
+    pub parent_edition_bump: u8, // bump seed of the master edition PDA of a PrintsWithParent policy, unused for other policies
+    pub allowlist_amount: u64, // amount of the NFT mint's allowlist entry, unused without merkle_root
+    pub merkle_proof: Vec<[u8; 32]>, // siblings of the allowlist entry from the leaf up, unused without merkle_root
+}
+
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+/// Args for set paused
+pub struct SetPausedArgs {
+    pub paused: bool, // true to pause claims, false to resume them
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PartialEq, Debug, Clone)]
+/// Return data of get claim status
+pub struct ClaimStatus {
+    pub eligible: bool, // a claim with this NFT would succeed now (wallet limits and fees aside)
+    pub ineligibility_error: Option<u64>, // error code a claim would fail with, None if eligible
+    pub already_claimed: bool,
+    pub claimable_amount: u64, // reward a claim with this NFT would pay now, 0 if not eligible
+    pub remaining_pool: u64, // reward_amount_total - amount_claimed
+    pub seconds_since_start: i64, // now - start_ts, negative before the distribution starts
 }
 
 /// Instructions supported by the Casino program.
+#[allow(clippy::large_enum_variant)]
 #[derive(BorshSerialize, BorshDeserialize, Clone)]
 pub enum ClaimTokenInstruction {
-    // [signer] authority_account
-    // [writable] distributor_state_account
+    // [signer] authority_account (pays for the distributor state account)
+    // [writable] distributor_state_account (PDA derived from "distributor_state", authority and distributor_id)
     // [writable] reward_token_account
     // [] collection_creator_account
     // [] rent sysvar
     // [] token_program_account
+    // [] system_program_account
     CreateTokenDistributor(CreateTokenDistributorArgs),
 
-    // [signer] claimant_main_account
+    // accounts are parsed through ClaimTokensAccounts
+    // [signer, writable] claimant_main_account (pays for the receipt and the claim fee)
     // [writable] distributor_state_account (increment amount claimed)
     // [writable] distributor_reward_account (holds the tokens)
     // [writable] claimant_reward_account (receives the tokens)
     // [] pda (has authority to transfer distributor_reward_account tokens)
     // [] claimant_nft_account (holds the claimant's NFT)
     // [] nft_metadata_account (holds the metadata about the NFT account - must match the collection_creator and collection_name fields)
-    // [] pda_proof_of_receipt 
+    // [] nft_edition_account (master edition or edition PDA of the NFT mint - must match the distributor edition_policy - may be empty under Both)
+    // [] attribute_attestation_account (PDA of the trait_filter oracle and NFT mint - only checked if the distributor has a trait_filter)
+    // [writable] proof_of_receipt_account (PDA derived from "claimed", NFT mint and distributor - created on claim)
+    // [writable] wallet_claim_record_account (PDA derived from "wallet_claims", distributor and claimant - only used if the distributor has max_claims_per_wallet)
+    // [writable] fee_treasury_account (receives claim_fee_lamports from the claimant - must match the distributor fee_treasury)
     // [] clock sysvar (check now is after start_ts)
+    // [] rent sysvar
     // [] token_program_account (transfers tokens to claimant)
-    ClaimTokens(),
+    // [] system_program_account
+    // optional, to pay a referral bonus:
+    // [] referrer_main_account (must not be the claimant)
+    // [writable] referrer_reward_account (receives the referral bonus)
+    // [writable] referral_record_account (PDA derived from "referral", distributor and referrer)
+    ClaimTokens(ClaimTokensArgs),
+
+    // [signer] oracle_account (pays for the attestation account)
+    // [writable] attribute_attestation_account (PDA derived from "attestation", oracle and NFT mint)
+    // [] nft_mint_account
+    // [] rent sysvar
+    // [] system_program_account
+    AttestAttributes(AttestAttributesArgs),
+
+    // [signer] authority_account (pays for the extra space)
+    // [writable] distributor_state_account (reallocated and upgraded to the current version)
+    // [] rent sysvar
+    // [] system_program_account
+    MigrateDistributor(),
+
+    // read only, sets a ClaimStatus as return data
+    // [] distributor_state_account
+    // [] nft_mint_account
+    // [] nft_metadata_account (PDA of the NFT mint)
+    // [] nft_edition_account (master edition or edition PDA of the NFT mint)
+    // [] attribute_attestation_account (PDA of the trait_filter oracle and NFT mint - only checked if the distributor has a trait_filter)
+    // [] proof_of_receipt_account (PDA derived from "claimed", NFT mint and distributor)
+    // [] clock sysvar
+    // optional, to check a holder as claims do:
+    // [] claimant_nft_account (token account of the NFT mint)
+    GetClaimStatus(GetClaimStatusArgs),
+
+    // [signer] authority_account
+    // [writable] distributor_state_account
+    SetPaused(SetPausedArgs),
+
+    // [signer, writable] authority_account (receives the lamports of the closed accounts)
+    // [writable] distributor_state_account (closed)
+    // [writable] distributor_reward_account (emptied and closed)
+    // [writable] authority_reward_account (receives the remaining tokens)
+    // [] pda (has authority over distributor_reward_account)
+    // [] token_program_account
+    CloseDistributor(),
+}
+
+/// Accounts of a CreateTokenDistributor instruction, in instruction order
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CreateTokenDistributorAccounts<T = Pubkey> {
+    pub authority_account: T,
+    pub distributor_state_account: T,
+    pub reward_token_account: T,
+    pub collection_creator_account: T,
+    pub rent_sysvar: T,
+    pub token_program_account: T,
+    pub system_program_account: T,
+}
+
+impl CreateTokenDistributorAccounts {
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.authority_account, true),
+            AccountMeta::new(self.distributor_state_account, false),
+            AccountMeta::new(self.reward_token_account, false),
+            AccountMeta::new_readonly(self.collection_creator_account, false),
+            AccountMeta::new_readonly(self.rent_sysvar, false),
+            AccountMeta::new_readonly(self.token_program_account, false),
+            AccountMeta::new_readonly(self.system_program_account, false),
+        ]
+    }
+}
+
+impl<'a, 'b> TryFrom<&'a [AccountInfo<'b>]> for CreateTokenDistributorAccounts<&'a AccountInfo<'b>> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo<'b>]) -> Result<Self, Self::Error> {
+        let account_info_iter = &mut accounts.iter();
+        Ok(CreateTokenDistributorAccounts {
+            authority_account: next_account_checked(account_info_iter, "authority", &[Signer, Writable])?,
+            distributor_state_account: next_account_checked(account_info_iter, "distributor state", &[Writable])?,
+            reward_token_account: next_account_checked(account_info_iter, "reward token", &[Writable, Owner(spl_token::ID, DistributorError::InvalidTokenAccount)])?,
+            collection_creator_account: next_account_checked(account_info_iter, "collection creator", &[])?,
+            rent_sysvar: next_account_checked(account_info_iter, "rent sysvar", &[Key(sysvar::rent::ID, DistributorError::InvalidRentSysvar)])?,
+            token_program_account: next_account_checked(account_info_iter, "token program", &[Key(spl_token::ID, DistributorError::InvalidTokenProgram)])?,
+            system_program_account: next_account_checked(account_info_iter, "system program", &[Key(system_program::ID, DistributorError::InvalidSystemProgram)])?,
+        })
+    }
 }
 
 /// Creates an CreateTokenDistributor instruction
-#[allow(clippy::too_many_arguments)]
 pub fn create_token_distributor(
+    program_id: Pubkey,
+    accounts: CreateTokenDistributorAccounts,
+    args: CreateTokenDistributorArgs,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: accounts.to_account_metas(),
+        data: ClaimTokenInstruction::CreateTokenDistributor(args)
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+/// Optional referrer accounts of a ClaimTokens instruction
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReferralAccounts<T = Pubkey> {
+    pub referrer_main_account: T,
+    pub referrer_reward_account: T,
+    pub referral_record_account: T,
+}
+
+/// Accounts of a ClaimTokens instruction, in instruction order.
+/// The builder emits `ClaimTokensAccounts<Pubkey>` and the processor parses `ClaimTokensAccounts<&AccountInfo>`,
+/// so the two cannot drift apart.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClaimTokensAccounts<T = Pubkey> {
+    pub claimant_main_account: T,
+    pub distributor_state_account: T,
+    pub distributor_reward_account: T,
+    pub claimant_reward_account: T,
+    pub pda_account: T,
+    pub claimant_nft_account: T,
+    pub nft_metadata_account: T,
+    pub nft_edition_account: T,
+    pub attribute_attestation_account: T,
+    pub proof_of_receipt_account: T,
+    pub wallet_claim_record_account: T,
+    pub fee_treasury_account: T,
+    pub clock_sysvar: T,
+    pub rent_sysvar: T,
+    pub token_program_account: T,
+    pub system_program_account: T,
+    pub referral_accounts: Option<ReferralAccounts<T>>,
+}
+
+impl ClaimTokensAccounts {
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        let mut accounts = vec![
+            AccountMeta::new(self.claimant_main_account, true),
+            AccountMeta::new(self.distributor_state_account, false),
+            AccountMeta::new(self.distributor_reward_account, false),
+            AccountMeta::new(self.claimant_reward_account, false),
+            AccountMeta::new_readonly(self.pda_account, false),
+            AccountMeta::new_readonly(self.claimant_nft_account, false),
+            AccountMeta::new_readonly(self.nft_metadata_account, false),
+            AccountMeta::new_readonly(self.nft_edition_account, false),
+            AccountMeta::new_readonly(self.attribute_attestation_account, false),
+            AccountMeta::new(self.proof_of_receipt_account, false),
+            AccountMeta::new(self.wallet_claim_record_account, false),
+            AccountMeta::new(self.fee_treasury_account, false),
+            AccountMeta::new_readonly(self.clock_sysvar, false),
+            AccountMeta::new_readonly(self.rent_sysvar, false),
+            AccountMeta::new_readonly(self.token_program_account, false),
+            AccountMeta::new_readonly(self.system_program_account, false),
+        ];
+        if let Some(referral_accounts) = &self.referral_accounts {
+            accounts.push(AccountMeta::new_readonly(referral_accounts.referrer_main_account, false));
+            accounts.push(AccountMeta::new(referral_accounts.referrer_reward_account, false));
+            accounts.push(AccountMeta::new(referral_accounts.referral_record_account, false));
+        }
+        accounts
+    }
+}
+
+impl<'a, 'b> TryFrom<&'a [AccountInfo<'b>]> for ClaimTokensAccounts<&'a AccountInfo<'b>> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo<'b>]) -> Result<Self, Self::Error> {
+        let account_info_iter = &mut accounts.iter();
+        Ok(ClaimTokensAccounts {
+            claimant_main_account: next_account_checked(account_info_iter, "claimant", &[Signer, Writable])?,
+            distributor_state_account: next_account_checked(account_info_iter, "distributor state", &[Writable])?,
+            distributor_reward_account: next_account_checked(account_info_iter, "distributor reward", &[Writable, Owner(spl_token::ID, DistributorError::InvalidTokenAccount)])?,
+            claimant_reward_account: next_account_checked(account_info_iter, "claimant reward", &[Writable, Owner(spl_token::ID, DistributorError::InvalidTokenAccount)])?,
+            pda_account: next_account_checked(account_info_iter, "pda", &[])?,
+            claimant_nft_account: next_account_checked(account_info_iter, "claimant nft", &[Owner(spl_token::ID, DistributorError::InvalidTokenAccount)])?,
+            nft_metadata_account: next_account_checked(account_info_iter, "nft metadata", &[])?,
+            nft_edition_account: next_account_checked(account_info_iter, "nft edition", &[])?,
+            attribute_attestation_account: next_account_checked(account_info_iter, "attribute attestation", &[])?,
+            proof_of_receipt_account: next_account_checked(account_info_iter, "proof of receipt", &[Writable])?,
+            wallet_claim_record_account: next_account_checked(account_info_iter, "wallet claim record", &[Writable])?,
+            fee_treasury_account: next_account_checked(account_info_iter, "fee treasury", &[Writable])?,
+            clock_sysvar: next_account_checked(account_info_iter, "clock sysvar", &[Key(sysvar::clock::ID, DistributorError::InvalidClockSysvar)])?,
+            rent_sysvar: next_account_checked(account_info_iter, "rent sysvar", &[Key(sysvar::rent::ID, DistributorError::InvalidRentSysvar)])?,
+            token_program_account: next_account_checked(account_info_iter, "token program", &[Key(spl_token::ID, DistributorError::InvalidTokenProgram)])?,
+            system_program_account: next_account_checked(account_info_iter, "system program", &[Key(system_program::ID, DistributorError::InvalidSystemProgram)])?,
+            // optional referrer accounts - all three or none
+            referral_accounts: match account_info_iter.len() {
+                0 => None,
+                3 => Some(ReferralAccounts {
+                    referrer_main_account: next_account_checked(account_info_iter, "referrer", &[])?,
+                    referrer_reward_account: next_account_checked(account_info_iter, "referrer reward", &[Writable, Owner(spl_token::ID, DistributorError::InvalidTokenAccount)])?,
+                    referral_record_account: next_account_checked(account_info_iter, "referral record", &[Writable])?,
+                }),
+                _ => return Err(DistributorError::InvalidAccounts.into()),
+            },
+        })
+    }
+}
+
+/// Creates a ClaimTokens instruction
+pub fn claim_tokens(
+    program_id: Pubkey,
+    accounts: ClaimTokensAccounts,
+    args: ClaimTokensArgs,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: accounts.to_account_metas(),
+        data: ClaimTokenInstruction::ClaimTokens(args)
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+/// Accounts of an AttestAttributes instruction, in instruction order
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AttestAttributesAccounts<T = Pubkey> {
+    pub oracle_account: T,
+    pub attribute_attestation_account: T,
+    pub nft_mint_account: T,
+    pub rent_sysvar: T,
+    pub system_program_account: T,
+}
+
+impl AttestAttributesAccounts {
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.oracle_account, true),
+            AccountMeta::new(self.attribute_attestation_account, false),
+            AccountMeta::new_readonly(self.nft_mint_account, false),
+            AccountMeta::new_readonly(self.rent_sysvar, false),
+            AccountMeta::new_readonly(self.system_program_account, false),
+        ]
+    }
+}
+
+impl<'a, 'b> TryFrom<&'a [AccountInfo<'b>]> for AttestAttributesAccounts<&'a AccountInfo<'b>> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo<'b>]) -> Result<Self, Self::Error> {
+        let account_info_iter = &mut accounts.iter();
+        Ok(AttestAttributesAccounts {
+            oracle_account: next_account_checked(account_info_iter, "oracle", &[Signer, Writable])?,
+            attribute_attestation_account: next_account_checked(account_info_iter, "attribute attestation", &[Writable])?,
+            nft_mint_account: next_account_checked(account_info_iter, "nft mint", &[Owner(spl_token::ID, DistributorError::InvalidMint)])?,
+            rent_sysvar: next_account_checked(account_info_iter, "rent sysvar", &[Key(sysvar::rent::ID, DistributorError::InvalidRentSysvar)])?,
+            system_program_account: next_account_checked(account_info_iter, "system program", &[Key(system_program::ID, DistributorError::InvalidSystemProgram)])?,
+        })
+    }
+}
+
+/// Creates an AttestAttributes instruction
+pub fn attest_attributes(
+    program_id: Pubkey,
+    oracle_account: Pubkey,
+    attribute_attestation_account: Pubkey,
+    nft_mint_account: Pubkey,
+    trait_hashes: Vec<[u8; 32]>,
+) -> Instruction {
+    let accounts = AttestAttributesAccounts {
+        oracle_account,
+        attribute_attestation_account,
+        nft_mint_account,
+        rent_sysvar: sysvar::rent::id(),
+        system_program_account: system_program::id(),
+    };
+    Instruction {
+        program_id,
+        accounts: accounts.to_account_metas(),
+        data: ClaimTokenInstruction::AttestAttributes(AttestAttributesArgs {
+            trait_hashes
+        })
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+/// Accounts of a MigrateDistributor instruction, in instruction order
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MigrateDistributorAccounts<T = Pubkey> {
+    pub authority_account: T,
+    pub distributor_state_account: T,
+    pub rent_sysvar: T,
+    pub system_program_account: T,
+}
+
+impl MigrateDistributorAccounts {
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.authority_account, true),
+            AccountMeta::new(self.distributor_state_account, false),
+            AccountMeta::new_readonly(self.rent_sysvar, false),
+            AccountMeta::new_readonly(self.system_program_account, false),
+        ]
+    }
+}
+
+impl<'a, 'b> TryFrom<&'a [AccountInfo<'b>]> for MigrateDistributorAccounts<&'a AccountInfo<'b>> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo<'b>]) -> Result<Self, Self::Error> {
+        let account_info_iter = &mut accounts.iter();
+        Ok(MigrateDistributorAccounts {
+            authority_account: next_account_checked(account_info_iter, "authority", &[Signer, Writable])?,
+            distributor_state_account: next_account_checked(account_info_iter, "distributor state", &[Writable])?,
+            rent_sysvar: next_account_checked(account_info_iter, "rent sysvar", &[Key(sysvar::rent::ID, DistributorError::InvalidRentSysvar)])?,
+            system_program_account: next_account_checked(account_info_iter, "system program", &[Key(system_program::ID, DistributorError::InvalidSystemProgram)])?,
+        })
+    }
+}
+
+/// Creates a MigrateDistributor instruction
+pub fn migrate_distributor(
     program_id: Pubkey,
     authority_account: Pubkey,
     distributor_state_account: Pubkey,
-    reward_token_account: Pubkey,
-    collection_creator_account: Pubkey,
-    reward_amount_total: u64,
-    reward_amount_per_nft: u64,
-    start_ts: i64,
-    collection_symbol: String,
 ) -> Instruction {
+    let accounts = MigrateDistributorAccounts {
+        authority_account,
+        distributor_state_account,
+        rent_sysvar: sysvar::rent::id(),
+        system_program_account: system_program::id(),
+    };
     Instruction {
         program_id,
-        accounts: vec![
-            AccountMeta::new(authority_account, true),
-            AccountMeta::new(distributor_state_account, false),
-            AccountMeta::new(reward_token_account, false),
-            AccountMeta::new_readonly(collection_creator_account, false),
-            AccountMeta::new_readonly(sysvar::rent::id(), false),
-            AccountMeta::new_readonly(spl_token::ID, false),
-        ],
-        data: ClaimTokenInstruction::CreateTokenDistributor(CreateTokenDistributorArgs {
-            reward_amount_total,
-            reward_amount_per_nft,
-            start_ts,
-            collection_symbol
-        })
+        accounts: accounts.to_account_metas(),
+        data: ClaimTokenInstruction::MigrateDistributor()
         .try_to_vec()
         .unwrap(),
     }
 }
 
-/// Creates a ClaimTokens instruction
+/// Accounts of a GetClaimStatus instruction, in instruction order
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GetClaimStatusAccounts<T = Pubkey> {
+    pub distributor_state_account: T,
+    pub nft_mint_account: T,
+    pub nft_metadata_account: T,
+    pub nft_edition_account: T,
+    pub attribute_attestation_account: T,
+    pub proof_of_receipt_account: T,
+    pub clock_sysvar: T,
+    pub claimant_nft_account: Option<T>,
+}
+
+impl GetClaimStatusAccounts {
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        let mut accounts = vec![
+            AccountMeta::new_readonly(self.distributor_state_account, false),
+            AccountMeta::new_readonly(self.nft_mint_account, false),
+            AccountMeta::new_readonly(self.nft_metadata_account, false),
+            AccountMeta::new_readonly(self.nft_edition_account, false),
+            AccountMeta::new_readonly(self.attribute_attestation_account, false),
+            AccountMeta::new_readonly(self.proof_of_receipt_account, false),
+            AccountMeta::new_readonly(self.clock_sysvar, false),
+        ];
+        if let Some(claimant_nft_account) = self.claimant_nft_account {
+            accounts.push(AccountMeta::new_readonly(claimant_nft_account, false));
+        }
+        accounts
+    }
+}
+
+impl<'a, 'b> TryFrom<&'a [AccountInfo<'b>]> for GetClaimStatusAccounts<&'a AccountInfo<'b>> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo<'b>]) -> Result<Self, Self::Error> {
+        let account_info_iter = &mut accounts.iter();
+        Ok(GetClaimStatusAccounts {
+            distributor_state_account: next_account_checked(account_info_iter, "distributor state", &[])?,
+            nft_mint_account: next_account_checked(account_info_iter, "nft mint", &[Owner(spl_token::ID, DistributorError::InvalidMint)])?,
+            nft_metadata_account: next_account_checked(account_info_iter, "nft metadata", &[])?,
+            nft_edition_account: next_account_checked(account_info_iter, "nft edition", &[])?,
+            attribute_attestation_account: next_account_checked(account_info_iter, "attribute attestation", &[])?,
+            proof_of_receipt_account: next_account_checked(account_info_iter, "proof of receipt", &[])?,
+            clock_sysvar: next_account_checked(account_info_iter, "clock sysvar", &[Key(sysvar::clock::ID, DistributorError::InvalidClockSysvar)])?,
+            // optional holder account
+            claimant_nft_account: match account_info_iter.len() {
+                0 => None,
+                1 => Some(next_account_checked(account_info_iter, "claimant nft", &[Owner(spl_token::ID, DistributorError::InvalidTokenAccount)])?),
+                _ => return Err(DistributorError::InvalidAccounts.into()),
+            },
+        })
+    }
+}
+
+/// Creates a GetClaimStatus instruction
 #[allow(clippy::too_many_arguments)]
-pub fn claim_tokens(
+pub fn get_claim_status(
     program_id: Pubkey,
-    claimant_main_account: Pubkey,
     distributor_state_account: Pubkey,
-    distributor_reward_account: Pubkey,
-    claimant_reward_account: Pubkey,
-    pda_account: Pubkey,
-    claimant_nft_account: Pubkey,
+    nft_mint_account: Pubkey,
     nft_metadata_account: Pubkey,
+    nft_edition_account: Pubkey,
+    attribute_attestation_account: Pubkey,
     proof_of_receipt_account: Pubkey,
+    claimant_nft_account: Option<Pubkey>,
+    metadata_bump: u8,
+    edition_bump: u8,
+    parent_edition_bump: u8,
+    allowlist_amount: u64,
+    merkle_proof: Vec<[u8; 32]>,
+) -> Instruction {
+    let accounts = GetClaimStatusAccounts {
+        distributor_state_account,
+        nft_mint_account,
+        nft_metadata_account,
+        nft_edition_account,
+        attribute_attestation_account,
+        proof_of_receipt_account,
+        clock_sysvar: sysvar::clock::id(),
+        claimant_nft_account,
+    };
+    Instruction {
+        program_id,
+        accounts: accounts.to_account_metas(),
+        data: ClaimTokenInstruction::GetClaimStatus(GetClaimStatusArgs {
+            metadata_bump,
+            edition_bump,
+            parent_edition_bump,
+            allowlist_amount,
+            merkle_proof
+        })
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+/// Accounts of a SetPaused instruction, in instruction order
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SetPausedAccounts<T = Pubkey> {
+    pub authority_account: T,
+    pub distributor_state_account: T,
+}
+
+impl SetPausedAccounts {
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new_readonly(self.authority_account, true),
+            AccountMeta::new(self.distributor_state_account, false),
+        ]
+    }
+}
+
+impl<'a, 'b> TryFrom<&'a [AccountInfo<'b>]> for SetPausedAccounts<&'a AccountInfo<'b>> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo<'b>]) -> Result<Self, Self::Error> {
+        let account_info_iter = &mut accounts.iter();
+        Ok(SetPausedAccounts {
+            authority_account: next_account_checked(account_info_iter, "authority", &[Signer])?,
+            distributor_state_account: next_account_checked(account_info_iter, "distributor state", &[Writable])?,
+        })
+    }
+}
+
+/// Creates a SetPaused instruction
+pub fn set_paused(
+    program_id: Pubkey,
+    authority_account: Pubkey,
+    distributor_state_account: Pubkey,
+    paused: bool,
+) -> Instruction {
+    let accounts = SetPausedAccounts {
+        authority_account,
+        distributor_state_account,
+    };
+    Instruction {
+        program_id,
+        accounts: accounts.to_account_metas(),
+        data: ClaimTokenInstruction::SetPaused(SetPausedArgs {
+            paused
+        })
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+/// Accounts of a CloseDistributor instruction, in instruction order
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CloseDistributorAccounts<T = Pubkey> {
+    pub authority_account: T,
+    pub distributor_state_account: T,
+    pub distributor_reward_account: T,
+    pub authority_reward_account: T,
+    pub pda_account: T,
+    pub token_program_account: T,
+}
+
+impl CloseDistributorAccounts {
+    pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.authority_account, true),
+            AccountMeta::new(self.distributor_state_account, false),
+            AccountMeta::new(self.distributor_reward_account, false),
+            AccountMeta::new(self.authority_reward_account, false),
+            AccountMeta::new_readonly(self.pda_account, false),
+            AccountMeta::new_readonly(self.token_program_account, false),
+        ]
+    }
+}
+
+impl<'a, 'b> TryFrom<&'a [AccountInfo<'b>]> for CloseDistributorAccounts<&'a AccountInfo<'b>> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo<'b>]) -> Result<Self, Self::Error> {
+        let account_info_iter = &mut accounts.iter();
+        Ok(CloseDistributorAccounts {
+            authority_account: next_account_checked(account_info_iter, "authority", &[Signer, Writable])?,
+            distributor_state_account: next_account_checked(account_info_iter, "distributor state", &[Writable])?,
+            distributor_reward_account: next_account_checked(account_info_iter, "distributor reward", &[Writable, Owner(spl_token::ID, DistributorError::InvalidTokenAccount)])?,
+            authority_reward_account: next_account_checked(account_info_iter, "authority reward", &[Writable, Owner(spl_token::ID, DistributorError::InvalidTokenAccount)])?,
+            pda_account: next_account_checked(account_info_iter, "pda", &[])?,
+            token_program_account: next_account_checked(account_info_iter, "token program", &[Key(spl_token::ID, DistributorError::InvalidTokenProgram)])?,
+        })
+    }
+}
+
+/// Creates a CloseDistributor instruction
+pub fn close_distributor(
+    program_id: Pubkey,
+    authority_account: Pubkey,
+    distributor_state_account: Pubkey,
+    distributor_reward_account: Pubkey,
+    authority_reward_account: Pubkey,
+    pda_account: Pubkey,
 ) -> Instruction {
+    let accounts = CloseDistributorAccounts {
+        authority_account,
+        distributor_state_account,
+        distributor_reward_account,
+        authority_reward_account,
+        pda_account,
+        token_program_account: spl_token::ID,
+    };
     Instruction {
         program_id,
-        accounts: vec![
-            AccountMeta::new(claimant_main_account, true),
-            AccountMeta::new(distributor_state_account, false),
-            AccountMeta::new(distributor_reward_account, false),
-            AccountMeta::new(claimant_reward_account, false),
-            AccountMeta::new_readonly(pda_account, false),
-            AccountMeta::new_readonly(claimant_nft_account, false),
-            AccountMeta::new_readonly(nft_metadata_account, false),
-            AccountMeta::new(proof_of_receipt_account, false),
-            AccountMeta::new_readonly(sysvar::clock::id(), false),
-            AccountMeta::new_readonly(sysvar::rent::id(), false),
-            AccountMeta::new_readonly(spl_token::ID, false),
-        ],
-        data: ClaimTokenInstruction::ClaimTokens()
+        accounts: accounts.to_account_metas(),
+        data: ClaimTokenInstruction::CloseDistributor()
         .try_to_vec()
         .unwrap(),
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(accounts: &ClaimTokensAccounts<&AccountInfo>) -> ClaimTokensAccounts {
+        ClaimTokensAccounts {
+            claimant_main_account: *accounts.claimant_main_account.key,
+            distributor_state_account: *accounts.distributor_state_account.key,
+            distributor_reward_account: *accounts.distributor_reward_account.key,
+            claimant_reward_account: *accounts.claimant_reward_account.key,
+            pda_account: *accounts.pda_account.key,
+            claimant_nft_account: *accounts.claimant_nft_account.key,
+            nft_metadata_account: *accounts.nft_metadata_account.key,
+            nft_edition_account: *accounts.nft_edition_account.key,
+            attribute_attestation_account: *accounts.attribute_attestation_account.key,
+            proof_of_receipt_account: *accounts.proof_of_receipt_account.key,
+            wallet_claim_record_account: *accounts.wallet_claim_record_account.key,
+            fee_treasury_account: *accounts.fee_treasury_account.key,
+            clock_sysvar: *accounts.clock_sysvar.key,
+            rent_sysvar: *accounts.rent_sysvar.key,
+            token_program_account: *accounts.token_program_account.key,
+            system_program_account: *accounts.system_program_account.key,
+            referral_accounts: accounts.referral_accounts.map(|referral_accounts| ReferralAccounts {
+                referrer_main_account: *referral_accounts.referrer_main_account.key,
+                referrer_reward_account: *referral_accounts.referrer_reward_account.key,
+                referral_record_account: *referral_accounts.referral_record_account.key,
+            }),
+        }
+    }
+
+    // unique keys, with the sysvars and programs the processor expects
+    fn claim_tokens_accounts(referral_accounts: Option<ReferralAccounts>) -> ClaimTokensAccounts {
+        ClaimTokensAccounts {
+            claimant_main_account: Pubkey::new_unique(),
+            distributor_state_account: Pubkey::new_unique(),
+            distributor_reward_account: Pubkey::new_unique(),
+            claimant_reward_account: Pubkey::new_unique(),
+            pda_account: Pubkey::new_unique(),
+            claimant_nft_account: Pubkey::new_unique(),
+            nft_metadata_account: Pubkey::new_unique(),
+            nft_edition_account: Pubkey::new_unique(),
+            attribute_attestation_account: Pubkey::new_unique(),
+            proof_of_receipt_account: Pubkey::new_unique(),
+            wallet_claim_record_account: Pubkey::new_unique(),
+            fee_treasury_account: Pubkey::new_unique(),
+            clock_sysvar: sysvar::clock::id(),
+            rent_sysvar: sysvar::rent::id(),
+            token_program_account: spl_token::ID,
+            system_program_account: system_program::id(),
+            referral_accounts,
+        }
+    }
+
+    fn claim_tokens_args() -> ClaimTokensArgs {
+        ClaimTokensArgs {
+            metadata_bump: 0,
+            edition_bump: 0,
+            parent_edition_bump: 0,
+            allowlist_amount: 0,
+            merkle_proof: vec![],
+        }
+    }
+
+    #[test]
+    fn test_claim_tokens_builder_matches_processor_accounts() {
+        let program_id = Pubkey::new_unique();
+        let referral_accounts = ReferralAccounts {
+            referrer_main_account: Pubkey::new_unique(),
+            referrer_reward_account: Pubkey::new_unique(),
+            referral_record_account: Pubkey::new_unique(),
+        };
+        for referral_accounts in [None, Some(referral_accounts)].iter() {
+            let expected = claim_tokens_accounts(*referral_accounts);
+            let instruction = claim_tokens(program_id, expected, claim_tokens_args());
+
+            let mut lamports = vec![0u64; instruction.accounts.len()];
+            let mut data = vec![vec![0u8; 0]; instruction.accounts.len()];
+            let account_infos: Vec<AccountInfo> = instruction.accounts.iter()
+                .zip(lamports.iter_mut())
+                .zip(data.iter_mut())
+                .map(|((meta, lamports), data)| {
+                    AccountInfo::new(&meta.pubkey, meta.is_signer, meta.is_writable, lamports, &mut data[..], &spl_token::ID, false, 0)
+                })
+                .collect();
+
+            // the processor reads every account the builder emits, in the same order
+            let parsed = ClaimTokensAccounts::try_from(&account_infos[..]).unwrap();
+            assert_eq!(keys(&parsed), expected);
+
+            // and the builder marks the accounts the processor signs with or writes to
+            assert!(parsed.claimant_main_account.is_signer && parsed.claimant_main_account.is_writable);
+            for account in [
+                parsed.distributor_state_account,
+                parsed.distributor_reward_account,
+                parsed.claimant_reward_account,
+                parsed.proof_of_receipt_account,
+                parsed.wallet_claim_record_account,
+                parsed.fee_treasury_account,
+            ].iter() {
+                assert!(account.is_writable);
+            }
+            if let Some(referral_accounts) = parsed.referral_accounts {
+                assert!(referral_accounts.referrer_reward_account.is_writable && referral_accounts.referral_record_account.is_writable);
+            }
+
+            // a missing account is not read as an optional one
+            assert!(ClaimTokensAccounts::try_from(&account_infos[..account_infos.len() - 1]).is_err());
+        }
+    }
+
+    fn claim_tokens_parse_error(metas: &[AccountMeta]) -> ProgramError {
+        let mut lamports = vec![0u64; metas.len()];
+        let mut data = vec![vec![0u8; 0]; metas.len()];
+        let account_infos: Vec<AccountInfo> = metas.iter()
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+            .map(|((meta, lamports), data)| {
+                AccountInfo::new(&meta.pubkey, meta.is_signer, meta.is_writable, lamports, &mut data[..], &spl_token::ID, false, 0)
+            })
+            .collect();
+        ClaimTokensAccounts::try_from(&account_infos[..]).unwrap_err()
+    }
+
+    #[test]
+    fn test_claim_tokens_account_checks() {
+        let metas = claim_tokens(Pubkey::new_unique(), claim_tokens_accounts(None), claim_tokens_args()).accounts;
+
+        let mut unsigned = metas.clone();
+        unsigned[0].is_signer = false;
+        assert_eq!(claim_tokens_parse_error(&unsigned), DistributorError::IncorrectSigner.into());
+
+        let mut read_only_receipt = metas.clone();
+        read_only_receipt[9].is_writable = false;
+        assert_eq!(claim_tokens_parse_error(&read_only_receipt), DistributorError::AccountNotWritable.into());
+
+        let mut wrong_rent_sysvar = metas.clone();
+        wrong_rent_sysvar[13].pubkey = Pubkey::new_unique();
+        assert_eq!(claim_tokens_parse_error(&wrong_rent_sysvar), DistributorError::InvalidRentSysvar.into());
+
+        let mut wrong_token_program = metas;
+        wrong_token_program[14].pubkey = Pubkey::new_unique();
+        assert_eq!(claim_tokens_parse_error(&wrong_token_program), DistributorError::InvalidTokenProgram.into());
+    }
+
+    #[test]
+    fn test_claim_tokens_partial_referral_accounts_rejected() {
+        let referral_accounts = ReferralAccounts {
+            referrer_main_account: Pubkey::new_unique(),
+            referrer_reward_account: Pubkey::new_unique(),
+            referral_record_account: Pubkey::new_unique(),
+        };
+        let metas = claim_tokens(Pubkey::new_unique(), claim_tokens_accounts(Some(referral_accounts)), claim_tokens_args()).accounts;
+
+        // the referrer accounts are all three or none, a missing referral record is not taken for no referral
+        for missing in 1..3 {
+            assert_eq!(claim_tokens_parse_error(&metas[..metas.len() - missing]), DistributorError::InvalidAccounts.into());
+        }
+    }
+}
@@ -1,6 +1,9 @@
+pub mod client;
 pub mod entrypoint;
 pub mod instruction;
+pub mod merkle;
 pub mod error;
+pub mod events;
 pub mod processor;
 pub mod state;
 pub mod utils;
\ No newline at end of file
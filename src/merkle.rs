@@ -0,0 +1,45 @@
+//! Merkle allowlists: leaf and node hashing shared by the off-chain tree builder and proof verification,
+//! so proofs built by the tooling are the ones a verifier accepts.
+
+use solana_program::{hash::hashv, pubkey::Pubkey};
+
+// domain separation, a node can never be passed off as a leaf
+pub const LEAF_PREFIX: &[u8] = &[0];
+pub const NODE_PREFIX: &[u8] = &[1];
+
+/// sha256 of a `mint[,amount]` allowlist entry, amount is 0 for entries without one (the distributor reward applies)
+pub fn hash_leaf(mint: &Pubkey, amount: u64) -> [u8; 32] {
+    hashv(&[LEAF_PREFIX, mint.as_ref(), &amount.to_le_bytes()]).to_bytes()
+}
+
+/// sha256 of two sibling nodes, sorted so proofs don't need the side of each sibling
+pub fn hash_nodes(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[NODE_PREFIX, left, right]).to_bytes()
+}
+
+/// Checks the entry is in the tree of the root, the proof being the siblings from the leaf up
+pub fn verify_proof(root: &[u8; 32], mint: &Pubkey, amount: u64, proof: &[[u8; 32]]) -> bool {
+    let computed = proof.iter().fold(hash_leaf(mint, amount), |node, sibling| hash_nodes(&node, sibling));
+    computed == *root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_proof() {
+        let mint = Pubkey::new_unique();
+        let sibling = hash_leaf(&Pubkey::new_unique(), 5);
+        let root = hash_nodes(&sibling, &hash_leaf(&mint, 10));
+
+        assert!(verify_proof(&root, &mint, 10, &[sibling]));
+        assert!(!verify_proof(&root, &mint, 11, &[sibling]));
+        assert!(!verify_proof(&root, &Pubkey::new_unique(), 10, &[sibling]));
+        // a node is not a leaf
+        assert!(!verify_proof(&root, &mint, 10, &[]));
+        // a single leaf tree
+        assert!(verify_proof(&hash_leaf(&mint, 0), &mint, 0, &[]));
+    }
+}
@@ -1,37 +1,79 @@
 use solana_program::{
-    account_info::{next_account_info, AccountInfo},
+    account_info::AccountInfo,
     entrypoint::ProgramResult,
     msg,
+    program::set_return_data,
+    program_error::ProgramError,
     pubkey::Pubkey,
     program_pack::{Pack},
     sysvar::{rent::Rent, Sysvar},
     program::{invoke, invoke_signed},
+    system_instruction,
     clock::{Clock},
-    system_program::{check_id}
 };
 
 use spl_token::state::Account as TokenAccount;
 
 use spl_token_metadata::state::Metadata as MetadataAccount;
+use spl_token_metadata::state::Edition as EditionAccount;
+use spl_token_metadata::state::Key as MetadataKey;
 use spl_token_metadata::error::MetadataError;
 
 use crate::{
     instruction::ClaimTokenInstruction,
+    instruction::CreateTokenDistributorAccounts,
+    instruction::ClaimTokensAccounts,
+    instruction::AttestAttributesAccounts,
+    instruction::MigrateDistributorAccounts,
+    instruction::GetClaimStatusAccounts,
+    instruction::SetPausedAccounts,
+    instruction::CloseDistributorAccounts,
+    instruction::ClaimStatus,
+    instruction::ClaimTokensArgs,
+    instruction::GetClaimStatusArgs,
+    events::DistributorEvent,
     error::DistributorError,
+    merkle::verify_proof,
     utils::PREFIX,
+    utils::DISTRIBUTOR_STATE_PREFIX,
+    utils::find_distributor_state_address,
+    utils::ATTESTATION_PREFIX,
+    utils::WALLET_CLAIMS_PREFIX,
+    utils::REFERRAL_PREFIX,
+    utils::RECEIPT_PREFIX,
+    utils::MAX_BPS,
     utils::create_or_allocate_account_raw,
-    utils::puffed_out_string,
+    utils::puffed_out_symbol,
+    utils::trim_null_padding,
     state::DistributorAccount,
+    state::AccountKey,
+    state::DISTRIBUTOR_VERSION,
+    state::PROOF_OF_RECEIPT_VERSION,
+    state::ATTRIBUTE_ATTESTATION_VERSION,
+    state::WALLET_CLAIM_RECORD_VERSION,
+    state::REFERRAL_RECORD_VERSION,
     state::ProofOfReceiptAccount,
-    state::MAX_SYMBOL_LENGTH
+    state::EditionPolicy,
+    state::TraitFilter,
+    state::AttributeAttestationAccount,
+    state::WalletClaimRecordAccount,
+    state::WALLET_CLAIM_RECORD_DATA_LENGTH,
+    state::ReferralRecordAccount,
+    state::REFERRAL_RECORD_DATA_LENGTH,
+    state::DistributorHeader,
+    state::MAX_ATTESTED_TRAITS,
+    state::MAX_ATTESTATION_DATA_LENGTH,
+    state::MAX_NAME_PREFIX_LENGTH,
+    state::MAX_URI_PREFIX_LENGTH
 };
 
 // use std::convert::TryInto;
+use std::convert::TryFrom;
 use borsh::{BorshSerialize, BorshDeserialize};
 
 pub fn process_instruction<'a>(
-    program_id: &'a Pubkey,
-    accounts: &'a [AccountInfo<'a>],
+    program_id: &Pubkey,
+    accounts: &[AccountInfo<'a>],
     input: &[u8],
 ) -> ProgramResult {
     let instruction = ClaimTokenInstruction::try_from_slice(input)?;
@@ -41,54 +83,137 @@ pub fn process_instruction<'a>(
             process_create_distributor(
                 program_id,
                 accounts,
+                args.distributor_id,
                 args.reward_amount_total,
                 args.reward_amount_per_nft,
                 args.start_ts,
-                args.collection_symbol
+                args.collection_symbol,
+                args.reward_amount_per_print,
+                args.edition_policy,
+                args.trait_filter,
+                args.name_prefix,
+                args.uri_prefix,
+                args.max_claims_per_wallet,
+                args.claim_fee_lamports,
+                args.fee_treasury,
+                args.referral_bps,
+                args.merkle_root
             )
         },
-        ClaimTokenInstruction::ClaimTokens() => {
+        ClaimTokenInstruction::ClaimTokens(args) => {
             msg!("Instruction: Claim Tokens");
             process_claim_tokens(
                 program_id,
                 accounts,
+                args
+            )
+        },
+        ClaimTokenInstruction::AttestAttributes(args) => {
+            msg!("Instruction: Attest Attributes");
+            process_attest_attributes(
+                program_id,
+                accounts,
+                args.trait_hashes
+            )
+        },
+        ClaimTokenInstruction::MigrateDistributor() => {
+            msg!("Instruction: Migrate Distributor");
+            process_migrate_distributor(
+                program_id,
+                accounts,
+            )
+        },
+        ClaimTokenInstruction::GetClaimStatus(args) => {
+            msg!("Instruction: Get Claim Status");
+            process_get_claim_status(
+                program_id,
+                accounts,
+                args
+            )
+        },
+        ClaimTokenInstruction::SetPaused(args) => {
+            msg!("Instruction: Set Paused");
+            process_set_paused(
+                program_id,
+                accounts,
+                args.paused
+            )
+        },
+        ClaimTokenInstruction::CloseDistributor() => {
+            msg!("Instruction: Close Distributor");
+            process_close_distributor(
+                program_id,
+                accounts,
             )
         },
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn process_create_distributor<'a>(
-    program_id: &'a Pubkey,
-    accounts: &'a [AccountInfo<'a>],
+    program_id: &Pubkey,
+    accounts: &[AccountInfo<'a>],
+    distributor_id: u64,
     reward_amount_total: u64,
     reward_amount_per_nft: u64,
     start_ts: i64,
     collection_symbol: String,
+    reward_amount_per_print: u64,
+    edition_policy: EditionPolicy,
+    trait_filter: Option<TraitFilter>,
+    name_prefix: Option<String>,
+    uri_prefix: Option<String>,
+    max_claims_per_wallet: Option<u64>,
+    claim_fee_lamports: u64,
+    fee_treasury: Pubkey,
+    referral_bps: u16,
+    merkle_root: Option<[u8; 32]>,
 ) -> ProgramResult {
-    let account_info_iter = &mut accounts.iter();
-    let authority_account_info = next_account_info(account_info_iter)?;
-    let distributor_state_account_info = next_account_info(account_info_iter)?;
-    let reward_token_account_info = next_account_info(account_info_iter)?;
-    let collection_creator_account_info = next_account_info(account_info_iter)?;
-    let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
-    let token_program_account = next_account_info(account_info_iter)?;
-    spl_token::check_program_account(token_program_account.key)?;
+    // signer, writable flags, sysvar and program ids are checked while parsing
+    let CreateTokenDistributorAccounts {
+        authority_account: authority_account_info,
+        distributor_state_account: distributor_state_account_info,
+        reward_token_account: reward_token_account_info,
+        collection_creator_account: collection_creator_account_info,
+        rent_sysvar: rent_account,
+        token_program_account,
+        system_program_account,
+    } = CreateTokenDistributorAccounts::try_from(accounts)?;
 
-    // check authority_account_info is the tx signer
-    if !authority_account_info.is_signer {
-        return Err(DistributorError::IncorrectSigner.into());
+    // check the distributor state account given is the one derived from "distributor_state", authority key and distributor id
+    let (distributor_state_pubkey, bump_seed) = find_distributor_state_address(program_id, authority_account_info.key, distributor_id);
+
+    if distributor_state_pubkey != *distributor_state_account_info.key {
+        return Err(DistributorError::InvalidAccounts.into());
     }
 
-    // check program is owner of the distributor_state_account_info
-    if distributor_state_account_info.owner != program_id {
-        return Err(DistributorError::IncorrectOwner.into());
+    // check the distributor has not been created already - it would overwrite amount_claimed, creator and symbol
+    if !distributor_state_account_info.data_is_empty() {
+        let distributor_state_account = DistributorAccount::from_account_info(distributor_state_account_info)?;
+        if distributor_state_account.is_initialized {
+            return Err(DistributorError::AlreadyInitialized.into());
+        }
     }
 
-    // check distributor_state_account_info has enough lamports to be rent exempt
-    if !rent.is_exempt(distributor_state_account_info.lamports(), distributor_state_account_info.data_len()) {
-        return Err(DistributorError::NotRentExempt.into());
+    // check the name and uri prefixes fit in the metadata fields they are matched against, and the uri prefix ends at a
+    // path boundary - "https://arweave.net" would also let "https://arweave.net.attacker.com/..." through
+    if name_prefix.as_ref().map_or(false, |prefix| prefix.len() > MAX_NAME_PREFIX_LENGTH) ||
+        uri_prefix.as_ref().map_or(false, |prefix| prefix.len() > MAX_URI_PREFIX_LENGTH || !prefix.ends_with('/')) {
+        return Err(DistributorError::InvalidMetadataPrefix.into());
     }
 
+    if referral_bps > MAX_BPS {
+        return Err(DistributorError::InvalidReferralBps.into());
+    }
+
+    // a claim fee needs a treasury to be paid to, the default pubkey is no account anyone controls
+    if claim_fee_lamports > 0 && fee_treasury == Pubkey::default() {
+        return Err(DistributorError::InvalidFeeTreasury.into());
+    }
+
+    // check the collection symbol fits the metadata symbol field and has no control characters
+    let collection_symbol = puffed_out_symbol(&collection_symbol)?;
+
     // check the reward token account has enough tokens
     let reward_token_account = TokenAccount::unpack(&reward_token_account_info.data.borrow())?;
     if  reward_token_account.amount < reward_amount_total {
@@ -100,7 +225,7 @@ pub fn process_create_distributor<'a>(
         PREFIX.as_bytes(),
         distributor_state_account_info.key.as_ref(),
     ];
-    let (pda, _bump_seed) = Pubkey::find_program_address(distributor_seeds, program_id);
+    let (pda, authority_bump) = Pubkey::find_program_address(distributor_seeds, program_id);
 
     // call token program, set account owner authority of the reward token account to PDA
     let transfer_authority_change_ix = spl_token::instruction::set_authority(
@@ -109,7 +234,7 @@ pub fn process_create_distributor<'a>(
         Some(&pda),
         spl_token::instruction::AuthorityType::AccountOwner,
         authority_account_info.key,
-        &[&authority_account_info.key],
+        &[authority_account_info.key],
     )?;
     msg!("Calling the token program to transfer ownership authority to PDA...");
     invoke(
@@ -120,11 +245,31 @@ pub fn process_create_distributor<'a>(
             token_program_account.clone(),
         ],
     )?;
-    
+
+    let distributor_state_authority_seeds = &[
+        DISTRIBUTOR_STATE_PREFIX.as_bytes(),
+        authority_account_info.key.as_ref(),
+        &distributor_id.to_le_bytes(),
+        &[bump_seed],
+    ];
+
+    // create the account, the authority pays
+    create_or_allocate_account_raw(
+        *program_id,
+        distributor_state_account_info,
+        rent_account,
+        system_program_account,
+        authority_account_info,
+        DistributorAccount::LEN,
+        distributor_state_authority_seeds
+    )?;
+
     // unpack the distributor_state_account_info
-    let mut distributor_state_account = DistributorAccount::from_account_info(&distributor_state_account_info)?;
+    let mut distributor_state_account = DistributorAccount::from_account_info(distributor_state_account_info)?;
 
     // write the data to state
+    distributor_state_account.key = AccountKey::Distributor;
+    distributor_state_account.version = DISTRIBUTOR_VERSION;
     distributor_state_account.is_initialized = true;
     distributor_state_account.authority = *authority_account_info.key;
     distributor_state_account.reward_token_account = *reward_token_account_info.key;
@@ -135,39 +280,63 @@ pub fn process_create_distributor<'a>(
     distributor_state_account.start_ts = start_ts;
     distributor_state_account.collection_symbol = collection_symbol;
     distributor_state_account.collection_creator = *collection_creator_account_info.key;
+    distributor_state_account.reward_amount_per_print = reward_amount_per_print;
+    distributor_state_account.edition_policy = edition_policy;
+    distributor_state_account.trait_filter = trait_filter;
+    distributor_state_account.name_prefix = name_prefix;
+    distributor_state_account.uri_prefix = uri_prefix;
+    distributor_state_account.max_claims_per_wallet = max_claims_per_wallet;
+    distributor_state_account.claim_fee_lamports = claim_fee_lamports;
+    distributor_state_account.fee_treasury = fee_treasury;
+    distributor_state_account.referral_bps = referral_bps;
+    distributor_state_account.distributor_id = distributor_id;
+    distributor_state_account.authority_bump = authority_bump;
+    distributor_state_account.merkle_root = merkle_root;
+    distributor_state_account.paused = false;
 
     // pack the distributor_state_account
     distributor_state_account.serialize(&mut &mut distributor_state_account_info.data.borrow_mut()[..])?;
+
+    DistributorEvent::DistributorCreated {
+        distributor: *distributor_state_account_info.key,
+        authority: distributor_state_account.authority,
+        reward_mint: distributor_state_account.reward_mint,
+        reward_amount_total,
+        collection_creator: distributor_state_account.collection_creator,
+    }.emit();
    
     Ok(())
 }
 
 pub fn process_claim_tokens<'a>(
-    program_id: &'a Pubkey,
-    accounts: &'a [AccountInfo<'a>],
+    program_id: &Pubkey,
+    accounts: &[AccountInfo<'a>],
+    args: ClaimTokensArgs,
 ) -> ProgramResult {
-    let account_info_iter = &mut accounts.iter();
-    let claimant_main_account_info = next_account_info(account_info_iter)?;
-    let distributor_state_account_info = next_account_info(account_info_iter)?;
-    let distributor_reward_account_info = next_account_info(account_info_iter)?;
-    let claimant_reward_account_info = next_account_info(account_info_iter)?;
-    let pda_account_info = next_account_info(account_info_iter)?;
-    let claimant_nft_account_info = next_account_info(account_info_iter)?;
-    let nft_metadata_account_info = next_account_info(account_info_iter)?;
-    let proof_receipt_account_info = next_account_info(account_info_iter)?;
-    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
-    let rent_account = next_account_info(account_info_iter)?;
-    let token_program_account = next_account_info(account_info_iter)?;
-    spl_token::check_program_account(token_program_account.key)?;
-    let system_program_account = next_account_info(account_info_iter)?;
-    if check_id(system_program_account.key) == false {
-        return Err(DistributorError::InvalidSystemProgram.into());
-    }
-
-    // check claimant_main_account_info is the tx signer
-    if !claimant_main_account_info.is_signer {
-        return Err(DistributorError::IncorrectSigner.into());
-    }
+    // signer, writable flags, token account owners, sysvar and program ids are checked while parsing
+    let ClaimTokensAccounts {
+        claimant_main_account: claimant_main_account_info,
+        distributor_state_account: distributor_state_account_info,
+        distributor_reward_account: distributor_reward_account_info,
+        claimant_reward_account: claimant_reward_account_info,
+        pda_account: pda_account_info,
+        claimant_nft_account: claimant_nft_account_info,
+        nft_metadata_account: nft_metadata_account_info,
+        nft_edition_account: nft_edition_account_info,
+        attribute_attestation_account: attribute_attestation_account_info,
+        proof_of_receipt_account: proof_receipt_account_info,
+        wallet_claim_record_account: wallet_claim_record_account_info,
+        fee_treasury_account: fee_treasury_account_info,
+        clock_sysvar: clock_sysvar_info,
+        rent_sysvar: rent_account,
+        token_program_account,
+        system_program_account,
+        referral_accounts,
+    } = ClaimTokensAccounts::try_from(accounts)?;
+    let clock = &Clock::from_account_info(clock_sysvar_info)?;
+    let referral_account_infos = referral_accounts.map(|referral_accounts| {
+        (referral_accounts.referrer_main_account, referral_accounts.referrer_reward_account, referral_accounts.referral_record_account)
+    });
 
     // check program is owner of the distributor_state_account_info
     if distributor_state_account_info.owner != program_id {
@@ -175,7 +344,20 @@ pub fn process_claim_tokens<'a>(
     }
 
     // unpack distributor state
-    let mut distributor_state_account = DistributorAccount::from_account_info(&distributor_state_account_info)?;
+    let distributor_state_account = DistributorAccount::from_account_info(distributor_state_account_info)?;
+    if !distributor_state_account.is_initialized {
+        return Err(DistributorError::Uninitialized.into());
+    }
+
+    // accounts created before the current layout must be migrated before they can be written
+    if distributor_state_account.version != DISTRIBUTOR_VERSION {
+        return Err(DistributorError::AccountNotMigrated.into());
+    }
+
+    // the authority can pause claims
+    if distributor_state_account.paused {
+        return Err(DistributorError::DistributorPaused.into());
+    }
 
     // check the current ts is after start_ts
     if clock.unix_timestamp < distributor_state_account.start_ts {
@@ -187,46 +369,46 @@ pub fn process_claim_tokens<'a>(
     if claimant_nft_account.owner != *claimant_main_account_info.key {
         return Err(DistributorError::IncorrectOwner.into());
     }
- 
-    // pda derived from "metadata", metadata program id, mint account pubkey
-    let metadata_prefix: &str = "metadata";
-    let metadata_seeds = &[
-        metadata_prefix.as_bytes(),
-        spl_token_metadata::ID.as_ref(),
-        claimant_nft_account.mint.as_ref()
-    ];
-    // check the nft_metadata_account_info is derived from the claimant_nft_account_info mint and metadata prefix - ensures we have the correct metadata account
-    let (metadata_account_pubkey, _bump_seed) = Pubkey::find_program_address(metadata_seeds, &spl_token_metadata::ID);
-    if *nft_metadata_account_info.key != metadata_account_pubkey {
-        return Err(DistributorError::InvalidMetadataAccount.into());
-    }
 
-    // check the metadata account data - find the creator in metadata creators
-    let nft_metadata_account = MetadataAccount::from_account_info(&nft_metadata_account_info)?;
-    if let Some(creators) = &nft_metadata_account.data.creators {
-        let mut found = false;
-        for creator in creators {
-            if creator.address == distributor_state_account.collection_creator {
-                found = true;
-                break;
-            }
-            creator.address.log();
-        }
-        if !found {
-            return Err(MetadataError::CreatorNotFound.into());
-        }
-    } else {
-        return Err(MetadataError::NoCreatorsPresentOnMetadata.into());
+    // check the claimant_nft_account_info holds the NFT, an empty account for the mint is not enough
+    if claimant_nft_account.amount != 1 {
+        return Err(DistributorError::NftNotHeld.into());
     }
+ 
+    // check the NFT is eligible under the distributor filters
+    let is_print = check_nft_eligibility(
+        program_id,
+        &distributor_state_account,
+        &claimant_nft_account.mint,
+        nft_metadata_account_info,
+        nft_edition_account_info,
+        attribute_attestation_account_info,
+        args.metadata_bump,
+        args.edition_bump,
+        args.parent_edition_bump,
+    )?;
 
-    // collection symbol must be same as in distributor state
-    let symbol = &nft_metadata_account.data.symbol;
+    // check the NFT mint is in the allowlist, if the distributor has one
+    let allowlist_amount = check_allowlist(&distributor_state_account, &claimant_nft_account.mint, args.allowlist_amount, &args.merkle_proof)?;
 
-    // puff the symbol in distributor state to match puffed symbol in metadata account
-    let puffed_symbol = puffed_out_string(&distributor_state_account.collection_symbol, MAX_SYMBOL_LENGTH);
+    // originals and prints have separate reward amounts, an allowlist amount overrides both
+    let reward_amount = allowlist_amount.unwrap_or_else(|| distributor_state_account.reward_amount(is_print));
 
-    if *symbol != puffed_symbol {
-        return Err(DistributorError::IncorrectSymbol.into());
+    // referrers get a bonus of referral_bps of the claimed amount
+    let referral_amount = match referral_account_infos {
+        Some(_) => (reward_amount as u128 * distributor_state_account.referral_bps as u128 / MAX_BPS as u128) as u64,
+        None => 0,
+    };
+
+    // the claim and the referral bonus both count against reward_amount_total
+    // amount_claimed is read from, and written back to, the fixed size head of the distributor account
+    let mut distributor_header = DistributorHeader::unpack_from_slice(&distributor_state_account_info.data.borrow())?;
+    let amount_claimed = distributor_header.amount_claimed
+        .checked_add(reward_amount)
+        .and_then(|amount| amount.checked_add(referral_amount))
+        .ok_or(DistributorError::AmountOverflow)?;
+    if amount_claimed > distributor_state_account.reward_amount_total {
+        return Err(DistributorError::RewardPoolExhausted.into());
     }
 
     // check distributor_reward_account_info is same as in distributor state
@@ -234,12 +416,13 @@ pub fn process_claim_tokens<'a>(
         return Err(DistributorError::InvalidAccounts.into());
     }
  
-    // get the PDA account Pubkey (derived from the distributor_state_account_info Pubkey and prefix "distributor")
-    let distributor_seeds = &[
+    // get the PDA account Pubkey (derived from the distributor_state_account_info Pubkey and prefix "distributor", bump stored at creation)
+    let distributor_transfer_seeds = &[
         PREFIX.as_bytes(),
         distributor_state_account_info.key.as_ref(),
+        &[distributor_state_account.authority_bump]
     ];
-    let (reward_account_pda, bump_seed) = Pubkey::find_program_address(distributor_seeds, program_id);
+    let reward_account_pda = Pubkey::create_program_address(distributor_transfer_seeds, program_id)?;
 
     // transfer tokens to claimant_reward_account from distributor_reward_account_info (pda_account signs)
     let transfer_to_claimant_ix = spl_token::instruction::transfer(
@@ -248,14 +431,9 @@ pub fn process_claim_tokens<'a>(
         claimant_reward_account_info.key, // dst
         &reward_account_pda, // authority
         &[&reward_account_pda], 
-        distributor_state_account.reward_amount_per_nft,
+        reward_amount,
     )?;
     msg!("Calling the token program to transfer tokens to claimant account");
-    let distributor_transfer_seeds = &[
-        PREFIX.as_bytes(),
-        distributor_state_account_info.key.as_ref(),
-        &[bump_seed]
-    ];
     invoke_signed(
         &transfer_to_claimant_ix,
         &[
@@ -266,22 +444,127 @@ pub fn process_claim_tokens<'a>(
         ],
         &[distributor_transfer_seeds]
     )?;
-    // increment the distributor state amount claimed
-    distributor_state_account.amount_claimed += distributor_state_account.reward_amount_per_nft;
 
-    // pack the distributor state
-    distributor_state_account.serialize(&mut &mut distributor_state_account_info.data.borrow_mut()[..])?;
+    // Referral record account - counts the claims referred by the referrer and pays the referral bonus
+    if let Some((referrer_main_account_info, referrer_reward_account_info, referral_record_account_info)) = referral_account_infos {
+        if referrer_main_account_info.key == claimant_main_account_info.key {
+            return Err(DistributorError::SelfReferral.into());
+        }
+
+        // check the referrer_reward_account_info belongs to the referrer and holds the reward mint
+        let referrer_reward_account = TokenAccount::unpack(&referrer_reward_account_info.data.borrow())?;
+        if referrer_reward_account.owner != *referrer_main_account_info.key {
+            return Err(DistributorError::IncorrectOwner.into());
+        }
+        if referrer_reward_account.mint != distributor_state_account.reward_mint {
+            return Err(DistributorError::InvalidMint.into());
+        }
+
+        let find_referral_seed = &[
+            REFERRAL_PREFIX.as_bytes(),
+            distributor_state_account_info.key.as_ref(),
+            referrer_main_account_info.key.as_ref()
+        ];
+
+        // check the referral record account given is the correct one
+        // the canonical bump is searched for, any other bump would start a second record for the referrer
+        let (referral_record_pubkey, bump_seed) = Pubkey::find_program_address(find_referral_seed, program_id);
+
+        if referral_record_pubkey != *referral_record_account_info.key {
+            return Err(DistributorError::InvalidReferralRecord.into());
+        }
+
+        // create the account on the referrer's first referred claim, the claimant pays
+        if referral_record_account_info.data_is_empty() {
+            let referral_authority_seeds = &[
+                REFERRAL_PREFIX.as_bytes(),
+                distributor_state_account_info.key.as_ref(),
+                referrer_main_account_info.key.as_ref(),
+                &[bump_seed],
+            ];
+            create_or_allocate_account_raw(
+                *program_id,
+                referral_record_account_info,
+                rent_account,
+                system_program_account,
+                claimant_main_account_info,
+                REFERRAL_RECORD_DATA_LENGTH,
+                referral_authority_seeds
+            )?;
+        } else if referral_record_account_info.owner != program_id {
+            return Err(DistributorError::IncorrectOwner.into());
+        }
+
+        if referral_amount > 0 {
+            // transfer tokens to referrer_reward_account from distributor_reward_account_info (pda_account signs)
+            let transfer_to_referrer_ix = spl_token::instruction::transfer(
+                token_program_account.key, 
+                distributor_reward_account_info.key, // src
+                referrer_reward_account_info.key, // dst
+                &reward_account_pda, // authority
+                &[&reward_account_pda], 
+                referral_amount,
+            )?;
+            msg!("Calling the token program to transfer referral bonus to referrer account");
+            invoke_signed(
+                &transfer_to_referrer_ix,
+                &[
+                    distributor_reward_account_info.clone(),
+                    referrer_reward_account_info.clone(),
+                    pda_account_info.clone(),
+                    token_program_account.clone(),
+                ],
+                &[distributor_transfer_seeds]
+            )?;
+        }
+
+        // unpack the referral record account data
+        let mut referral_record_account = ReferralRecordAccount::unpack_unchecked(&referral_record_account_info.data.borrow())?;
+
+        if !referral_record_account.is_initialized {
+            // first referred claim - write the whole record
+            referral_record_account.key = AccountKey::ReferralRecord;
+            referral_record_account.version = REFERRAL_RECORD_VERSION;
+            referral_record_account.is_initialized = true;
+            referral_record_account.distributor = *distributor_state_account_info.key;
+            referral_record_account.referrer = *referrer_main_account_info.key;
+            referral_record_account.referred_claims = 1;
+            referral_record_account.amount_earned = referral_amount;
+        } else {
+            // increment the referred claims and amount earned
+            referral_record_account.referred_claims = referral_record_account.referred_claims
+                .checked_add(1)
+                .ok_or(DistributorError::ClaimCountOverflow)?;
+            referral_record_account.amount_earned = referral_record_account.amount_earned
+                .checked_add(referral_amount)
+                .ok_or(DistributorError::AmountOverflow)?;
+        }
+
+        // pack referral record state
+        ReferralRecordAccount::pack(referral_record_account, &mut referral_record_account_info.data.borrow_mut())?;
+
+        DistributorEvent::ReferralPaid {
+            distributor: *distributor_state_account_info.key,
+            referrer: *referrer_main_account_info.key,
+            claimant: *claimant_main_account_info.key,
+            amount: referral_amount,
+        }.emit();
+    }
+
+    // increment the distributor state amount claimed - only the head is packed, the rest of the distributor state is unchanged
+    distributor_header.amount_claimed = amount_claimed;
+    distributor_header.pack_into_slice(&mut distributor_state_account_info.data.borrow_mut());
 
     // Proof of receipt account
     // get account pubkey of account derived from nft mint, distributor key and "claimed"
-    pub const SEED_STR: &str = "claimed";
     let find_receipt_seed = &[
-        SEED_STR.as_bytes(),
+        RECEIPT_PREFIX.as_bytes(),
         claimant_nft_account.mint.as_ref(),
         distributor_state_account_info.key.as_ref()
     ];
 
     // check the proof of receipt account given is the correct one
+    // the receipt keeps the canonical bump search - any other bump would derive a fresh receipt and allow a second claim
     let (proof_of_receipt_pubkey, bump_seed) = Pubkey::find_program_address(find_receipt_seed, program_id);
 
     if proof_of_receipt_pubkey != *proof_receipt_account_info.key {
@@ -289,13 +572,17 @@ pub fn process_claim_tokens<'a>(
     }
 
     let receipt_authority_seeds = &[
-        SEED_STR.as_bytes(),
+        RECEIPT_PREFIX.as_bytes(),
         claimant_nft_account.mint.as_ref(),
         distributor_state_account_info.key.as_ref(),
         &[bump_seed],
     ];
 
-    // TX FAILS IF CLAIMANT HAS ALREADY CLAIMED AS CAN'T MAKE THIS ACCOUNT AS ACCOUNT ALREADY IN USE
+    // the receipt account only exists if tokens have already been claimed for this NFT
+    if !proof_receipt_account_info.data_is_empty() {
+        return Err(DistributorError::TokensAlreadyClaimed.into());
+    }
+
     // create the account
     create_or_allocate_account_raw(
         *program_id,
@@ -303,22 +590,1328 @@ pub fn process_claim_tokens<'a>(
         rent_account,
         system_program_account,
         claimant_main_account_info,
-        1,
+        ProofOfReceiptAccount::LEN,
         receipt_authority_seeds
     )?;
 
-    // THIS IS REDUNDANT AS TX FAILS ANYWAY IF USER TRIES TO CREATE THE ACCOUNT AGAIN
+    // THIS IS REDUNDANT AS THE RECEIPT ACCOUNT IS CHECKED TO BE EMPTY BEFORE IT IS CREATED
     // unpack the proof of receipt account data
-    let mut proof_of_receipt_account = ProofOfReceiptAccount::from_account_info(&proof_receipt_account_info)?;
+    let mut proof_of_receipt_account = ProofOfReceiptAccount::from_account_info(proof_receipt_account_info)?;
     // check that tokens have not already been claimed
-    if proof_of_receipt_account.received_tokens == true {
+    if proof_of_receipt_account.received_tokens {
         return Err(DistributorError::TokensAlreadyClaimed.into());
     }    
     // set proof of receipt account received_tokens true
+    proof_of_receipt_account.key = AccountKey::ProofOfReceipt;
+    proof_of_receipt_account.version = PROOF_OF_RECEIPT_VERSION;
     proof_of_receipt_account.received_tokens = true;
+    proof_of_receipt_account.bump = bump_seed;
 
     // pack proof of receipt state
     proof_of_receipt_account.serialize(&mut &mut proof_receipt_account_info.data.borrow_mut()[..])?;
 
+    // Wallet claim record account - counts the claims of the claimant wallet if the distributor has a per wallet limit
+    if let Some(max_claims_per_wallet) = distributor_state_account.max_claims_per_wallet {
+        let find_wallet_claims_seed = &[
+            WALLET_CLAIMS_PREFIX.as_bytes(),
+            distributor_state_account_info.key.as_ref(),
+            claimant_main_account_info.key.as_ref()
+        ];
+
+
+        // check the wallet claim record account given is the correct one
+        // the canonical bump is searched for, any other bump would start a fresh record and lift the per wallet limit
+        let (wallet_claim_record_pubkey, bump_seed) = Pubkey::find_program_address(find_wallet_claims_seed, program_id);
+
+        if wallet_claim_record_pubkey != *wallet_claim_record_account_info.key {
+            return Err(DistributorError::InvalidWalletClaimRecord.into());
+        }
+
+        // create the account on the claimant's first claim
+        if wallet_claim_record_account_info.data_is_empty() {
+            let wallet_claims_authority_seeds = &[
+                WALLET_CLAIMS_PREFIX.as_bytes(),
+                distributor_state_account_info.key.as_ref(),
+                claimant_main_account_info.key.as_ref(),
+                &[bump_seed],
+            ];
+            create_or_allocate_account_raw(
+                *program_id,
+                wallet_claim_record_account_info,
+                rent_account,
+                system_program_account,
+                claimant_main_account_info,
+                WALLET_CLAIM_RECORD_DATA_LENGTH,
+                wallet_claims_authority_seeds
+            )?;
+        } else if wallet_claim_record_account_info.owner != program_id {
+            return Err(DistributorError::IncorrectOwner.into());
+        }
+
+        // unpack the wallet claim record account data
+        let mut wallet_claim_record_account = WalletClaimRecordAccount::unpack_unchecked(&wallet_claim_record_account_info.data.borrow())?;
+        if wallet_claim_record_account.claims >= max_claims_per_wallet {
+            return Err(DistributorError::WalletClaimLimitReached.into());
+        }
+
+        if !wallet_claim_record_account.is_initialized {
+            // first claim - write the whole record
+            wallet_claim_record_account.key = AccountKey::WalletClaimRecord;
+            wallet_claim_record_account.version = WALLET_CLAIM_RECORD_VERSION;
+            wallet_claim_record_account.is_initialized = true;
+            wallet_claim_record_account.distributor = *distributor_state_account_info.key;
+            wallet_claim_record_account.claimant = *claimant_main_account_info.key;
+            wallet_claim_record_account.claims = 1;
+        } else {
+            // increment the wallet claims
+            wallet_claim_record_account.claims = wallet_claim_record_account.claims
+                .checked_add(1)
+                .ok_or(DistributorError::ClaimCountOverflow)?;
+        }
+
+        // pack wallet claim record state
+        WalletClaimRecordAccount::pack(wallet_claim_record_account, &mut wallet_claim_record_account_info.data.borrow_mut())?;
+    }
+
+    // transfer the claim fee from the claimant to the fee treasury
+    if distributor_state_account.claim_fee_lamports > 0 {
+        // check fee_treasury_account_info is same as in distributor state
+        if *fee_treasury_account_info.key != distributor_state_account.fee_treasury {
+            return Err(DistributorError::InvalidFeeTreasury.into());
+        }
+
+        msg!("Transfer {} lamports claim fee to the fee treasury", distributor_state_account.claim_fee_lamports);
+        invoke(
+            &system_instruction::transfer(
+                claimant_main_account_info.key,
+                fee_treasury_account_info.key,
+                distributor_state_account.claim_fee_lamports
+            ),
+            &[
+                claimant_main_account_info.clone(),
+                fee_treasury_account_info.clone(),
+                system_program_account.clone(),
+            ],
+        )?;
+    }
+
+    DistributorEvent::TokensClaimed {
+        distributor: *distributor_state_account_info.key,
+        claimant: *claimant_main_account_info.key,
+        nft_mint: claimant_nft_account.mint,
+        amount: reward_amount,
+        total_claimed: amount_claimed,
+    }.emit();
+
+    Ok(())
+}
+
+/// Checks the NFT passes the distributor metadata, edition and trait filters.
+/// Returns whether the NFT is a print.
+#[allow(clippy::too_many_arguments)]
+fn check_nft_eligibility(
+    program_id: &Pubkey,
+    distributor: &DistributorAccount,
+    nft_mint: &Pubkey,
+    nft_metadata_account_info: &AccountInfo,
+    nft_edition_account_info: &AccountInfo,
+    attribute_attestation_account_info: &AccountInfo,
+    metadata_bump: u8,
+    edition_bump: u8,
+    parent_edition_bump: u8,
+) -> Result<bool, ProgramError> {
+    // pda derived from "metadata", metadata program id, mint account pubkey
+    let metadata_prefix: &str = "metadata";
+    let metadata_seeds = &[
+        metadata_prefix.as_bytes(),
+        spl_token_metadata::ID.as_ref(),
+        nft_mint.as_ref(),
+        &[metadata_bump]
+    ];
+    // check the nft_metadata_account_info is derived from the NFT mint and metadata prefix - ensures we have the correct metadata account
+    // the client supplies the bump, only the canonical one can derive an account the metadata program has written
+    let metadata_account_pubkey = Pubkey::create_program_address(metadata_seeds, &spl_token_metadata::ID)
+        .map_err(|_| DistributorError::InvalidMetadataAccount)?;
+    if *nft_metadata_account_info.key != metadata_account_pubkey || nft_metadata_account_info.owner != &spl_token_metadata::ID {
+        return Err(DistributorError::InvalidMetadataAccount.into());
+    }
+
+    // check the metadata account data matches the distributor collection
+    let nft_metadata_account = MetadataAccount::from_account_info(nft_metadata_account_info)?;
+    check_metadata_eligibility(distributor, &nft_metadata_account)?;
+
+    // check the nft_edition_account_info is derived from the NFT mint, metadata prefix and "edition"
+    let edition_seeds = &[
+        metadata_prefix.as_bytes(),
+        spl_token_metadata::ID.as_ref(),
+        nft_mint.as_ref(),
+        spl_token_metadata::state::EDITION.as_bytes(),
+        &[edition_bump]
+    ];
+    let edition_account_pubkey = Pubkey::create_program_address(edition_seeds, &spl_token_metadata::ID)
+        .map_err(|_| DistributorError::InvalidEditionAccount)?;
+    if *nft_edition_account_info.key != edition_account_pubkey {
+        return Err(DistributorError::InvalidEditionAccount.into());
+    }
+
+    let is_print = if nft_edition_account_info.owner == &spl_token_metadata::ID {
+        check_edition_eligibility(distributor, &nft_edition_account_info.data.borrow(), parent_edition_bump)?
+    } else if distributor.edition_policy == EditionPolicy::Both {
+        // NFTs without an edition account claimed before edition policies, and still claim as originals under Both
+        // only at the canonical edition address - an empty account at another bump would hide the edition of a print
+        let (canonical_edition_pubkey, _bump_seed) = Pubkey::find_program_address(&edition_seeds[..4], &spl_token_metadata::ID);
+        if *nft_edition_account_info.key != canonical_edition_pubkey {
+            return Err(DistributorError::InvalidEditionAccount.into());
+        }
+        false
+    } else {
+        return Err(DistributorError::InvalidEditionAccount.into());
+    };
+
+    // check the NFT has the required trait, as attested by the trait filter oracle
+    if let Some(trait_filter) = &distributor.trait_filter {
+        let attestation_seeds = &[
+            ATTESTATION_PREFIX.as_bytes(),
+            trait_filter.oracle.as_ref(),
+            nft_mint.as_ref()
+        ];
+        let (attestation_pubkey, _bump_seed) = Pubkey::find_program_address(attestation_seeds, program_id);
+        if *attribute_attestation_account_info.key != attestation_pubkey || attribute_attestation_account_info.owner != program_id {
+            return Err(DistributorError::InvalidAttestation.into());
+        }
+
+        let attestation = AttributeAttestationAccount::from_account_info(attribute_attestation_account_info)?;
+        if !attestation.is_initialized || attestation.oracle != trait_filter.oracle || attestation.mint != *nft_mint {
+            return Err(DistributorError::InvalidAttestation.into());
+        }
+        if !attestation.trait_hashes.contains(&trait_filter.trait_hash) {
+            return Err(DistributorError::TraitNotAttested.into());
+        }
+    }
+
+    Ok(is_print)
+}
+
+/// Checks the NFT metadata belongs to the distributor collection: creator present, symbol, name and uri prefixes.
+/// Public so off-chain tools apply the same rules as claims.
+pub fn check_metadata_eligibility(distributor: &DistributorAccount, nft_metadata_account: &MetadataAccount) -> ProgramResult {
+    // find the creator in metadata creators
+    if let Some(creators) = &nft_metadata_account.data.creators {
+        let mut found = false;
+        for creator in creators {
+            if creator.address == distributor.collection_creator {
+                found = true;
+                break;
+            }
+        }
+        if !found {
+            return Err(MetadataError::CreatorNotFound.into());
+        }
+    } else {
+        return Err(MetadataError::NoCreatorsPresentOnMetadata.into());
+    }
+
+    // collection symbol must be same as in distributor state, ignoring the 0u8 padding of both
+    let symbol = trim_null_padding(&nft_metadata_account.data.symbol).as_bytes();
+
+    if symbol != distributor.trimmed_collection_symbol() {
+        return Err(DistributorError::IncorrectSymbol.into());
+    }
+
+    // metadata name must start with the name prefix in distributor state (catches copycat collections reusing the symbol)
+    if let Some(name_prefix) = &distributor.name_prefix {
+        if !trim_null_padding(&nft_metadata_account.data.name).starts_with(name_prefix.as_str()) {
+            return Err(DistributorError::IncorrectName.into());
+        }
+    }
+
+    // metadata uri must start with the uri prefix in distributor state (the allowed host)
+    if let Some(uri_prefix) = &distributor.uri_prefix {
+        if !trim_null_padding(&nft_metadata_account.data.uri).starts_with(uri_prefix.as_str()) {
+            return Err(DistributorError::IncorrectUri.into());
+        }
+    }
+
     Ok(())
+}
+
+/// Checks the NFT edition account data is eligible under the distributor edition policy, returns whether the NFT is a print
+pub fn check_edition_eligibility(distributor: &DistributorAccount, edition_data: &[u8], parent_edition_bump: u8) -> Result<bool, ProgramError> {
+    // the first byte of the edition account is the metadata Key - master edition for originals, edition for prints
+    let edition_key = edition_data.first().copied();
+    let is_print = if edition_key == Some(MetadataKey::MasterEditionV1 as u8) || edition_key == Some(MetadataKey::MasterEditionV2 as u8) {
+        false
+    } else if edition_key == Some(MetadataKey::EditionV1 as u8) {
+        true
+    } else {
+        return Err(DistributorError::InvalidEditionAccount.into());
+    };
+
+    // check the edition is eligible under the distributor edition policy
+    match distributor.edition_policy {
+        EditionPolicy::OriginalsOnly => {
+            if is_print {
+                return Err(DistributorError::EditionNotEligible.into());
+            }
+        },
+        EditionPolicy::PrintsOnly => {
+            if !is_print {
+                return Err(DistributorError::EditionNotEligible.into());
+            }
+        },
+        EditionPolicy::Both => {},
+        EditionPolicy::PrintsWithParent(master_mint) => {
+            if !is_print {
+                return Err(DistributorError::EditionNotEligible.into());
+            }
+            // the print's parent is the master edition PDA of the master mint, the client supplies its bump
+            let master_edition_seeds = &[
+                spl_token_metadata::state::PREFIX.as_bytes(),
+                spl_token_metadata::ID.as_ref(),
+                master_mint.as_ref(),
+                spl_token_metadata::state::EDITION.as_bytes(),
+                &[parent_edition_bump]
+            ];
+            let master_edition_pubkey = Pubkey::create_program_address(master_edition_seeds, &spl_token_metadata::ID)
+                .map_err(|_| DistributorError::EditionNotEligible)?;
+            let nft_edition_account: EditionAccount = spl_token_metadata::utils::try_from_slice_checked(
+                edition_data,
+                MetadataKey::EditionV1,
+                spl_token_metadata::state::MAX_EDITION_LEN
+            )?;
+            if nft_edition_account.parent != master_edition_pubkey {
+                return Err(DistributorError::EditionNotEligible.into());
+            }
+        },
+    }
+
+    Ok(is_print)
+}
+
+/// Checks the NFT mint is in the distributor allowlist with the amount given, when the distributor has a merkle_root.
+/// Returns the amount of the allowlist entry, None without an allowlist or for entries without an amount
+pub fn check_allowlist(
+    distributor: &DistributorAccount,
+    nft_mint: &Pubkey,
+    allowlist_amount: u64,
+    merkle_proof: &[[u8; 32]],
+) -> Result<Option<u64>, ProgramError> {
+    let merkle_root = match &distributor.merkle_root {
+        Some(merkle_root) => merkle_root,
+        None => return Ok(None),
+    };
+    if !verify_proof(merkle_root, nft_mint, allowlist_amount, merkle_proof) {
+        return Err(DistributorError::NotAllowlisted.into());
+    }
+
+    // entries without an amount get the distributor reward
+    Ok(if allowlist_amount > 0 { Some(allowlist_amount) } else { None })
+}
+
+pub fn process_attest_attributes<'a>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo<'a>],
+    trait_hashes: Vec<[u8; 32]>,
+) -> ProgramResult {
+    // signer, writable flags, the mint owner, sysvar and program ids are checked while parsing
+    let AttestAttributesAccounts {
+        oracle_account: oracle_account_info,
+        attribute_attestation_account: attribute_attestation_account_info,
+        nft_mint_account: nft_mint_account_info,
+        rent_sysvar: rent_account,
+        system_program_account,
+    } = AttestAttributesAccounts::try_from(accounts)?;
+
+    if trait_hashes.len() > MAX_ATTESTED_TRAITS {
+        return Err(DistributorError::TooManyTraits.into());
+    }
+
+    // get account pubkey of account derived from "attestation", oracle key and nft mint
+    let find_attestation_seed = &[
+        ATTESTATION_PREFIX.as_bytes(),
+        oracle_account_info.key.as_ref(),
+        nft_mint_account_info.key.as_ref()
+    ];
+
+    // check the attestation account given is the correct one
+    let (attestation_pubkey, bump_seed) = Pubkey::find_program_address(find_attestation_seed, program_id);
+
+    if attestation_pubkey != *attribute_attestation_account_info.key {
+        return Err(DistributorError::InvalidAttestation.into());
+    }
+
+    let attestation_authority_seeds = &[
+        ATTESTATION_PREFIX.as_bytes(),
+        oracle_account_info.key.as_ref(),
+        nft_mint_account_info.key.as_ref(),
+        &[bump_seed],
+    ];
+
+    // the attestation is written once
+    if !attribute_attestation_account_info.data_is_empty() {
+        return Err(DistributorError::AlreadyInitialized.into());
+    }
+
+    create_or_allocate_account_raw(
+        *program_id,
+        attribute_attestation_account_info,
+        rent_account,
+        system_program_account,
+        oracle_account_info,
+        MAX_ATTESTATION_DATA_LENGTH,
+        attestation_authority_seeds
+    )?;
+
+    let mut attestation = AttributeAttestationAccount::from_account_info(attribute_attestation_account_info)?;
+
+    // write the data to state
+    attestation.key = AccountKey::AttributeAttestation;
+    attestation.version = ATTRIBUTE_ATTESTATION_VERSION;
+    attestation.is_initialized = true;
+    attestation.oracle = *oracle_account_info.key;
+    attestation.mint = *nft_mint_account_info.key;
+    attestation.trait_hashes = trait_hashes;
+
+    // pack the attestation account
+    attestation.serialize(&mut &mut attribute_attestation_account_info.data.borrow_mut()[..])?;
+
+    DistributorEvent::AttributesAttested {
+        oracle: *oracle_account_info.key,
+        nft_mint: *nft_mint_account_info.key,
+        trait_count: attestation.trait_hashes.len() as u8,
+    }.emit();
+
+    Ok(())
+}
+
+pub fn process_migrate_distributor<'a>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo<'a>],
+) -> ProgramResult {
+    // signer, writable flags, sysvar and program ids are checked while parsing
+    let MigrateDistributorAccounts {
+        authority_account: authority_account_info,
+        distributor_state_account: distributor_state_account_info,
+        rent_sysvar: rent_sysvar_info,
+        system_program_account,
+    } = MigrateDistributorAccounts::try_from(accounts)?;
+    let rent = &Rent::from_account_info(rent_sysvar_info)?;
+
+    // check program is owner of the distributor_state_account_info
+    if distributor_state_account_info.owner != program_id {
+        return Err(DistributorError::IncorrectOwner.into());
+    }
+
+    // unpack distributor state, whatever version it was written with
+    let mut distributor_state_account = DistributorAccount::from_account_info(distributor_state_account_info)?;
+    if !distributor_state_account.is_initialized {
+        return Err(DistributorError::Uninitialized.into());
+    }
+
+    // check authority_account_info is the distributor authority
+    if distributor_state_account.authority != *authority_account_info.key {
+        return Err(DistributorError::UnauthorizedAccount.into());
+    }
+
+    if distributor_state_account.version == DISTRIBUTOR_VERSION {
+        msg!("Distributor already at version {}", DISTRIBUTOR_VERSION);
+        return Ok(());
+    }
+
+    // the authority pays the rent for the extra space
+    let required_lamports = rent
+        .minimum_balance(DistributorAccount::LEN)
+        .saturating_sub(distributor_state_account_info.lamports());
+    if required_lamports > 0 {
+        msg!("Transfer {} lamports to the distributor state account", required_lamports);
+        invoke(
+            &system_instruction::transfer(authority_account_info.key, distributor_state_account_info.key, required_lamports),
+            &[
+                authority_account_info.clone(),
+                distributor_state_account_info.clone(),
+                system_program_account.clone(),
+            ],
+        )?;
+    }
+
+    msg!("Realloc the distributor state account from version {} to {}", distributor_state_account.version, DISTRIBUTOR_VERSION);
+    distributor_state_account_info.realloc(DistributorAccount::LEN, true)?;
+    let from_version = distributor_state_account.version;
+
+    distributor_state_account.key = AccountKey::Distributor;
+    distributor_state_account.version = DISTRIBUTOR_VERSION;
+
+    // version 2 stores the bump seed of the PDA that owns the reward token account,
+    // version 3 appends merkle_root, which the realloc zeroes - None, older distributors have no allowlist,
+    // version 4 appends paused, zeroed to false
+    let distributor_seeds = &[
+        PREFIX.as_bytes(),
+        distributor_state_account_info.key.as_ref(),
+    ];
+    let (_pda, authority_bump) = Pubkey::find_program_address(distributor_seeds, program_id);
+    distributor_state_account.authority_bump = authority_bump;
+
+    // pack the distributor state
+    distributor_state_account.serialize(&mut &mut distributor_state_account_info.data.borrow_mut()[..])?;
+
+    DistributorEvent::DistributorMigrated {
+        distributor: *distributor_state_account_info.key,
+        from_version,
+        to_version: DISTRIBUTOR_VERSION,
+    }.emit();
+
+    Ok(())
+}
+
+pub fn process_set_paused<'a>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo<'a>],
+    paused: bool,
+) -> ProgramResult {
+    // signer and writable flags are checked while parsing
+    let SetPausedAccounts {
+        authority_account: authority_account_info,
+        distributor_state_account: distributor_state_account_info,
+    } = SetPausedAccounts::try_from(accounts)?;
+
+    // check program is owner of the distributor_state_account_info
+    if distributor_state_account_info.owner != program_id {
+        return Err(DistributorError::IncorrectOwner.into());
+    }
+
+    // unpack distributor state
+    let mut distributor_state_account = DistributorAccount::from_account_info(distributor_state_account_info)?;
+    if !distributor_state_account.is_initialized {
+        return Err(DistributorError::Uninitialized.into());
+    }
+
+    // accounts created before the current layout must be migrated before they can be written
+    if distributor_state_account.version != DISTRIBUTOR_VERSION {
+        return Err(DistributorError::AccountNotMigrated.into());
+    }
+
+    // check authority_account_info is the distributor authority
+    if distributor_state_account.authority != *authority_account_info.key {
+        return Err(DistributorError::UnauthorizedAccount.into());
+    }
+
+    // pack the distributor state
+    distributor_state_account.paused = paused;
+    distributor_state_account.serialize(&mut &mut distributor_state_account_info.data.borrow_mut()[..])?;
+
+    DistributorEvent::DistributorPauseSet {
+        distributor: *distributor_state_account_info.key,
+        paused,
+    }.emit();
+
+    Ok(())
+}
+
+pub fn process_close_distributor<'a>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo<'a>],
+) -> ProgramResult {
+    // signer, writable flags, token account owners and program ids are checked while parsing
+    let CloseDistributorAccounts {
+        authority_account: authority_account_info,
+        distributor_state_account: distributor_state_account_info,
+        distributor_reward_account: distributor_reward_account_info,
+        authority_reward_account: authority_reward_account_info,
+        pda_account: pda_account_info,
+        token_program_account,
+    } = CloseDistributorAccounts::try_from(accounts)?;
+
+    // check program is owner of the distributor_state_account_info
+    if distributor_state_account_info.owner != program_id {
+        return Err(DistributorError::IncorrectOwner.into());
+    }
+
+    // unpack distributor state
+    let distributor_state_account = DistributorAccount::from_account_info(distributor_state_account_info)?;
+    if !distributor_state_account.is_initialized {
+        return Err(DistributorError::Uninitialized.into());
+    }
+
+    // the vault is signed for with the stored authority_bump, which older versions do not have
+    if distributor_state_account.version != DISTRIBUTOR_VERSION {
+        return Err(DistributorError::AccountNotMigrated.into());
+    }
+
+    // check authority_account_info is the distributor authority
+    if distributor_state_account.authority != *authority_account_info.key {
+        return Err(DistributorError::UnauthorizedAccount.into());
+    }
+
+    // check distributor_reward_account_info is same as in distributor state
+    if *distributor_reward_account_info.key != distributor_state_account.reward_token_account {
+        return Err(DistributorError::InvalidAccounts.into());
+    }
+
+    let distributor_transfer_seeds = &[
+        PREFIX.as_bytes(),
+        distributor_state_account_info.key.as_ref(),
+        &[distributor_state_account.authority_bump]
+    ];
+    let reward_account_pda = Pubkey::create_program_address(distributor_transfer_seeds, program_id)?;
+
+    // transfer the tokens left in the vault to authority_reward_account (pda_account signs)
+    let remaining_amount = TokenAccount::unpack(&distributor_reward_account_info.data.borrow())?.amount;
+    if remaining_amount > 0 {
+        let transfer_to_authority_ix = spl_token::instruction::transfer(
+            token_program_account.key,
+            distributor_reward_account_info.key, // src
+            authority_reward_account_info.key, // dst
+            &reward_account_pda, // authority
+            &[&reward_account_pda],
+            remaining_amount,
+        )?;
+        msg!("Calling the token program to transfer {} tokens to the authority account", remaining_amount);
+        invoke_signed(
+            &transfer_to_authority_ix,
+            &[
+                distributor_reward_account_info.clone(),
+                authority_reward_account_info.clone(),
+                pda_account_info.clone(),
+                token_program_account.clone(),
+            ],
+            &[distributor_transfer_seeds]
+        )?;
+    }
+
+    // close the emptied vault, its rent goes to the authority
+    let close_vault_ix = spl_token::instruction::close_account(
+        token_program_account.key,
+        distributor_reward_account_info.key,
+        authority_account_info.key, // dst
+        &reward_account_pda, // authority
+        &[&reward_account_pda],
+    )?;
+    msg!("Calling the token program to close the distributor reward account");
+    invoke_signed(
+        &close_vault_ix,
+        &[
+            distributor_reward_account_info.clone(),
+            authority_account_info.clone(),
+            pda_account_info.clone(),
+            token_program_account.clone(),
+        ],
+        &[distributor_transfer_seeds]
+    )?;
+
+    // close the distributor state, the runtime drops an account without lamports at the end of the transaction
+    let distributor_lamports = distributor_state_account_info.lamports();
+    **distributor_state_account_info.lamports.borrow_mut() = 0;
+    **authority_account_info.lamports.borrow_mut() = authority_account_info
+        .lamports()
+        .checked_add(distributor_lamports)
+        .ok_or(DistributorError::AmountOverflow)?;
+    distributor_state_account_info.data.borrow_mut().fill(0);
+
+    DistributorEvent::DistributorClosed {
+        distributor: *distributor_state_account_info.key,
+        authority: distributor_state_account.authority,
+        remaining_amount,
+    }.emit();
+
+    Ok(())
+}
+
+pub fn process_get_claim_status<'a>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo<'a>],
+    args: GetClaimStatusArgs,
+) -> ProgramResult {
+    // the mint owner and clock sysvar id are checked while parsing
+    let GetClaimStatusAccounts {
+        distributor_state_account: distributor_state_account_info,
+        nft_mint_account: nft_mint_account_info,
+        nft_metadata_account: nft_metadata_account_info,
+        nft_edition_account: nft_edition_account_info,
+        attribute_attestation_account: attribute_attestation_account_info,
+        proof_of_receipt_account: proof_receipt_account_info,
+        clock_sysvar: clock_sysvar_info,
+        claimant_nft_account: claimant_nft_account_info,
+    } = GetClaimStatusAccounts::try_from(accounts)?;
+    let clock = &Clock::from_account_info(clock_sysvar_info)?;
+
+    // the distributor itself must be claimable from, otherwise there is no status to report
+    if distributor_state_account_info.owner != program_id {
+        return Err(DistributorError::IncorrectOwner.into());
+    }
+    let distributor_state_account = DistributorAccount::from_account_info(distributor_state_account_info)?;
+    if !distributor_state_account.is_initialized {
+        return Err(DistributorError::Uninitialized.into());
+    }
+    if distributor_state_account.version != DISTRIBUTOR_VERSION {
+        return Err(DistributorError::AccountNotMigrated.into());
+    }
+
+    // check the proof of receipt account given is the correct one
+    // a receipt stores the bump it was created with, receipts before version 2 and empty accounts need the canonical bump search
+    let already_claimed = !proof_receipt_account_info.data_is_empty();
+    let stored_bump = if already_claimed {
+        let proof_of_receipt_account = ProofOfReceiptAccount::from_account_info(proof_receipt_account_info)?;
+        Some(proof_of_receipt_account.bump).filter(|_| proof_of_receipt_account.version == PROOF_OF_RECEIPT_VERSION)
+    } else {
+        None
+    };
+    let find_receipt_seed = &[
+        RECEIPT_PREFIX.as_bytes(),
+        nft_mint_account_info.key.as_ref(),
+        distributor_state_account_info.key.as_ref()
+    ];
+    let proof_of_receipt_pubkey = match stored_bump {
+        Some(bump) => {
+            let receipt_seeds = &[
+                RECEIPT_PREFIX.as_bytes(),
+                nft_mint_account_info.key.as_ref(),
+                distributor_state_account_info.key.as_ref(),
+                &[bump]
+            ];
+            Pubkey::create_program_address(receipt_seeds, program_id).map_err(|_| DistributorError::InvalidAccounts)?
+        },
+        None => Pubkey::find_program_address(find_receipt_seed, program_id).0,
+    };
+    if proof_of_receipt_pubkey != *proof_receipt_account_info.key {
+        return Err(DistributorError::InvalidAccounts.into());
+    }
+
+    // run the claim checks in the order claim runs them, keeping the first failure
+    let is_print = check_nft_eligibility(
+        program_id,
+        &distributor_state_account,
+        nft_mint_account_info.key,
+        nft_metadata_account_info,
+        nft_edition_account_info,
+        attribute_attestation_account_info,
+        args.metadata_bump,
+        args.edition_bump,
+        args.parent_edition_bump,
+    );
+    let allowlist_amount = check_allowlist(&distributor_state_account, nft_mint_account_info.key, args.allowlist_amount, &args.merkle_proof);
+    let reward_amount = match allowlist_amount {
+        Ok(Some(allowlist_amount)) => allowlist_amount,
+        _ => distributor_state_account.reward_amount(*is_print.as_ref().unwrap_or(&false)),
+    };
+    let remaining_pool = distributor_state_account.reward_amount_total.saturating_sub(distributor_state_account.amount_claimed);
+    // a holder account given must hold the NFT, as the claim checks it
+    let holds_nft = match claimant_nft_account_info {
+        Some(claimant_nft_account_info) => {
+            let claimant_nft_account = TokenAccount::unpack(&claimant_nft_account_info.data.borrow())?;
+            claimant_nft_account.mint == *nft_mint_account_info.key && claimant_nft_account.amount == 1
+        },
+        None => true,
+    };
+    let ineligibility_error: Option<ProgramError> = if distributor_state_account.paused {
+        Some(DistributorError::DistributorPaused.into())
+    } else if clock.unix_timestamp < distributor_state_account.start_ts {
+        Some(DistributorError::DistributionNotStarted.into())
+    } else if !holds_nft {
+        Some(DistributorError::NftNotHeld.into())
+    } else if let Err(error) = is_print {
+        Some(error)
+    } else if let Err(error) = allowlist_amount {
+        Some(error)
+    } else if reward_amount > remaining_pool {
+        Some(DistributorError::RewardPoolExhausted.into())
+    } else if already_claimed {
+        Some(DistributorError::TokensAlreadyClaimed.into())
+    } else {
+        None
+    };
+
+    let eligible = ineligibility_error.is_none();
+    let claim_status = ClaimStatus {
+        eligible,
+        ineligibility_error: ineligibility_error.map(u64::from),
+        already_claimed,
+        // nothing is claimable when the claim would fail
+        claimable_amount: if eligible { reward_amount } else { 0 },
+        remaining_pool,
+        seconds_since_start: clock.unix_timestamp.saturating_sub(distributor_state_account.start_ts),
+    };
+    set_return_data(&claim_status.try_to_vec()?);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        client::{find_edition_address, find_receipt_address},
+        instruction::{attest_attributes, claim_tokens, create_token_distributor, migrate_distributor, CreateTokenDistributorArgs},
+        state::{DistributorAccountV0, MAX_ATTESTATION_DATA_LENGTH, MAX_SYMBOL_LENGTH},
+        utils::{puffed_out_string, ATTESTATION_PREFIX}
+    };
+    use solana_program::{
+        instruction::Instruction,
+        program::get_return_data,
+        program_stubs::{set_syscall_stubs, SyscallStubs},
+        system_program,
+        sysvar
+    };
+    use std::{cell::RefCell, sync::Once};
+    use spl_token_metadata::state::{Creator, Data, Key, MAX_NAME_LENGTH, MAX_URI_LENGTH};
+
+    // owner, lamports and data of an account given to the processor
+    struct TestAccount {
+        owner: Pubkey,
+        lamports: u64,
+        data: Vec<u8>,
+    }
+
+    impl TestAccount {
+        fn new(owner: Pubkey, data: Vec<u8>) -> Self {
+            TestAccount { owner, lamports: 1_000_000_000, data }
+        }
+    }
+
+    fn process(program_id: &Pubkey, instruction: &Instruction, accounts: &mut [TestAccount]) -> ProgramResult {
+        let account_infos: Vec<AccountInfo> = instruction.accounts.iter()
+            .zip(accounts.iter_mut())
+            .map(|(meta, account)| AccountInfo::new(
+                &meta.pubkey,
+                meta.is_signer,
+                meta.is_writable,
+                &mut account.lamports,
+                &mut account.data,
+                &account.owner,
+                false,
+                0,
+            ))
+            .collect();
+        process_instruction(program_id, &account_infos, &instruction.data)
+    }
+
+    fn initialized_distributor(authority: Pubkey, distributor_id: u64) -> Vec<u8> {
+        let mut data = vec![0u8; DistributorAccount::LEN];
+        let mut distributor = DistributorAccount::from_slice(&data).unwrap();
+        distributor.key = AccountKey::Distributor;
+        distributor.version = DISTRIBUTOR_VERSION;
+        distributor.is_initialized = true;
+        distributor.authority = authority;
+        distributor.amount_claimed = 42;
+        distributor.distributor_id = distributor_id;
+        distributor.serialize(&mut &mut data[..]).unwrap();
+        data
+    }
+
+    fn create_distributor_instruction(program_id: Pubkey, authority: Pubkey, collection_symbol: &str) -> Instruction {
+        let (distributor_state, _) = find_distributor_state_address(&program_id, &authority, 7);
+        let accounts = CreateTokenDistributorAccounts {
+            authority_account: authority,
+            distributor_state_account: distributor_state,
+            reward_token_account: Pubkey::new_unique(),
+            collection_creator_account: Pubkey::new_unique(),
+            rent_sysvar: sysvar::rent::id(),
+            token_program_account: spl_token::ID,
+            system_program_account: system_program::id(),
+        };
+        let args = CreateTokenDistributorArgs {
+            distributor_id: 7,
+            reward_amount_total: 1_000,
+            reward_amount_per_nft: 10,
+            start_ts: 0,
+            collection_symbol: collection_symbol.to_string(),
+            reward_amount_per_print: 10,
+            edition_policy: EditionPolicy::Both,
+            trait_filter: None,
+            name_prefix: None,
+            uri_prefix: None,
+            max_claims_per_wallet: None,
+            claim_fee_lamports: 0,
+            fee_treasury: Pubkey::default(),
+            referral_bps: 0,
+            merkle_root: None,
+        };
+        create_token_distributor(program_id, accounts, args)
+    }
+
+    fn create_distributor_accounts(program_id: Pubkey, distributor_state_data: Vec<u8>) -> [TestAccount; 7] {
+        [
+            TestAccount::new(system_program::id(), vec![]),
+            TestAccount::new(program_id, distributor_state_data),
+            TestAccount::new(spl_token::id(), vec![]),
+            TestAccount::new(system_program::id(), vec![]),
+            TestAccount::new(sysvar::id(), vec![]),
+            TestAccount::new(Pubkey::default(), vec![]),
+            TestAccount::new(Pubkey::default(), vec![]),
+        ]
+    }
+
+    #[test]
+    fn test_create_distributor_reinitialization_rejected() {
+        let program_id = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let instruction = create_distributor_instruction(program_id, authority, "SYMBOL");
+        let mut accounts = create_distributor_accounts(program_id, initialized_distributor(authority, 7));
+
+        assert_eq!(
+            process(&program_id, &instruction, &mut accounts),
+            Err(DistributorError::AlreadyInitialized.into())
+        );
+        // the live distributor is left untouched
+        assert_eq!(accounts[1].data, initialized_distributor(authority, 7));
+    }
+
+    #[test]
+    fn test_create_distributor_invalid_symbol_rejected() {
+        let program_id = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let too_long = "S".repeat(MAX_SYMBOL_LENGTH + 1);
+        // 3 emoji, 12 bytes
+        let multibyte = "\u{1F600}\u{1F600}\u{1F600}";
+        for symbol in [too_long.as_str(), multibyte, "SYM\0BOL", "SYM\nBOL"] {
+            let instruction = create_distributor_instruction(program_id, authority, symbol);
+            let mut accounts = create_distributor_accounts(program_id, vec![]);
+            assert_eq!(
+                process(&program_id, &instruction, &mut accounts),
+                Err(DistributorError::InvalidSymbol.into()),
+                "{:?}", symbol
+            );
+        }
+    }
+
+    #[test]
+    fn test_create_distributor_fee_without_treasury_rejected() {
+        let program_id = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let with_fee = |fee_treasury: Pubkey| {
+            let mut instruction = create_distributor_instruction(program_id, authority, "SYMBOL");
+            let mut args = match ClaimTokenInstruction::try_from_slice(&instruction.data).unwrap() {
+                ClaimTokenInstruction::CreateTokenDistributor(args) => args,
+                _ => unreachable!(),
+            };
+            args.claim_fee_lamports = 5_000;
+            args.fee_treasury = fee_treasury;
+            instruction.data = ClaimTokenInstruction::CreateTokenDistributor(args).try_to_vec().unwrap();
+            instruction
+        };
+
+        let mut accounts = create_distributor_accounts(program_id, vec![]);
+        assert_eq!(
+            process(&program_id, &with_fee(Pubkey::default()), &mut accounts),
+            Err(DistributorError::InvalidFeeTreasury.into())
+        );
+
+        // with a treasury the create goes on, to fail on the empty reward token account
+        let mut accounts = create_distributor_accounts(program_id, vec![]);
+        assert_ne!(
+            process(&program_id, &with_fee(Pubkey::new_unique()), &mut accounts),
+            Err(DistributorError::InvalidFeeTreasury.into())
+        );
+    }
+
+    fn print_edition_data(parent: Pubkey) -> Vec<u8> {
+        let mut data = vec![0u8; spl_token_metadata::state::MAX_EDITION_LEN];
+        EditionAccount { key: Key::EditionV1, parent, edition: 1 }.serialize(&mut &mut data[..]).unwrap();
+        data
+    }
+
+    #[test]
+    fn test_edition_policies() {
+        let master_mint = Pubkey::new_unique();
+        let (master_edition, parent_edition_bump) = find_edition_address(&master_mint);
+        let original = [Key::MasterEditionV2 as u8];
+        let print = print_edition_data(master_edition);
+        let other_print = print_edition_data(find_edition_address(&Pubkey::new_unique()).0);
+        let not_eligible = Err(DistributorError::EditionNotEligible.into());
+
+        let mut distributor = DistributorAccount::from_slice(&[0u8; DistributorAccount::LEN]).unwrap();
+        for (edition_policy, original_result, print_result, other_print_result) in [
+            (EditionPolicy::Both, Ok(false), Ok(true), Ok(true)),
+            (EditionPolicy::OriginalsOnly, Ok(false), not_eligible.clone(), not_eligible.clone()),
+            (EditionPolicy::PrintsOnly, not_eligible.clone(), Ok(true), Ok(true)),
+            (EditionPolicy::PrintsWithParent(master_mint), not_eligible.clone(), Ok(true), not_eligible.clone()),
+        ] {
+            distributor.edition_policy = edition_policy;
+            assert_eq!(check_edition_eligibility(&distributor, &original, parent_edition_bump), original_result, "{:?}", edition_policy);
+            assert_eq!(check_edition_eligibility(&distributor, &print, parent_edition_bump), print_result, "{:?}", edition_policy);
+            assert_eq!(check_edition_eligibility(&distributor, &other_print, parent_edition_bump), other_print_result, "{:?}", edition_policy);
+            // an account that is no edition is rejected whatever the policy
+            assert_eq!(
+                check_edition_eligibility(&distributor, &[Key::MetadataV1 as u8], parent_edition_bump),
+                Err(DistributorError::InvalidEditionAccount.into())
+            );
+        }
+
+        // the parent is derived with the bump given, another bump does not derive the master edition
+        distributor.edition_policy = EditionPolicy::PrintsWithParent(master_mint);
+        assert_eq!(check_edition_eligibility(&distributor, &print, parent_edition_bump.wrapping_sub(1)), not_eligible);
+    }
+
+    fn metadata_with_symbol(creator: Pubkey, symbol: &str) -> MetadataAccount {
+        MetadataAccount {
+            key: Key::MetadataV1,
+            update_authority: creator,
+            mint: Pubkey::new_unique(),
+            data: Data {
+                name: String::new(),
+                symbol: symbol.to_string(),
+                uri: String::new(),
+                seller_fee_basis_points: 0,
+                creators: Some(vec![Creator { address: creator, verified: true, share: 100 }]),
+            },
+            primary_sale_happened: false,
+            is_mutable: true,
+            edition_nonce: None,
+        }
+    }
+
+    #[test]
+    fn test_metadata_symbol_compared_without_padding() {
+        let creator = Pubkey::new_unique();
+        let mut distributor = DistributorAccount::from_slice(&[0u8; DistributorAccount::LEN]).unwrap();
+        distributor.collection_creator = creator;
+        distributor.collection_symbol = puffed_out_symbol("SYM").unwrap();
+
+        // metadata symbols padded to the field size and unpadded ones both match
+        for symbol in ["SYM", "SYM\0", "SYM\0\0\0\0\0\0\0"] {
+            assert_eq!(check_metadata_eligibility(&distributor, &metadata_with_symbol(creator, symbol)), Ok(()));
+        }
+        for symbol in ["", "SY", "SYMB", "SYMBOL"] {
+            assert_eq!(
+                check_metadata_eligibility(&distributor, &metadata_with_symbol(creator, symbol)),
+                Err(DistributorError::IncorrectSymbol.into())
+            );
+        }
+    }
+
+    #[test]
+    fn test_allowlist_amount_and_proof_checked() {
+        let (mint, other_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (leaf, other_leaf) = (crate::merkle::hash_leaf(&mint, 25), crate::merkle::hash_leaf(&other_mint, 0));
+        let mut distributor = DistributorAccount::from_slice(&[0u8; DistributorAccount::LEN]).unwrap();
+
+        // without a merkle root every mint is in, with the distributor reward
+        assert_eq!(check_allowlist(&distributor, &mint, 0, &[]), Ok(None));
+
+        distributor.merkle_root = Some(crate::merkle::hash_nodes(&leaf, &other_leaf));
+        assert_eq!(check_allowlist(&distributor, &mint, 25, &[other_leaf]), Ok(Some(25)));
+        assert_eq!(check_allowlist(&distributor, &other_mint, 0, &[leaf]), Ok(None));
+        for (nft_mint, amount, proof) in [(mint, 24, vec![other_leaf]), (mint, 25, vec![]), (Pubkey::new_unique(), 25, vec![other_leaf])] {
+            assert_eq!(check_allowlist(&distributor, &nft_mint, amount, &proof), Err(DistributorError::NotAllowlisted.into()));
+        }
+    }
+
+    #[test]
+    fn test_metadata_name_and_uri_prefix_matching() {
+        let creator = Pubkey::new_unique();
+        let mut distributor = DistributorAccount::from_slice(&[0u8; DistributorAccount::LEN]).unwrap();
+        distributor.collection_creator = creator;
+        distributor.collection_symbol = puffed_out_symbol("SYM").unwrap();
+        distributor.name_prefix = Some("Degen #".to_string());
+        distributor.uri_prefix = Some("https://arweave.net/".to_string());
+
+        let metadata = |name: &str, uri: &str| {
+            let mut metadata = metadata_with_symbol(creator, "SYM");
+            metadata.data.name = puffed_out_string(name, MAX_NAME_LENGTH);
+            metadata.data.uri = puffed_out_string(uri, MAX_URI_LENGTH);
+            metadata
+        };
+        assert_eq!(check_metadata_eligibility(&distributor, &metadata("Degen #42", "https://arweave.net/abc")), Ok(()));
+        for name in ["Degen 42", "degen #42", "Copycat Degen #42", ""] {
+            assert_eq!(
+                check_metadata_eligibility(&distributor, &metadata(name, "https://arweave.net/abc")),
+                Err(DistributorError::IncorrectName.into()),
+                "{:?}", name
+            );
+        }
+        for uri in ["https://arweave.net.attacker.com/abc", "https://arweave.network/abc", "http://arweave.net/abc", ""] {
+            assert_eq!(
+                check_metadata_eligibility(&distributor, &metadata("Degen #42", uri)),
+                Err(DistributorError::IncorrectUri.into()),
+                "{:?}", uri
+            );
+        }
+    }
+
+    #[test]
+    fn test_create_distributor_uri_prefix_without_path_boundary_rejected() {
+        let program_id = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        for uri_prefix in ["https://arweave.net", "https:", "u".repeat(MAX_URI_PREFIX_LENGTH + 1).as_str()] {
+            let mut instruction = create_distributor_instruction(program_id, authority, "SYM");
+            let mut args = match ClaimTokenInstruction::try_from_slice(&instruction.data).unwrap() {
+                ClaimTokenInstruction::CreateTokenDistributor(args) => args,
+                _ => unreachable!(),
+            };
+            args.uri_prefix = Some(uri_prefix.to_string());
+            instruction.data = ClaimTokenInstruction::CreateTokenDistributor(args).try_to_vec().unwrap();
+            let mut accounts = create_distributor_accounts(program_id, vec![]);
+            assert_eq!(
+                process(&program_id, &instruction, &mut accounts),
+                Err(DistributorError::InvalidMetadataPrefix.into()),
+                "{:?}", uri_prefix
+            );
+        }
+    }
+
+    fn token_account_data(mint: Pubkey, owner: Pubkey, amount: u64) -> Vec<u8> {
+        let mut data = vec![0u8; TokenAccount::LEN];
+        TokenAccount { mint, owner, amount, state: spl_token::state::AccountState::Initialized, ..TokenAccount::default() }
+            .pack_into_slice(&mut data);
+        data
+    }
+
+    fn clock_data() -> Vec<u8> {
+        bincode::serialize(&Clock::default()).unwrap()
+    }
+
+    fn claim_instruction(program_id: Pubkey, claimant: Pubkey) -> Instruction {
+        let accounts = ClaimTokensAccounts {
+            claimant_main_account: claimant,
+            distributor_state_account: Pubkey::new_unique(),
+            distributor_reward_account: Pubkey::new_unique(),
+            claimant_reward_account: Pubkey::new_unique(),
+            pda_account: Pubkey::new_unique(),
+            claimant_nft_account: Pubkey::new_unique(),
+            nft_metadata_account: Pubkey::new_unique(),
+            nft_edition_account: Pubkey::new_unique(),
+            attribute_attestation_account: Pubkey::new_unique(),
+            proof_of_receipt_account: Pubkey::new_unique(),
+            wallet_claim_record_account: Pubkey::new_unique(),
+            fee_treasury_account: Pubkey::new_unique(),
+            clock_sysvar: sysvar::clock::id(),
+            rent_sysvar: sysvar::rent::id(),
+            token_program_account: spl_token::ID,
+            system_program_account: system_program::id(),
+            referral_accounts: None,
+        };
+        let args = ClaimTokensArgs {
+            metadata_bump: 255,
+            edition_bump: 255,
+            parent_edition_bump: 255,
+            allowlist_amount: 0,
+            merkle_proof: vec![],
+        };
+        claim_tokens(program_id, accounts, args)
+    }
+
+    // the accounts of claim_instruction, up to the claimant NFT account the data of which is given
+    fn claim_accounts(program_id: Pubkey, distributor_data: Vec<u8>, claimant_nft_data: Vec<u8>) -> Vec<TestAccount> {
+        vec![
+            TestAccount::new(system_program::id(), vec![]),
+            TestAccount::new(program_id, distributor_data),
+            TestAccount::new(spl_token::id(), token_account_data(Pubkey::new_unique(), Pubkey::new_unique(), 1_000)),
+            TestAccount::new(spl_token::id(), token_account_data(Pubkey::new_unique(), Pubkey::new_unique(), 0)),
+            TestAccount::new(system_program::id(), vec![]),
+            TestAccount::new(spl_token::id(), claimant_nft_data),
+            TestAccount::new(spl_token_metadata::id(), vec![]),
+            TestAccount::new(spl_token_metadata::id(), vec![]),
+            TestAccount::new(system_program::id(), vec![]),
+            TestAccount::new(system_program::id(), vec![]),
+            TestAccount::new(system_program::id(), vec![]),
+            TestAccount::new(system_program::id(), vec![]),
+            TestAccount::new(sysvar::id(), clock_data()),
+            TestAccount::new(sysvar::id(), vec![]),
+            TestAccount::new(Pubkey::default(), vec![]),
+            TestAccount::new(Pubkey::default(), vec![]),
+        ]
+    }
+
+    #[test]
+    fn test_claim_with_empty_nft_account_rejected() {
+        let program_id = Pubkey::new_unique();
+        let claimant = Pubkey::new_unique();
+        let instruction = claim_instruction(program_id, claimant);
+        // an account of the claimant for the NFT mint, opened without the NFT
+        let mut accounts = claim_accounts(
+            program_id,
+            initialized_distributor(Pubkey::new_unique(), 0),
+            token_account_data(Pubkey::new_unique(), claimant, 0),
+        );
+
+        assert_eq!(
+            process(&program_id, &instruction, &mut accounts),
+            Err(DistributorError::NftNotHeld.into())
+        );
+    }
+
+    fn distributor_v0_data(is_initialized: bool) -> Vec<u8> {
+        let mut data = vec![0u8; DistributorAccountV0::LEN];
+        DistributorAccountV0 {
+            is_initialized,
+            authority: Pubkey::new_unique(),
+            reward_token_account: Pubkey::new_unique(),
+            reward_mint: Pubkey::new_unique(),
+            reward_amount_total: 1_000,
+            reward_amount_per_nft: 10,
+            amount_claimed: 0,
+            start_ts: 0,
+            collection_symbol: "SYMBOL".to_string(),
+            collection_creator: Pubkey::new_unique(),
+        }.serialize(&mut &mut data[..]).unwrap();
+        data
+    }
+
+    #[test]
+    fn test_claim_uninitialized_or_unmigrated_distributor_rejected() {
+        let program_id = Pubkey::new_unique();
+        let claimant = Pubkey::new_unique();
+        let mut version_3 = initialized_distributor(Pubkey::new_unique(), 0);
+        version_3[1] = 3;
+        version_3.truncate(DistributorAccount::V3_LEN);
+        for (distributor_data, error) in [
+            (vec![0u8; DistributorAccount::LEN], DistributorError::Uninitialized),
+            // a version 0 account is only told apart by its is_initialized byte, without it the data has no layout
+            (distributor_v0_data(false), DistributorError::DataTypeMismatch),
+            (distributor_v0_data(true), DistributorError::AccountNotMigrated),
+            (version_3, DistributorError::AccountNotMigrated),
+        ] {
+            let instruction = claim_instruction(program_id, claimant);
+            let mut accounts = claim_accounts(program_id, distributor_data.clone(), token_account_data(Pubkey::new_unique(), claimant, 1));
+            assert_eq!(process(&program_id, &instruction, &mut accounts), Err(error.clone().into()), "{:?}", error);
+            assert_eq!(accounts[1].data, distributor_data);
+        }
+    }
+
+    #[test]
+    fn test_migrate_uninitialized_distributor_rejected() {
+        let program_id = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        for (distributor_data, error) in [
+            (vec![0u8; DistributorAccount::LEN], DistributorError::Uninitialized),
+            (distributor_v0_data(false), DistributorError::DataTypeMismatch),
+        ] {
+            let instruction = migrate_distributor(program_id, authority, Pubkey::new_unique());
+            let mut accounts = [
+                TestAccount::new(system_program::id(), vec![]),
+                TestAccount::new(program_id, distributor_data.clone()),
+                TestAccount::new(sysvar::id(), bincode::serialize(&Rent::default()).unwrap()),
+                TestAccount::new(Pubkey::default(), vec![]),
+            ];
+            assert_eq!(process(&program_id, &instruction, &mut accounts), Err(error.clone().into()), "{:?}", error);
+            // nothing is reallocated or written
+            assert_eq!(accounts[1].data, distributor_data);
+        }
+    }
+
+    thread_local! {
+        static RETURN_DATA: RefCell<Option<(Pubkey, Vec<u8>)>> = const { RefCell::new(None) };
+    }
+
+    // the default syscall stubs drop return data, these keep it per test thread
+    struct ReturnDataStubs;
+
+    impl SyscallStubs for ReturnDataStubs {
+        fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+            RETURN_DATA.with(|return_data| return_data.borrow().clone())
+        }
+
+        fn sol_set_return_data(&self, data: &[u8]) {
+            RETURN_DATA.with(|return_data| *return_data.borrow_mut() = Some((Pubkey::default(), data.to_vec())));
+        }
+    }
+
+    fn claim_status(program_id: &Pubkey, instruction: &Instruction, accounts: &mut [TestAccount]) -> ClaimStatus {
+        static INSTALL: Once = Once::new();
+        INSTALL.call_once(|| {
+            set_syscall_stubs(Box::new(ReturnDataStubs));
+        });
+        RETURN_DATA.with(|return_data| *return_data.borrow_mut() = None);
+        process(program_id, instruction, accounts).unwrap();
+        ClaimStatus::try_from_slice(&get_return_data().unwrap().1).unwrap()
+    }
+
+    #[test]
+    fn test_get_claim_status_return_data() {
+        let program_id = Pubkey::new_unique();
+        let creator = Pubkey::new_unique();
+        let distributor_state = Pubkey::new_unique();
+        let nft_mint = Pubkey::new_unique();
+        let mut distributor = DistributorAccount::from_slice(&[0u8; DistributorAccount::LEN]).unwrap();
+        distributor.key = AccountKey::Distributor;
+        distributor.version = DISTRIBUTOR_VERSION;
+        distributor.is_initialized = true;
+        distributor.reward_amount_total = 1_000;
+        distributor.reward_amount_per_nft = 100;
+        distributor.reward_amount_per_print = 10;
+        distributor.amount_claimed = 300;
+        distributor.collection_symbol = puffed_out_symbol("SYMBOL").unwrap();
+        distributor.collection_creator = creator;
+        let mut distributor_data = vec![0u8; DistributorAccount::LEN];
+        distributor.serialize(&mut &mut distributor_data[..]).unwrap();
+        let metadata_data = |symbol: &str| {
+            let mut data = vec![0u8; spl_token_metadata::state::MAX_METADATA_LEN];
+            metadata_with_symbol(creator, symbol).serialize(&mut &mut data[..]).unwrap();
+            data
+        };
+        let (_receipt, receipt_bump) = find_receipt_address(&program_id, &nft_mint, &distributor_state);
+        let mut receipt_data = vec![0u8; ProofOfReceiptAccount::LEN];
+        ProofOfReceiptAccount { key: AccountKey::ProofOfReceipt, version: PROOF_OF_RECEIPT_VERSION, received_tokens: true, bump: receipt_bump }
+            .serialize(&mut &mut receipt_data[..])
+            .unwrap();
+        let instruction = crate::client::get_claim_status(&program_id, &distributor_state, &distributor, &nft_mint, None, None);
+        let accounts = |metadata: Vec<u8>, receipt: TestAccount| vec![
+            TestAccount::new(program_id, distributor_data.clone()),
+            TestAccount::new(spl_token::id(), vec![]),
+            TestAccount::new(spl_token_metadata::id(), metadata),
+            TestAccount::new(spl_token_metadata::id(), vec![Key::MasterEditionV2 as u8]),
+            TestAccount::new(system_program::id(), vec![]),
+            receipt,
+            TestAccount::new(sysvar::id(), bincode::serialize(&Clock { unix_timestamp: 60, ..Clock::default() }).unwrap()),
+        ];
+        let unclaimed_status = ClaimStatus {
+            eligible: true,
+            ineligibility_error: None,
+            already_claimed: false,
+            claimable_amount: 100,
+            remaining_pool: 700,
+            seconds_since_start: 60,
+        };
+
+        // unclaimed
+        let mut unclaimed = accounts(metadata_data("SYMBOL"), TestAccount::new(system_program::id(), vec![]));
+        assert_eq!(claim_status(&program_id, &instruction, &mut unclaimed), unclaimed_status);
+
+        // claimed, the receipt exists
+        let mut claimed = accounts(metadata_data("SYMBOL"), TestAccount::new(program_id, receipt_data));
+        assert_eq!(claim_status(&program_id, &instruction, &mut claimed), ClaimStatus {
+            eligible: false,
+            ineligibility_error: Some(DistributorError::TokensAlreadyClaimed as u64),
+            already_claimed: true,
+            claimable_amount: 0,
+            ..unclaimed_status.clone()
+        });
+
+        // ineligible, the metadata is of another collection
+        let mut ineligible = accounts(metadata_data("OTHER"), TestAccount::new(system_program::id(), vec![]));
+        assert_eq!(claim_status(&program_id, &instruction, &mut ineligible), ClaimStatus {
+            eligible: false,
+            ineligibility_error: Some(DistributorError::IncorrectSymbol as u64),
+            claimable_amount: 0,
+            ..unclaimed_status
+        });
+    }
+
+    #[test]
+    fn test_attest_attributes_reinitialization_rejected() {
+        let program_id = Pubkey::new_unique();
+        let oracle = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let (attestation, _) = Pubkey::find_program_address(
+            &[ATTESTATION_PREFIX.as_bytes(), oracle.as_ref(), mint.as_ref()],
+            &program_id,
+        );
+        let instruction = attest_attributes(program_id, oracle, attestation, mint, vec![[1u8; 32]]);
+        let mut existing = vec![0u8; MAX_ATTESTATION_DATA_LENGTH];
+        existing[0] = AccountKey::AttributeAttestation as u8;
+        existing[1] = ATTRIBUTE_ATTESTATION_VERSION;
+        existing[2] = 1; // is_initialized
+        let mut accounts = [
+            TestAccount::new(system_program::id(), vec![]),
+            TestAccount::new(program_id, existing.clone()),
+            TestAccount::new(spl_token::id(), vec![]),
+            TestAccount::new(sysvar::id(), vec![]),
+            TestAccount::new(Pubkey::default(), vec![]),
+        ];
+
+        assert_eq!(
+            process(&program_id, &instruction, &mut accounts),
+            Err(DistributorError::AlreadyInitialized.into())
+        );
+        assert_eq!(accounts[1].data, existing);
+    }
 }
\ No newline at end of file
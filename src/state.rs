@@ -1,19 +1,154 @@
 use solana_program::{
     pubkey::Pubkey,
     account_info::AccountInfo,
-    program_error::ProgramError
+    program_error::ProgramError,
+    program_pack::{IsInitialized, Pack, Sealed}
 };
 use borsh::{BorshSerialize, BorshDeserialize};
+use arrayref::{array_ref, array_refs, array_mut_ref, mut_array_refs};
 use crate::{
-    utils::try_from_slice_checked
+    error::DistributorError,
+    utils::try_from_slice_checked,
+    utils::try_from_slice_versioned
 };
+use std::convert::TryFrom;
+
+// ACCOUNT KEYS AND VERSIONS
+// every account starts with its account key and version, a freshly allocated account is Uninitialized with version 0
+pub const DISTRIBUTOR_VERSION: u8 = 4;
+pub const PROOF_OF_RECEIPT_VERSION: u8 = 2;
+pub const ATTRIBUTE_ATTESTATION_VERSION: u8 = 1;
+pub const WALLET_CLAIM_RECORD_VERSION: u8 = 1;
+pub const REFERRAL_RECORD_VERSION: u8 = 1;
+
+/// Account type discriminator, checked every time an account is loaded
+#[repr(C)]
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub enum AccountKey {
+    Uninitialized,
+    Distributor,
+    ProofOfReceipt,
+    AttributeAttestation,
+    WalletClaimRecord,
+    ReferralRecord,
+}
+
+// ZERO-COPY ACCESS
+// claims read and write the fields they count with through fixed size Pack views, with the same layout as the
+// Borsh encoding: the head of the distributor account, ahead of any variable length field, and the claim records.
+// Packing a view only touches its own bytes, the rest of the account is never reserialized
+
+fn unpack_bool(src: &[u8; 1]) -> Result<bool, ProgramError> {
+    match src {
+        [0] => Ok(false),
+        [1] => Ok(true),
+        _ => Err(ProgramError::InvalidAccountData),
+    }
+}
+
+/// Checks the key and version a Pack view starts with: a freshly allocated account (Uninitialized, version 0)
+/// or the expected account at its current version, as try_from_slice_versioned accepts
+fn unpack_key_and_version(src: &[u8; 2], expected: AccountKey, current_version: u8) -> Result<(AccountKey, u8), ProgramError> {
+    match src {
+        [0, 0] => Ok((AccountKey::Uninitialized, 0)),
+        [key, version] if *key == expected as u8 && *version == current_version => Ok((expected, current_version)),
+        _ => Err(DistributorError::DataTypeMismatch.into()),
+    }
+}
+
+/// The head of a distributor account: its flags, pool totals and amount_claimed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DistributorHeader {
+    pub key: AccountKey,
+    pub version: u8,
+    pub is_initialized: bool,
+    pub authority: Pubkey,
+    pub reward_token_account: Pubkey,
+    pub reward_mint: Pubkey,
+    pub reward_amount_total: u64,
+    pub reward_amount_per_nft: u64,
+    pub amount_claimed: u64,
+    pub start_ts: i64
+}
+
+impl Sealed for DistributorHeader {}
+
+impl IsInitialized for DistributorHeader {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for DistributorHeader {
+    const LEN: usize = 1 + 1 + 1 + 32 + 32 + 32 + 8 + 8 + 8 + 8;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, DistributorHeader::LEN];
+        let (key_and_version, is_initialized, authority, reward_token_account, reward_mint, reward_amount_total, reward_amount_per_nft, amount_claimed, start_ts) =
+            array_refs![src, 2, 1, 32, 32, 32, 8, 8, 8, 8];
+        let (key, version) = unpack_key_and_version(key_and_version, AccountKey::Distributor, DISTRIBUTOR_VERSION)?;
+        Ok(DistributorHeader {
+            key,
+            version,
+            is_initialized: unpack_bool(is_initialized)?,
+            authority: Pubkey::new_from_array(*authority),
+            reward_token_account: Pubkey::new_from_array(*reward_token_account),
+            reward_mint: Pubkey::new_from_array(*reward_mint),
+            reward_amount_total: u64::from_le_bytes(*reward_amount_total),
+            reward_amount_per_nft: u64::from_le_bytes(*reward_amount_per_nft),
+            amount_claimed: u64::from_le_bytes(*amount_claimed),
+            start_ts: i64::from_le_bytes(*start_ts),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, DistributorHeader::LEN];
+        let (key, version, is_initialized, authority, reward_token_account, reward_mint, reward_amount_total, reward_amount_per_nft, amount_claimed, start_ts) =
+            mut_array_refs![dst, 1, 1, 1, 32, 32, 32, 8, 8, 8, 8];
+        key[0] = self.key as u8;
+        version[0] = self.version;
+        is_initialized[0] = self.is_initialized as u8;
+        authority.copy_from_slice(self.authority.as_ref());
+        reward_token_account.copy_from_slice(self.reward_token_account.as_ref());
+        reward_mint.copy_from_slice(self.reward_mint.as_ref());
+        *reward_amount_total = self.reward_amount_total.to_le_bytes();
+        *reward_amount_per_nft = self.reward_amount_per_nft.to_le_bytes();
+        *amount_claimed = self.amount_claimed.to_le_bytes();
+        *start_ts = self.start_ts.to_le_bytes();
+    }
+}
 
 // DISTRIBUTOR ACCOUNT
 pub const MAX_SYMBOL_LENGTH: usize = 10;
-pub const MAX_DISTRIBUTOR_DATA_LENGTH: usize = 1 + 32 + 32 + 32 + 32 + 8 + 8 + MAX_SYMBOL_LENGTH + 32;
+pub const MAX_NAME_PREFIX_LENGTH: usize = spl_token_metadata::state::MAX_NAME_LENGTH;
+pub const MAX_URI_PREFIX_LENGTH: usize = spl_token_metadata::state::MAX_URI_LENGTH;
+pub const MAX_EDITION_POLICY_LENGTH: usize = 1 + 32;
+pub const MAX_TRAIT_FILTER_LENGTH: usize = 1 + 32 + 32;
 
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
+/// Which kind of NFT editions are eligible to claim from a distributor
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub enum EditionPolicy {
+    // only master editions (1/1 originals)
+    OriginalsOnly,
+    // only print editions
+    PrintsOnly,
+    // master editions and print editions, NFTs without an edition account claim as originals
+    Both,
+    // only print editions printed from the master edition of the given mint
+    PrintsWithParent(Pubkey),
+}
+
+/// Trait an NFT must have, as attested by the oracle in an AttributeAttestationAccount
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub struct TraitFilter {
+    pub oracle: Pubkey,
+    pub trait_hash: [u8; 32] // utils::hash_trait of the required trait
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct DistributorAccount {
+    pub key: AccountKey,
+    pub version: u8, // 0 for accounts created before versioning, until migrated
     pub is_initialized: bool,
     pub authority: Pubkey, 
     pub reward_token_account: Pubkey,
@@ -22,29 +157,679 @@ pub struct DistributorAccount {
     pub reward_amount_per_nft: u64,
     pub amount_claimed: u64,
     pub start_ts: i64,
-    pub collection_symbol: String,
-    pub collection_creator: Pubkey // candy machine (/the first creator in token metadata)
+    pub collection_symbol: [u8; MAX_SYMBOL_LENGTH], // padded with 0u8s, as in the metadata account
+    pub collection_creator: Pubkey, // candy machine (/the first creator in token metadata)
+    pub reward_amount_per_print: u64, // reward_amount_per_nft is paid for originals
+    pub edition_policy: EditionPolicy,
+    pub trait_filter: Option<TraitFilter>,
+    pub name_prefix: Option<String>, // metadata name must start with this
+    pub uri_prefix: Option<String>, // metadata uri must start with this, ends with '/' (e.g. "https://arweave.net/")
+    pub max_claims_per_wallet: Option<u64>,
+    pub claim_fee_lamports: u64, // paid by the claimant on every claim, 0 for no fee
+    pub fee_treasury: Pubkey, // receives the claim fee
+    pub referral_bps: u16, // referrer bonus in basis points of the claimed amount, paid from the pool
+    pub distributor_id: u64, // with authority, seeds the distributor state PDA
+    pub authority_bump: u8, // bump seed of the PDA that owns reward_token_account
+    pub merkle_root: Option<[u8; 32]>, // root of the mint[,amount] allowlist (see merkle), None to allow every mint
+    pub paused: bool, // set by the authority, claims fail while it is set
 }
 
 impl DistributorAccount {
+    /// Size of the account - the Borsh encoding with every option set and every string at its max length
+    pub const LEN: usize = DistributorAccount::V3_LEN
+        + 1; // paused
+    pub const V3_LEN: usize = DistributorAccount::V2_LEN
+        + 1 + 32; // merkle_root
+    pub const V2_LEN: usize = DistributorAccount::V1_LEN
+        + 1; // authority_bump
+    pub const V1_LEN: usize = 1 // key
+        + 1 // version
+        + 1 // is_initialized
+        + 32 // authority
+        + 32 // reward_token_account
+        + 32 // reward_mint
+        + 8 // reward_amount_total
+        + 8 // reward_amount_per_nft
+        + 8 // amount_claimed
+        + 8 // start_ts
+        + MAX_SYMBOL_LENGTH // collection_symbol
+        + 32 // collection_creator
+        + 8 // reward_amount_per_print
+        + MAX_EDITION_POLICY_LENGTH // edition_policy
+        + MAX_TRAIT_FILTER_LENGTH // trait_filter
+        + 1 + 4 + MAX_NAME_PREFIX_LENGTH // name_prefix
+        + 1 + 4 + MAX_URI_PREFIX_LENGTH // uri_prefix
+        + 1 + 8 // max_claims_per_wallet
+        + 8 // claim_fee_lamports
+        + 32 // fee_treasury
+        + 2 // referral_bps
+        + 8; // distributor_id
+    pub const VERSION_SIZES: [usize; DISTRIBUTOR_VERSION as usize] = [DistributorAccount::V1_LEN, DistributorAccount::V2_LEN, DistributorAccount::V3_LEN, DistributorAccount::LEN];
+
     pub fn from_account_info(a: &AccountInfo) -> Result<DistributorAccount, ProgramError> {
-        let distributor: DistributorAccount =
-            try_from_slice_checked(&a.data.borrow_mut(), MAX_DISTRIBUTOR_DATA_LENGTH)?;
+        DistributorAccount::from_slice(&a.data.borrow())
+    }
+
+    pub fn from_slice(data: &[u8]) -> Result<DistributorAccount, ProgramError> {
+        // accounts created before versioning have no account key or version, they start with is_initialized instead.
+        // No versioned account has their size, and only initialized ones hold a distributor worth migrating
+        if data.len() == DistributorAccountV0::LEN && data[0] == 1 {
+            let distributor: DistributorAccountV0 = try_from_slice_checked(data, DistributorAccountV0::LEN)?;
+            return DistributorAccount::try_from(distributor);
+        }
+
+        try_from_slice_versioned(data, AccountKey::Distributor, &DistributorAccount::VERSION_SIZES)
+    }
+
+    /// Reward for claiming with an original or a print
+    pub fn reward_amount(&self, is_print: bool) -> u64 {
+        if is_print {
+            self.reward_amount_per_print
+        } else {
+            self.reward_amount_per_nft
+        }
+    }
 
-        Ok(distributor)
+    /// Collection symbol without its `0u8` padding
+    pub fn trimmed_collection_symbol(&self) -> &[u8] {
+        let len = self.collection_symbol.iter().rposition(|b| *b != 0).map_or(0, |last| last + 1);
+        &self.collection_symbol[..len]
+    }
+}
+
+/// Distributor account layout before account keys and versions were added.
+/// The client allocated the account at LEN bytes, the Borsh encoding only fills the start of it
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct DistributorAccountV0 {
+    pub is_initialized: bool,
+    pub authority: Pubkey,
+    pub reward_token_account: Pubkey,
+    pub reward_mint: Pubkey,
+    pub reward_amount_total: u64,
+    pub reward_amount_per_nft: u64,
+    pub amount_claimed: u64,
+    pub start_ts: i64,
+    pub collection_symbol: String, // unpadded, claims padded it to compare with the metadata symbol
+    pub collection_creator: Pubkey
+}
+
+impl DistributorAccountV0 {
+    // as it was computed then - one pubkey more than the layout has, and no string length prefix
+    pub const LEN: usize = 1 + 32 + 32 + 32 + 32 + 8 + 8 + MAX_SYMBOL_LENGTH + 32;
+}
+
+impl TryFrom<DistributorAccountV0> for DistributorAccount {
+    type Error = ProgramError;
+
+    /// Version 0 distributors paid the same reward for every edition and had none of the later filters.
+    /// They were not PDAs, so they have no distributor_id, and their authority_bump is found when they are migrated
+    fn try_from(distributor: DistributorAccountV0) -> Result<Self, Self::Error> {
+        // a symbol longer than the metadata field never matched a claim, there is nothing to migrate
+        let symbol = distributor.collection_symbol.as_bytes();
+        if symbol.len() > MAX_SYMBOL_LENGTH {
+            return Err(DistributorError::InvalidSymbol.into());
+        }
+        let mut collection_symbol = [0u8; MAX_SYMBOL_LENGTH];
+        collection_symbol[..symbol.len()].copy_from_slice(symbol);
+
+        Ok(DistributorAccount {
+            key: AccountKey::Distributor,
+            version: 0,
+            is_initialized: distributor.is_initialized,
+            authority: distributor.authority,
+            reward_token_account: distributor.reward_token_account,
+            reward_mint: distributor.reward_mint,
+            reward_amount_total: distributor.reward_amount_total,
+            reward_amount_per_nft: distributor.reward_amount_per_nft,
+            amount_claimed: distributor.amount_claimed,
+            start_ts: distributor.start_ts,
+            collection_symbol,
+            collection_creator: distributor.collection_creator,
+            reward_amount_per_print: distributor.reward_amount_per_nft,
+            edition_policy: EditionPolicy::Both,
+            trait_filter: None,
+            name_prefix: None,
+            uri_prefix: None,
+            max_claims_per_wallet: None,
+            claim_fee_lamports: 0,
+            fee_treasury: Pubkey::default(),
+            referral_bps: 0,
+            distributor_id: 0,
+            authority_bump: 0,
+            merkle_root: None,
+            paused: false,
+        })
     }
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
 pub struct ProofOfReceiptAccount {
-    pub received_tokens: bool
+    pub key: AccountKey,
+    pub version: u8,
+    pub received_tokens: bool,
+    pub bump: u8
 }
 
 impl ProofOfReceiptAccount {
+    pub const LEN: usize = 1 + 1 + 1 + 1; // key, version, received_tokens, bump
+    pub const V1_LEN: usize = 1 + 1 + 1; // key, version, received_tokens
+    pub const V0_LEN: usize = 1; // received_tokens
+    pub const VERSION_SIZES: [usize; PROOF_OF_RECEIPT_VERSION as usize] = [ProofOfReceiptAccount::V1_LEN, ProofOfReceiptAccount::LEN];
+
     pub fn from_account_info(a: &AccountInfo) -> Result<ProofOfReceiptAccount, ProgramError> {
-        let receipt: ProofOfReceiptAccount =
-            try_from_slice_checked(&a.data.borrow_mut(), 1)?;
+        ProofOfReceiptAccount::from_slice(&a.data.borrow())
+    }
+
+    pub fn from_slice(data: &[u8]) -> Result<ProofOfReceiptAccount, ProgramError> {
+        // receipts created before versioning only hold received_tokens
+        if data.len() == ProofOfReceiptAccount::V0_LEN {
+            return Ok(ProofOfReceiptAccount {
+                key: AccountKey::ProofOfReceipt,
+                version: 0,
+                received_tokens: data[0] != 0,
+                bump: 0,
+            });
+        }
+
+        try_from_slice_versioned(data, AccountKey::ProofOfReceipt, &ProofOfReceiptAccount::VERSION_SIZES)
+    }
+}
+
+// ATTRIBUTE ATTESTATION ACCOUNT
+pub const MAX_ATTESTED_TRAITS: usize = 16;
+pub const MAX_ATTESTATION_DATA_LENGTH: usize = 1 + 1 + 1 + 32 + 32 + 4 + 32 * MAX_ATTESTED_TRAITS;
+
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub struct AttributeAttestationAccount {
+    pub key: AccountKey,
+    pub version: u8,
+    pub is_initialized: bool,
+    pub oracle: Pubkey,
+    pub mint: Pubkey,
+    pub trait_hashes: Vec<[u8; 32]> // utils::hash_trait of every trait of the NFT
+}
+
+impl AttributeAttestationAccount {
+    pub fn from_account_info(a: &AccountInfo) -> Result<AttributeAttestationAccount, ProgramError> {
+        AttributeAttestationAccount::from_slice(&a.data.borrow())
+    }
+
+    pub fn from_slice(data: &[u8]) -> Result<AttributeAttestationAccount, ProgramError> {
+        try_from_slice_versioned(data, AccountKey::AttributeAttestation, &[MAX_ATTESTATION_DATA_LENGTH])
+    }
+}
+
+// WALLET CLAIM RECORD ACCOUNT
+pub const WALLET_CLAIM_RECORD_DATA_LENGTH: usize = 1 + 1 + 1 + 32 + 32 + 8;
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct WalletClaimRecordAccount {
+    pub key: AccountKey,
+    pub version: u8,
+    pub is_initialized: bool,
+    pub distributor: Pubkey,
+    pub claimant: Pubkey,
+    pub claims: u64
+}
+
+impl Sealed for WalletClaimRecordAccount {}
+
+impl IsInitialized for WalletClaimRecordAccount {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for WalletClaimRecordAccount {
+    const LEN: usize = WALLET_CLAIM_RECORD_DATA_LENGTH;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, WalletClaimRecordAccount::LEN];
+        let (key_and_version, is_initialized, distributor, claimant, claims) = array_refs![src, 2, 1, 32, 32, 8];
+        let (key, version) = unpack_key_and_version(key_and_version, AccountKey::WalletClaimRecord, WALLET_CLAIM_RECORD_VERSION)?;
+        Ok(WalletClaimRecordAccount {
+            key,
+            version,
+            is_initialized: unpack_bool(is_initialized)?,
+            distributor: Pubkey::new_from_array(*distributor),
+            claimant: Pubkey::new_from_array(*claimant),
+            claims: u64::from_le_bytes(*claims),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, WalletClaimRecordAccount::LEN];
+        let (key, version, is_initialized, distributor, claimant, claims) = mut_array_refs![dst, 1, 1, 1, 32, 32, 8];
+        key[0] = self.key as u8;
+        version[0] = self.version;
+        is_initialized[0] = self.is_initialized as u8;
+        distributor.copy_from_slice(self.distributor.as_ref());
+        claimant.copy_from_slice(self.claimant.as_ref());
+        *claims = self.claims.to_le_bytes();
+    }
+}
+
+impl WalletClaimRecordAccount {
+    pub fn from_account_info(a: &AccountInfo) -> Result<WalletClaimRecordAccount, ProgramError> {
+        WalletClaimRecordAccount::from_slice(&a.data.borrow())
+    }
+
+    pub fn from_slice(data: &[u8]) -> Result<WalletClaimRecordAccount, ProgramError> {
+        try_from_slice_versioned(data, AccountKey::WalletClaimRecord, &[WALLET_CLAIM_RECORD_DATA_LENGTH])
+    }
+}
+
+// REFERRAL RECORD ACCOUNT
+pub const REFERRAL_RECORD_DATA_LENGTH: usize = 1 + 1 + 1 + 32 + 32 + 8 + 8;
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq)]
+pub struct ReferralRecordAccount {
+    pub key: AccountKey,
+    pub version: u8,
+    pub is_initialized: bool,
+    pub distributor: Pubkey,
+    pub referrer: Pubkey,
+    pub referred_claims: u64,
+    pub amount_earned: u64
+}
+
+impl Sealed for ReferralRecordAccount {}
+
+impl IsInitialized for ReferralRecordAccount {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for ReferralRecordAccount {
+    const LEN: usize = REFERRAL_RECORD_DATA_LENGTH;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, ReferralRecordAccount::LEN];
+        let (key_and_version, is_initialized, distributor, referrer, referred_claims, amount_earned) = array_refs![src, 2, 1, 32, 32, 8, 8];
+        let (key, version) = unpack_key_and_version(key_and_version, AccountKey::ReferralRecord, REFERRAL_RECORD_VERSION)?;
+        Ok(ReferralRecordAccount {
+            key,
+            version,
+            is_initialized: unpack_bool(is_initialized)?,
+            distributor: Pubkey::new_from_array(*distributor),
+            referrer: Pubkey::new_from_array(*referrer),
+            referred_claims: u64::from_le_bytes(*referred_claims),
+            amount_earned: u64::from_le_bytes(*amount_earned),
+        })
+    }
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, ReferralRecordAccount::LEN];
+        let (key, version, is_initialized, distributor, referrer, referred_claims, amount_earned) = mut_array_refs![dst, 1, 1, 1, 32, 32, 8, 8];
+        key[0] = self.key as u8;
+        version[0] = self.version;
+        is_initialized[0] = self.is_initialized as u8;
+        distributor.copy_from_slice(self.distributor.as_ref());
+        referrer.copy_from_slice(self.referrer.as_ref());
+        *referred_claims = self.referred_claims.to_le_bytes();
+        *amount_earned = self.amount_earned.to_le_bytes();
+    }
+}
+
+impl ReferralRecordAccount {
+    pub fn from_account_info(a: &AccountInfo) -> Result<ReferralRecordAccount, ProgramError> {
+        ReferralRecordAccount::from_slice(&a.data.borrow())
+    }
+
+    pub fn from_slice(data: &[u8]) -> Result<ReferralRecordAccount, ProgramError> {
+        try_from_slice_versioned(data, AccountKey::ReferralRecord, &[REFERRAL_RECORD_DATA_LENGTH])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::puffed_out_symbol;
+
+    fn distributor(symbol: &str) -> DistributorAccount {
+        DistributorAccount {
+            key: AccountKey::Distributor,
+            version: DISTRIBUTOR_VERSION,
+            is_initialized: true,
+            authority: Pubkey::new_unique(),
+            reward_token_account: Pubkey::new_unique(),
+            reward_mint: Pubkey::new_unique(),
+            reward_amount_total: u64::MAX,
+            reward_amount_per_nft: u64::MAX,
+            amount_claimed: u64::MAX,
+            start_ts: i64::MIN,
+            collection_symbol: puffed_out_symbol(symbol).unwrap(),
+            collection_creator: Pubkey::new_unique(),
+            reward_amount_per_print: u64::MAX,
+            edition_policy: EditionPolicy::Both,
+            trait_filter: None,
+            name_prefix: None,
+            uri_prefix: None,
+            max_claims_per_wallet: None,
+            claim_fee_lamports: u64::MAX,
+            fee_treasury: Pubkey::new_unique(),
+            referral_bps: u16::MAX,
+            distributor_id: u64::MAX,
+            authority_bump: u8::MAX,
+            merkle_root: None,
+            paused: false,
+        }
+    }
+
+    fn full_distributor(symbol: &str) -> DistributorAccount {
+        DistributorAccount {
+            edition_policy: EditionPolicy::PrintsWithParent(Pubkey::new_unique()),
+            trait_filter: Some(TraitFilter {
+                oracle: Pubkey::new_unique(),
+                trait_hash: [7u8; 32],
+            }),
+            name_prefix: Some("n".repeat(MAX_NAME_PREFIX_LENGTH)),
+            uri_prefix: Some("u".repeat(MAX_URI_PREFIX_LENGTH)),
+            max_claims_per_wallet: Some(u64::MAX),
+            merkle_root: Some([9u8; 32]),
+            paused: true,
+            ..distributor(symbol)
+        }
+    }
+
+    fn round_trip(distributor: &DistributorAccount) -> DistributorAccount {
+        let mut data = vec![0u8; DistributorAccount::LEN];
+        distributor.serialize(&mut &mut data[..]).unwrap();
+        DistributorAccount::from_slice(&data).unwrap()
+    }
+
+    #[test]
+    fn test_distributor_len_matches_largest_encoding() {
+        for len in 0..=MAX_SYMBOL_LENGTH {
+            let encoded = full_distributor(&"S".repeat(len)).try_to_vec().unwrap();
+            assert_eq!(encoded.len(), DistributorAccount::LEN);
+        }
+    }
+
+    #[test]
+    fn test_distributor_round_trip_every_symbol_length() {
+        for len in 0..=MAX_SYMBOL_LENGTH {
+            let symbol = "S".repeat(len);
+            for distributor in [distributor(&symbol), full_distributor(&symbol)].iter() {
+                let decoded = round_trip(distributor);
+                assert_eq!(&decoded, distributor);
+                assert_eq!(&decoded.collection_symbol[..len], symbol.as_bytes());
+                assert!(decoded.collection_symbol[len..].iter().all(|b| *b == 0));
+            }
+        }
+    }
+
+    #[test]
+    fn test_symbol_longer_than_max_rejected() {
+        assert_eq!(
+            puffed_out_symbol(&"S".repeat(MAX_SYMBOL_LENGTH + 1)).unwrap_err(),
+            DistributorError::InvalidSymbol.into()
+        );
+    }
+
+    #[test]
+    fn test_trimmed_collection_symbol() {
+        for len in 0..=MAX_SYMBOL_LENGTH {
+            let symbol = "S".repeat(len);
+            assert_eq!(distributor(&symbol).trimmed_collection_symbol(), symbol.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_distributor_wrong_size_rejected() {
+        let data = vec![0u8; DistributorAccount::LEN - 1];
+        assert_eq!(
+            DistributorAccount::from_slice(&data).unwrap_err(),
+            DistributorError::DataTypeMismatch.into()
+        );
+    }
+
+    #[test]
+    fn test_distributor_wrong_key_rejected() {
+        for key in [AccountKey::ProofOfReceipt, AccountKey::AttributeAttestation, AccountKey::WalletClaimRecord, AccountKey::ReferralRecord].iter() {
+            let mut data = vec![0u8; DistributorAccount::LEN];
+            DistributorAccount { key: *key, ..distributor("SYMBOL") }.serialize(&mut &mut data[..]).unwrap();
+            assert_eq!(
+                DistributorAccount::from_slice(&data).unwrap_err(),
+                DistributorError::DataTypeMismatch.into()
+            );
+        }
+    }
+
+    #[test]
+    fn test_uninitialized_account_loaded_by_every_type() {
+        let data = vec![0u8; DistributorAccount::LEN];
+        let distributor = DistributorAccount::from_slice(&data).unwrap();
+        assert_eq!(distributor.key, AccountKey::Uninitialized);
+        assert!(!distributor.is_initialized);
+
+        let data = vec![0u8; WALLET_CLAIM_RECORD_DATA_LENGTH];
+        let record: WalletClaimRecordAccount =
+            try_from_slice_versioned(&data, AccountKey::WalletClaimRecord, &[WALLET_CLAIM_RECORD_DATA_LENGTH]).unwrap();
+        assert_eq!(record.key, AccountKey::Uninitialized);
+    }
+
+    #[test]
+    fn test_record_loaded_as_other_record_rejected() {
+        // wallet claim and referral records only differ by the trailing amount_earned, so truncate to the same size
+        let mut data = ReferralRecordAccount {
+            key: AccountKey::ReferralRecord,
+            version: REFERRAL_RECORD_VERSION,
+            is_initialized: true,
+            distributor: Pubkey::new_unique(),
+            referrer: Pubkey::new_unique(),
+            referred_claims: 1,
+            amount_earned: 1,
+        }.try_to_vec().unwrap();
+        data.truncate(WALLET_CLAIM_RECORD_DATA_LENGTH);
+        assert_eq!(
+            try_from_slice_versioned::<WalletClaimRecordAccount>(&data, AccountKey::WalletClaimRecord, &[WALLET_CLAIM_RECORD_DATA_LENGTH]).unwrap_err(),
+            DistributorError::DataTypeMismatch.into()
+        );
+    }
+
+    fn distributor_v0(symbol: &str) -> DistributorAccountV0 {
+        DistributorAccountV0 {
+            is_initialized: true,
+            authority: Pubkey::new_unique(),
+            reward_token_account: Pubkey::new_unique(),
+            reward_mint: Pubkey::new_unique(),
+            reward_amount_total: 1_000,
+            reward_amount_per_nft: 10,
+            amount_claimed: 30,
+            start_ts: 1_600_000_000,
+            collection_symbol: symbol.to_string(),
+            collection_creator: Pubkey::new_unique(),
+        }
+    }
+
+    #[test]
+    fn test_distributor_v0_decoded_as_version_0() {
+        let distributor = distributor_v0("SYMBOL");
+        let mut data = vec![0u8; DistributorAccountV0::LEN];
+        distributor.serialize(&mut &mut data[..]).unwrap();
+
+        let decoded = DistributorAccount::from_slice(&data).unwrap();
+        assert_eq!(decoded, DistributorAccount {
+            key: AccountKey::Distributor,
+            version: 0,
+            is_initialized: true,
+            authority: distributor.authority,
+            reward_token_account: distributor.reward_token_account,
+            reward_mint: distributor.reward_mint,
+            reward_amount_total: 1_000,
+            reward_amount_per_nft: 10,
+            amount_claimed: 30,
+            start_ts: 1_600_000_000,
+            collection_symbol: puffed_out_symbol("SYMBOL").unwrap(),
+            collection_creator: distributor.collection_creator,
+            reward_amount_per_print: 10,
+            edition_policy: EditionPolicy::Both,
+            trait_filter: None,
+            name_prefix: None,
+            uri_prefix: None,
+            max_claims_per_wallet: None,
+            claim_fee_lamports: 0,
+            fee_treasury: Pubkey::default(),
+            referral_bps: 0,
+            distributor_id: 0,
+            authority_bump: 0,
+            merkle_root: None,
+            paused: false,
+        });
+    }
+
+    #[test]
+    fn test_distributor_v0_fallback_needs_initialized_account_of_v0_size() {
+        let mut data = vec![0u8; DistributorAccountV0::LEN];
+        distributor_v0("SYMBOL").serialize(&mut &mut data[..]).unwrap();
+
+        // one byte off the v0 size is not a v0 distributor, whatever it starts with
+        let mut longer = data.clone();
+        longer.push(0);
+        assert_eq!(DistributorAccount::from_slice(&longer).unwrap_err(), DistributorError::DataTypeMismatch.into());
+        assert_eq!(DistributorAccount::from_slice(&data[1..]).unwrap_err(), DistributorError::DataTypeMismatch.into());
+
+        // an uninitialized account of the v0 size is not either
+        data[0] = 0;
+        assert_eq!(DistributorAccount::from_slice(&data).unwrap_err(), DistributorError::DataTypeMismatch.into());
+        assert_eq!(DistributorAccount::from_slice(&[0u8; DistributorAccountV0::LEN]).unwrap_err(), DistributorError::DataTypeMismatch.into());
+    }
+
+    #[test]
+    fn test_distributor_v0_symbol_longer_than_metadata_field_rejected() {
+        let mut data = vec![0u8; DistributorAccountV0::LEN];
+        distributor_v0(&"S".repeat(MAX_SYMBOL_LENGTH + 1)).serialize(&mut &mut data[..]).unwrap();
+        assert_eq!(DistributorAccount::from_slice(&data).unwrap_err(), DistributorError::InvalidSymbol.into());
+    }
+
+    #[test]
+    fn test_distributor_v1_decoded_as_version_1() {
+        let distributor = DistributorAccount { version: 1, ..full_distributor("SYMBOL") };
+        // the v1 layout is the current layout without the trailing authority_bump, merkle_root and paused
+        let encoded = distributor.try_to_vec().unwrap();
+        let decoded = DistributorAccount::from_slice(&encoded[..DistributorAccount::V1_LEN]).unwrap();
+        assert_eq!(decoded, DistributorAccount { authority_bump: 0, merkle_root: None, paused: false, ..distributor });
+
+        // a version 1 account must have the version 1 size
+        assert_eq!(
+            DistributorAccount::from_slice(&encoded).unwrap_err(),
+            DistributorError::DataTypeMismatch.into()
+        );
+    }
+
+    #[test]
+    fn test_distributor_v2_decoded_as_version_2() {
+        let distributor = DistributorAccount { version: 2, ..full_distributor("SYMBOL") };
+        // the v2 layout is the current layout without the trailing merkle_root and paused
+        let encoded = distributor.try_to_vec().unwrap();
+        let decoded = DistributorAccount::from_slice(&encoded[..DistributorAccount::V2_LEN]).unwrap();
+        assert_eq!(decoded, DistributorAccount { merkle_root: None, paused: false, ..distributor });
+    }
+
+    #[test]
+    fn test_distributor_v3_decoded_as_version_3() {
+        let distributor = DistributorAccount { version: 3, ..full_distributor("SYMBOL") };
+        // the v3 layout is the current layout without the trailing paused
+        let encoded = distributor.try_to_vec().unwrap();
+        let decoded = DistributorAccount::from_slice(&encoded[..DistributorAccount::V3_LEN]).unwrap();
+        assert_eq!(decoded, DistributorAccount { paused: false, ..distributor });
+    }
+
+    #[test]
+    fn test_distributor_unknown_version_rejected() {
+        let mut data = vec![0u8; DistributorAccount::LEN];
+        distributor("SYMBOL").serialize(&mut &mut data[..]).unwrap();
+        data[1] = DISTRIBUTOR_VERSION + 1;
+        assert_eq!(
+            DistributorAccount::from_slice(&data).unwrap_err(),
+            DistributorError::DataTypeMismatch.into()
+        );
+    }
+
+    #[test]
+    fn test_distributor_header_matches_layout() {
+        let mut data = vec![0u8; DistributorAccount::LEN];
+        let distributor = DistributorAccount { amount_claimed: 42, ..full_distributor("SYMBOL") };
+        distributor.serialize(&mut &mut data[..]).unwrap();
+
+        let mut header = DistributorHeader::unpack_from_slice(&data).unwrap();
+        assert_eq!(header, DistributorHeader {
+            key: AccountKey::Distributor,
+            version: DISTRIBUTOR_VERSION,
+            is_initialized: true,
+            authority: distributor.authority,
+            reward_token_account: distributor.reward_token_account,
+            reward_mint: distributor.reward_mint,
+            reward_amount_total: distributor.reward_amount_total,
+            reward_amount_per_nft: distributor.reward_amount_per_nft,
+            amount_claimed: 42,
+            start_ts: distributor.start_ts,
+        });
+
+        // packing the header leaves the rest of the account untouched
+        header.amount_claimed = 43;
+        header.pack_into_slice(&mut data);
+        assert_eq!(DistributorAccount::from_slice(&data).unwrap(), DistributorAccount { amount_claimed: 43, ..distributor });
+    }
+
+    #[test]
+    fn test_distributor_header_other_version_rejected() {
+        let mut data = vec![0u8; DistributorAccount::LEN];
+        distributor("SYMBOL").serialize(&mut &mut data[..]).unwrap();
+        data[1] = DISTRIBUTOR_VERSION - 1;
+        assert_eq!(DistributorHeader::unpack_from_slice(&data).unwrap_err(), DistributorError::DataTypeMismatch.into());
+    }
+
+    #[test]
+    fn test_claim_records_pack_matches_layout() {
+        let record = WalletClaimRecordAccount {
+            key: AccountKey::WalletClaimRecord,
+            version: WALLET_CLAIM_RECORD_VERSION,
+            is_initialized: true,
+            distributor: Pubkey::new_unique(),
+            claimant: Pubkey::new_unique(),
+            claims: 2,
+        };
+        let data = record.try_to_vec().unwrap();
+        assert_eq!(WalletClaimRecordAccount::unpack(&data).unwrap(), record);
+        let mut packed = vec![0u8; WalletClaimRecordAccount::LEN];
+        WalletClaimRecordAccount::pack(record, &mut packed).unwrap();
+        assert_eq!(packed, data);
+
+        let record = ReferralRecordAccount {
+            key: AccountKey::ReferralRecord,
+            version: REFERRAL_RECORD_VERSION,
+            is_initialized: true,
+            distributor: Pubkey::new_unique(),
+            referrer: Pubkey::new_unique(),
+            referred_claims: 2,
+            amount_earned: 20,
+        };
+        let data = record.try_to_vec().unwrap();
+        assert_eq!(ReferralRecordAccount::unpack(&data).unwrap(), record);
+        let mut packed = vec![0u8; ReferralRecordAccount::LEN];
+        ReferralRecordAccount::pack(record, &mut packed).unwrap();
+        assert_eq!(packed, data);
+    }
+
+    #[test]
+    fn test_claim_records_unpack_checks_key() {
+        // a freshly allocated record unpacks as uninitialized
+        let record = WalletClaimRecordAccount::unpack_unchecked(&[0u8; WALLET_CLAIM_RECORD_DATA_LENGTH]).unwrap();
+        assert!(!record.is_initialized);
+        let record = ReferralRecordAccount::unpack_unchecked(&[0u8; REFERRAL_RECORD_DATA_LENGTH]).unwrap();
+        assert!(!record.is_initialized);
+
+        // the same bytes under another account key are rejected
+        let mut data = [0u8; WALLET_CLAIM_RECORD_DATA_LENGTH];
+        data[..2].copy_from_slice(&[AccountKey::ProofOfReceipt as u8, WALLET_CLAIM_RECORD_VERSION]);
+        assert_eq!(WalletClaimRecordAccount::unpack_unchecked(&data).unwrap_err(), DistributorError::DataTypeMismatch.into());
 
-        Ok(receipt)
+        // and so are bools other than 0 or 1
+        let mut data = [0u8; REFERRAL_RECORD_DATA_LENGTH];
+        data[..3].copy_from_slice(&[AccountKey::ReferralRecord as u8, REFERRAL_RECORD_VERSION, 2]);
+        assert_eq!(ReferralRecordAccount::unpack_unchecked(&data).unwrap_err(), ProgramError::InvalidAccountData);
     }
 }
\ No newline at end of file
@@ -1,9 +1,10 @@
 use borsh::{BorshDeserialize};
 use solana_program::{
     borsh::try_from_slice_unchecked,
+    hash::hashv,
     program_error::ProgramError,
     system_instruction,
-    account_info::AccountInfo,
+    account_info::{next_account_info, AccountInfo},
     pubkey::Pubkey,
     entrypoint::ProgramResult,
     sysvar::{rent::Rent, Sysvar},
@@ -12,10 +13,18 @@ use solana_program::{
 };
 use std::convert::TryInto;
 use crate::{
-    error::DistributorError
+    error::DistributorError,
+    state::AccountKey,
+    state::MAX_SYMBOL_LENGTH
 };
 
 pub const PREFIX: &str = "distributor";
+pub const DISTRIBUTOR_STATE_PREFIX: &str = "distributor_state";
+pub const ATTESTATION_PREFIX: &str = "attestation";
+pub const WALLET_CLAIMS_PREFIX: &str = "wallet_claims";
+pub const REFERRAL_PREFIX: &str = "referral";
+pub const RECEIPT_PREFIX: &str = "claimed";
+pub const MAX_BPS: u16 = 10_000;
 
 pub fn try_from_slice_checked<T: BorshDeserialize>(
     data: &[u8],
@@ -31,6 +40,82 @@ pub fn try_from_slice_checked<T: BorshDeserialize>(
     Ok(result)
 }
 
+/// Finds the distributor state PDA (derived from "distributor_state", authority and distributor id)
+pub fn find_distributor_state_address(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    distributor_id: u64,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            DISTRIBUTOR_STATE_PREFIX.as_bytes(),
+            authority.as_ref(),
+            &distributor_id.to_le_bytes(),
+        ],
+        program_id,
+    )
+}
+
+/// Deserializes an account that starts with its AccountKey and layout version.
+/// `version_sizes[v - 1]` is the account size at version v, the last one being the current version.
+/// Fields are only ever appended, so an older version decodes with the newer fields zeroed until it is migrated.
+/// A freshly allocated account (Uninitialized, version 0) of the current size is accepted too,
+/// anything else is another account type or an unknown version.
+pub fn try_from_slice_versioned<T: BorshDeserialize>(
+    data: &[u8],
+    key: AccountKey,
+    version_sizes: &[usize],
+) -> Result<T, ProgramError> {
+    let current_size = *version_sizes.last().ok_or(DistributorError::DataTypeMismatch)?;
+    match (data.first(), data.get(1)) {
+        (Some(k), Some(0)) if *k == AccountKey::Uninitialized as u8 => try_from_slice_checked(data, current_size),
+        (Some(k), Some(version)) if *k == key as u8 && *version > 0 && *version as usize <= version_sizes.len() => {
+            let version_size = version_sizes[*version as usize - 1];
+            if version_size == current_size {
+                return try_from_slice_checked(data, current_size);
+            }
+            if data.len() != version_size {
+                return Err(DistributorError::DataTypeMismatch.into());
+            }
+            let mut padded = data.to_vec();
+            padded.resize(current_size, 0);
+            try_from_slice_checked(&padded, current_size)
+        },
+        _ => Err(DistributorError::DataTypeMismatch.into()),
+    }
+}
+
+/// Constraint on an account of an instruction, checked when the accounts are parsed
+pub enum AccountCheck {
+    Signer,
+    Writable,
+    // the account must be owned by the program, or fail with the error
+    Owner(Pubkey, DistributorError),
+    // the account must be the given account, or fail with the error
+    Key(Pubkey, DistributorError),
+}
+
+/// Takes the next account and checks its constraints, logging the name of the account that failed
+pub fn next_account_checked<'a, 'b>(
+    account_info_iter: &mut std::slice::Iter<'a, AccountInfo<'b>>,
+    name: &str,
+    checks: &[AccountCheck],
+) -> Result<&'a AccountInfo<'b>, ProgramError> {
+    let account_info = next_account_info(account_info_iter)?;
+    for check in checks {
+        let error = match check {
+            AccountCheck::Signer if !account_info.is_signer => DistributorError::IncorrectSigner,
+            AccountCheck::Writable if !account_info.is_writable => DistributorError::AccountNotWritable,
+            AccountCheck::Owner(owner, error) if account_info.owner != owner => error.clone(),
+            AccountCheck::Key(key, error) if account_info.key != key => error.clone(),
+            _ => continue,
+        };
+        msg!("Account {} ({}): {}", name, account_info.key, error);
+        return Err(error.into());
+    }
+    Ok(account_info)
+}
+
 /// Create account almost from scratch, lifted from
 /// https://github.com/solana-labs/solana-program-library/tree/master/associated-token-account/program/src/processor.rs#L51-L98
 #[inline(always)]
@@ -52,7 +137,7 @@ pub fn create_or_allocate_account_raw<'a>(
     if required_lamports > 0 {
         msg!("Transfer {} lamports to the new account", required_lamports);
         invoke(
-            &system_instruction::transfer(&payer_info.key, new_account_info.key, required_lamports),
+            &system_instruction::transfer(payer_info.key, new_account_info.key, required_lamports),
             &[
                 payer_info.clone(),
                 new_account_info.clone(),
@@ -67,26 +152,67 @@ pub fn create_or_allocate_account_raw<'a>(
     invoke_signed(
         &system_instruction::allocate(new_account_info.key, size.try_into().unwrap()),
         accounts,
-        &[&signer_seeds],
+        &[signer_seeds],
     )?;
 
     msg!("Assign the account to the owning program");
     invoke_signed(
         &system_instruction::assign(new_account_info.key, &program_id),
         accounts,
-        &[&signer_seeds],
+        &[signer_seeds],
     )?;
 
     Ok(())
 }
 
+/// Checks the symbol fits the metadata symbol field in bytes and has no control characters.
+/// A `0u8` in particular would be taken for padding when claims compare symbols.
+pub fn validate_symbol(s: &str) -> ProgramResult {
+    if s.len() > MAX_SYMBOL_LENGTH || s.chars().any(char::is_control) {
+        return Err(DistributorError::InvalidSymbol.into());
+    }
+    Ok(())
+}
+
+/// Pads the symbol to MAX_SYMBOL_LENGTH with `0u8`s, as the metadata program stores it.
+pub fn puffed_out_symbol(s: &str) -> Result<[u8; MAX_SYMBOL_LENGTH], ProgramError> {
+    validate_symbol(s)?;
+    let mut symbol = [0u8; MAX_SYMBOL_LENGTH];
+    symbol[..s.len()].copy_from_slice(s.as_bytes());
+    Ok(symbol)
+}
+
 /// Pads the string to the desired size with `0u8`s.
-/// NOTE: it is assumed that the string's size is never larger than the given size.
-pub fn puffed_out_string(s: &String, size: usize) -> String {
+/// Strings already longer than the size are returned unchanged.
+pub fn puffed_out_string(s: &str, size: usize) -> String {
     let mut array_of_zeroes = vec![];
-    let puff_amount = size - s.len();
+    let puff_amount = size.saturating_sub(s.len());
     while array_of_zeroes.len() < puff_amount {
         array_of_zeroes.push(0u8);
     }
-    s.clone() + std::str::from_utf8(&array_of_zeroes).unwrap()
+    s.to_owned() + std::str::from_utf8(&array_of_zeroes).unwrap()
+}
+
+/// Hashes an NFT trait (e.g. "Background", "Gold") as stored in attribute attestations and trait filters.
+/// The type is length prefixed so that ("a:b", "c") and ("a", "b:c") hash differently.
+pub fn hash_trait(trait_type: &str, value: &str) -> [u8; 32] {
+    let trait_type_len = (trait_type.len() as u32).to_le_bytes();
+    hashv(&[&trait_type_len, trait_type.as_bytes(), value.as_bytes()]).to_bytes()
+}
+
+/// Strips the `0u8` padding added to metadata strings.
+pub fn trim_null_padding(s: &str) -> &str {
+    s.trim_end_matches(char::from(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_trait_unambiguous() {
+        assert_ne!(hash_trait("a:b", "c"), hash_trait("a", "b:c"));
+        assert_ne!(hash_trait("ab", "c"), hash_trait("a", "bc"));
+        assert_eq!(hash_trait("Background", "Gold"), hash_trait("Background", "Gold"));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,730 @@
+// The program and spl-token run natively. Native program-test cannot resize accounts through CPI yet,
+// so the syscall stubs below stand in for the system program allocating the program's new accounts.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_nft_claim_tokens::{
+    client::{
+        find_distributor_authority, find_distributor_state_address, find_edition_address, find_metadata_address, find_receipt_address,
+        find_referral_record_address,
+    },
+    error::DistributorError,
+    instruction::{
+        claim_tokens, close_distributor, create_token_distributor, set_paused, ClaimTokenInstruction, ClaimTokensAccounts, ClaimTokensArgs,
+        CreateTokenDistributorAccounts, CreateTokenDistributorArgs, ReferralAccounts,
+    },
+    processor::process_instruction,
+    state::{AccountKey, DistributorAccount, EditionPolicy, ReferralRecordAccount, REFERRAL_RECORD_DATA_LENGTH, REFERRAL_RECORD_VERSION},
+    utils::puffed_out_string,
+};
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::ProgramResult,
+    instruction::{Instruction, InstructionError},
+    program_error::ProgramError,
+    program_option::COption,
+    program_pack::Pack,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+    system_instruction::{self, SystemInstruction},
+    system_program,
+    sysvar,
+};
+use solana_program_test::{processor, ProgramTest, ProgramTestBanksClientExt, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+    transport::TransportError,
+};
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+use std::sync::Once;
+use spl_token_metadata::{
+    error::MetadataError,
+    state::{Creator, Data, Key, MasterEditionV2, Metadata, MAX_MASTER_EDITION_LEN, MAX_METADATA_LEN, MAX_NAME_LENGTH, MAX_SYMBOL_LENGTH, MAX_URI_LENGTH},
+};
+
+const REWARD_AMOUNT_TOTAL: u64 = 1_000;
+const REWARD_AMOUNT_PER_NFT: u64 = 100;
+const COLLECTION_SYMBOL: &str = "SYMBOL";
+
+fn program_id() -> Pubkey {
+    Pubkey::new_from_array([7u8; 32])
+}
+
+struct NoSyscallStubs;
+
+impl SyscallStubs for NoSyscallStubs {}
+
+/// The program-test syscall stubs, except that allocating an account assigns it to the program first, which then
+/// grows its data itself: the program-test stubs refuse to resize an account on the way back from a CPI,
+/// but the runtime lets the owner of an account resize it
+struct NativeAllocate(Box<dyn SyscallStubs>);
+
+impl SyscallStubs for NativeAllocate {
+    fn sol_log(&self, message: &str) {
+        self.0.sol_log(message)
+    }
+
+    fn sol_log_compute_units(&self) {
+        self.0.sol_log_compute_units()
+    }
+
+    fn sol_invoke_signed(&self, instruction: &Instruction, account_infos: &[AccountInfo], signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+        if instruction.program_id == system_program::ID {
+            if let Ok(SystemInstruction::Allocate { space }) = bincode::deserialize(&instruction.data) {
+                let account = &instruction.accounts[0].pubkey;
+                let info = account_infos.iter().find(|info| info.key == account).ok_or(ProgramError::NotEnoughAccountKeys)?;
+                if !info.data_is_empty() {
+                    return Err(ProgramError::AccountAlreadyInitialized);
+                }
+                self.0.sol_invoke_signed(&system_instruction::assign(account, &program_id()), account_infos, signers_seeds)?;
+                // the account infos borrow their data, new space outlives the instruction
+                *info.data.borrow_mut() = Box::leak(vec![0u8; space as usize].into_boxed_slice());
+                return Ok(());
+            }
+        }
+        self.0.sol_invoke_signed(instruction, account_infos, signers_seeds)
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_clock_sysvar(var_addr)
+    }
+
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_epoch_schedule_sysvar(var_addr)
+    }
+
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_fees_sysvar(var_addr)
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.0.sol_get_rent_sysvar(var_addr)
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        self.0.sol_get_return_data()
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        self.0.sol_set_return_data(data)
+    }
+
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        self.0.sol_log_data(fields)
+    }
+}
+
+// program-test installs its stubs once, when the first test starts
+fn install_native_allocate() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let program_test_stubs = set_syscall_stubs(Box::new(NoSyscallStubs));
+        set_syscall_stubs(Box::new(NativeAllocate(program_test_stubs)));
+    });
+}
+
+/// A collection NFT held by the claimant, and the reward token accounts of the distributor and the claimant
+struct Fixture {
+    context: ProgramTestContext,
+    program_id: Pubkey,
+    claimant: Keypair,
+    creator: Pubkey,
+    nft_mint: Pubkey,
+    claimant_nft_account: Pubkey,
+    distributor_reward_account: Pubkey,
+    claimant_reward_account: Pubkey,
+}
+
+fn token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> Account {
+    let mut data = vec![0u8; TokenAccount::LEN];
+    TokenAccount {
+        mint,
+        owner,
+        amount,
+        state: AccountState::Initialized,
+        ..TokenAccount::default()
+    }.pack_into_slice(&mut data);
+    Account { lamports: 1_000_000_000, data, owner: spl_token::ID, executable: false, rent_epoch: 0 }
+}
+
+fn mint_account(mint_authority: Pubkey, supply: u64) -> Account {
+    let mut data = vec![0u8; Mint::LEN];
+    Mint {
+        mint_authority: COption::Some(mint_authority),
+        supply,
+        decimals: 0,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }.pack_into_slice(&mut data);
+    Account { lamports: 1_000_000_000, data, owner: spl_token::ID, executable: false, rent_epoch: 0 }
+}
+
+/// Metadata as the metadata program writes it, strings puffed out with `0u8`s
+fn metadata_account(mint: Pubkey, creator: Pubkey, symbol: &str) -> Account {
+    let mut data = vec![0u8; MAX_METADATA_LEN];
+    Metadata {
+        key: Key::MetadataV1,
+        update_authority: creator,
+        mint,
+        data: Data {
+            name: puffed_out_string("Collection #1", MAX_NAME_LENGTH),
+            symbol: puffed_out_string(symbol, MAX_SYMBOL_LENGTH),
+            uri: puffed_out_string("https://example.com/1.json", MAX_URI_LENGTH),
+            seller_fee_basis_points: 0,
+            creators: Some(vec![Creator { address: creator, verified: true, share: 100 }]),
+        },
+        primary_sale_happened: false,
+        is_mutable: true,
+        edition_nonce: None,
+    }.serialize(&mut &mut data[..]).unwrap();
+    Account { lamports: 1_000_000_000, data, owner: spl_token_metadata::ID, executable: false, rent_epoch: 0 }
+}
+
+fn master_edition_account() -> Account {
+    let mut data = vec![0u8; MAX_MASTER_EDITION_LEN];
+    MasterEditionV2 {
+        key: Key::MasterEditionV2,
+        supply: 0,
+        max_supply: Some(0),
+    }.serialize(&mut &mut data[..]).unwrap();
+    Account { lamports: 1_000_000_000, data, owner: spl_token_metadata::ID, executable: false, rent_epoch: 0 }
+}
+
+async fn setup() -> Fixture {
+    let program_id = program_id();
+    let mut program_test = ProgramTest::new("solana_nft_claim_tokens", program_id, processor!(process_instruction));
+    // the BPF build, which program-test prefers when BPF_OUT_DIR is set, runs against the spl-token program-test bundles
+    if std::env::var("BPF_OUT_DIR").is_err() {
+        program_test.add_program("spl_token", spl_token::ID, processor!(spl_token::processor::Processor::process));
+    }
+
+    // the claimant pays for the proof of receipt
+    let claimant = Keypair::new();
+    program_test.add_account(claimant.pubkey(), Account { lamports: 1_000_000_000, data: vec![], owner: system_program::ID, executable: false, rent_epoch: 0 });
+
+    // the collection NFT, with metadata and master edition written directly as the metadata program would
+    let creator = Pubkey::new_unique();
+    let nft_mint = Pubkey::new_unique();
+    let claimant_nft_account = Pubkey::new_unique();
+    program_test.add_account(nft_mint, mint_account(find_edition_address(&nft_mint).0, 1));
+    program_test.add_account(claimant_nft_account, token_account(nft_mint, claimant.pubkey(), 1));
+    program_test.add_account(find_metadata_address(&nft_mint).0, metadata_account(nft_mint, creator, COLLECTION_SYMBOL));
+    program_test.add_account(find_edition_address(&nft_mint).0, master_edition_account());
+
+    let mut context = program_test.start_with_context().await;
+    install_native_allocate();
+
+    // the reward tokens, owned by the authority (the payer) until the distributor takes them over
+    let reward_mint = Pubkey::new_unique();
+    let distributor_reward_account = Pubkey::new_unique();
+    let claimant_reward_account = Pubkey::new_unique();
+    let authority = context.payer.pubkey();
+    context.set_account(&reward_mint, &mint_account(authority, REWARD_AMOUNT_TOTAL).into());
+    context.set_account(&distributor_reward_account, &token_account(reward_mint, authority, REWARD_AMOUNT_TOTAL).into());
+    context.set_account(&claimant_reward_account, &token_account(reward_mint, claimant.pubkey(), 0).into());
+
+    Fixture {
+        context,
+        program_id,
+        claimant,
+        creator,
+        nft_mint,
+        claimant_nft_account,
+        distributor_reward_account,
+        claimant_reward_account,
+    }
+}
+
+/// The fixture NFT without its edition account
+async fn setup_without_edition() -> Fixture {
+    let mut fixture = setup().await;
+    let (nft_edition_account, _bump_seed) = find_edition_address(&fixture.nft_mint);
+    fixture.context.set_account(&nft_edition_account, &Account::default().into());
+    fixture
+}
+
+async fn now(fixture: &mut Fixture) -> Clock {
+    fixture.context.banks_client.get_sysvar::<Clock>().await.unwrap()
+}
+
+async fn create_distributor(fixture: &mut Fixture, collection_creator: Pubkey, collection_symbol: &str, start_ts: i64) -> Pubkey {
+    create_distributor_with_args(fixture, collection_creator, distributor_args(collection_symbol, start_ts)).await
+}
+
+async fn create_distributor_with_referral_bps(
+    fixture: &mut Fixture,
+    collection_creator: Pubkey,
+    collection_symbol: &str,
+    start_ts: i64,
+    referral_bps: u16,
+) -> Pubkey {
+    let args = CreateTokenDistributorArgs { referral_bps, ..distributor_args(collection_symbol, start_ts) };
+    create_distributor_with_args(fixture, collection_creator, args).await
+}
+
+/// Distributor 0 of the fixture reward tokens, open to originals and prints alike
+fn distributor_args(collection_symbol: &str, start_ts: i64) -> CreateTokenDistributorArgs {
+    CreateTokenDistributorArgs {
+        distributor_id: 0,
+        reward_amount_total: REWARD_AMOUNT_TOTAL,
+        reward_amount_per_nft: REWARD_AMOUNT_PER_NFT,
+        start_ts,
+        collection_symbol: collection_symbol.to_string(),
+        reward_amount_per_print: REWARD_AMOUNT_PER_NFT,
+        edition_policy: EditionPolicy::Both,
+        trait_filter: None,
+        name_prefix: None,
+        uri_prefix: None,
+        max_claims_per_wallet: None,
+        claim_fee_lamports: 0,
+        fee_treasury: Pubkey::default(),
+        referral_bps: 0,
+        merkle_root: None,
+    }
+}
+
+async fn create_distributor_with_args(fixture: &mut Fixture, collection_creator: Pubkey, args: CreateTokenDistributorArgs) -> Pubkey {
+    let authority = fixture.context.payer.pubkey();
+    let (distributor_state_account, _bump_seed) = find_distributor_state_address(&fixture.program_id, &authority, args.distributor_id);
+    let transaction = Transaction::new_signed_with_payer(
+        &[create_token_distributor(
+            fixture.program_id,
+            CreateTokenDistributorAccounts {
+                authority_account: authority,
+                distributor_state_account,
+                reward_token_account: fixture.distributor_reward_account,
+                collection_creator_account: collection_creator,
+                rent_sysvar: sysvar::rent::id(),
+                token_program_account: spl_token::ID,
+                system_program_account: system_program::id(),
+            },
+            args,
+        )],
+        Some(&authority),
+        &[&fixture.context.payer],
+        fixture.context.last_blockhash,
+    );
+    fixture.context.banks_client.process_transaction(transaction).await.unwrap();
+    distributor_state_account
+}
+
+async fn claim(fixture: &mut Fixture, distributor_state_account: Pubkey) -> Result<(), TransportError> {
+    claim_with_referral(fixture, distributor_state_account, None).await
+}
+
+/// A claim of the fixture NFT naming `referral`, a referrer and their reward token account
+fn claim_instruction(fixture: &Fixture, distributor_state_account: Pubkey, referral: Option<(Pubkey, Pubkey)>) -> Instruction {
+    let (pda_account, _bump_seed) = find_distributor_authority(&fixture.program_id, &distributor_state_account);
+    let (proof_of_receipt_account, _bump_seed) = find_receipt_address(&fixture.program_id, &fixture.nft_mint, &distributor_state_account);
+    let (nft_metadata_account, metadata_bump) = find_metadata_address(&fixture.nft_mint);
+    let (nft_edition_account, edition_bump) = find_edition_address(&fixture.nft_mint);
+    let referral_accounts = referral.map(|(referrer, referrer_reward_account)| ReferralAccounts {
+        referrer_main_account: referrer,
+        referrer_reward_account,
+        referral_record_account: find_referral_record_address(&fixture.program_id, &distributor_state_account, &referrer).0,
+    });
+    claim_tokens(
+        fixture.program_id,
+        ClaimTokensAccounts {
+            claimant_main_account: fixture.claimant.pubkey(),
+            distributor_state_account,
+            distributor_reward_account: fixture.distributor_reward_account,
+            claimant_reward_account: fixture.claimant_reward_account,
+            pda_account,
+            claimant_nft_account: fixture.claimant_nft_account,
+            nft_metadata_account,
+            nft_edition_account,
+            attribute_attestation_account: Pubkey::new_unique(),
+            proof_of_receipt_account,
+            wallet_claim_record_account: Pubkey::new_unique(),
+            fee_treasury_account: Pubkey::new_unique(),
+            clock_sysvar: sysvar::clock::id(),
+            rent_sysvar: sysvar::rent::id(),
+            token_program_account: spl_token::ID,
+            system_program_account: system_program::id(),
+            referral_accounts,
+        },
+        ClaimTokensArgs {
+            metadata_bump,
+            edition_bump,
+            parent_edition_bump: 0,
+            allowlist_amount: 0,
+            merkle_proof: vec![],
+        },
+    )
+}
+
+/// Claims naming `referral`, a referrer and their reward token account
+async fn claim_with_referral(fixture: &mut Fixture, distributor_state_account: Pubkey, referral: Option<(Pubkey, Pubkey)>) -> Result<(), TransportError> {
+    let instruction = claim_instruction(fixture, distributor_state_account, referral);
+
+    // a new blockhash so a retried claim is a new transaction
+    fixture.context.last_blockhash = fixture.context.banks_client
+        .get_new_latest_blockhash(&fixture.context.last_blockhash)
+        .await
+        .unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&fixture.context.payer.pubkey()),
+        &[&fixture.context.payer, &fixture.claimant],
+        fixture.context.last_blockhash,
+    );
+    fixture.context.banks_client.process_transaction(transaction).await
+}
+
+async fn process_as(fixture: &mut Fixture, instruction: Instruction, signer: &Keypair) -> Result<(), TransportError> {
+    fixture.context.last_blockhash = fixture.context.banks_client
+        .get_new_latest_blockhash(&fixture.context.last_blockhash)
+        .await
+        .unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&signer.pubkey()),
+        &[signer],
+        fixture.context.last_blockhash,
+    );
+    fixture.context.banks_client.process_transaction(transaction).await
+}
+
+async fn set_paused_as_authority(fixture: &mut Fixture, distributor_state_account: Pubkey, paused: bool) -> Result<(), TransportError> {
+    let instruction = set_paused(fixture.program_id, fixture.context.payer.pubkey(), distributor_state_account, paused);
+    let authority = Keypair::from_bytes(&fixture.context.payer.to_bytes()).unwrap();
+    process_as(fixture, instruction, &authority).await
+}
+
+async fn token_amount(context: &mut ProgramTestContext, token_account: Pubkey) -> u64 {
+    let account = context.banks_client.get_account(token_account).await.unwrap().unwrap();
+    TokenAccount::unpack(&account.data).unwrap().amount
+}
+
+fn assert_custom_error(result: Result<(), TransportError>, code: u32) {
+    assert_eq!(
+        result.unwrap_err().unwrap(),
+        TransactionError::InstructionError(0, InstructionError::Custom(code))
+    );
+}
+
+#[tokio::test]
+async fn test_claim_tokens() {
+    let mut fixture = setup().await;
+    let start_ts = now(&mut fixture).await.unix_timestamp;
+    let creator = fixture.creator;
+    let distributor_state_account = create_distributor(&mut fixture, creator, COLLECTION_SYMBOL, start_ts).await;
+
+    claim(&mut fixture, distributor_state_account).await.unwrap();
+
+    assert_eq!(token_amount(&mut fixture.context, fixture.claimant_reward_account).await, REWARD_AMOUNT_PER_NFT);
+    assert_eq!(token_amount(&mut fixture.context, fixture.distributor_reward_account).await, REWARD_AMOUNT_TOTAL - REWARD_AMOUNT_PER_NFT);
+    let distributor_state = fixture.context.banks_client.get_account(distributor_state_account).await.unwrap().unwrap();
+    assert_eq!(DistributorAccount::from_slice(&distributor_state.data).unwrap().amount_claimed, REWARD_AMOUNT_PER_NFT);
+}
+
+// the claim has to fit the default 200k compute unit budget with room to spare for the caller's own instructions
+#[cfg(feature = "test-bpf")]
+const CLAIM_COMPUTE_UNITS: u64 = 100_000;
+
+// compute units are only metered for BPF programs, natively the claim consumes none, so this runs under cargo test-bpf
+#[cfg(feature = "test-bpf")]
+#[tokio::test]
+async fn test_claim_compute_units() {
+    let mut fixture = setup().await;
+    let start_ts = now(&mut fixture).await.unix_timestamp;
+    let creator = fixture.creator;
+    let distributor_state_account = create_distributor(&mut fixture, creator, COLLECTION_SYMBOL, start_ts).await;
+
+    // preflight only reports the units consumed for a failing transaction, so a transfer the claimant
+    // cannot afford follows the claim; the system program consumes no units of its own
+    let claim = claim_instruction(&fixture, distributor_state_account, None);
+    let unaffordable = system_instruction::transfer(&fixture.claimant.pubkey(), &Pubkey::new_unique(), u64::MAX);
+    let transaction = Transaction::new_signed_with_payer(
+        &[claim, unaffordable],
+        Some(&fixture.context.payer.pubkey()),
+        &[&fixture.context.payer, &fixture.claimant],
+        fixture.context.last_blockhash,
+    );
+    let units_consumed = match fixture.context.banks_client.process_transaction_with_preflight(transaction).await {
+        Err(solana_program_test::BanksClientError::SimulationError { err, units_consumed, .. }) => {
+            // the claim went through, only the transfer failed
+            assert!(matches!(err, TransactionError::InstructionError(1, _)), "{:?}", err);
+            units_consumed
+        },
+        result => panic!("expected a failed simulation, got {:?}", result),
+    };
+    println!("claim consumed {} compute units", units_consumed);
+    assert!(units_consumed > 0);
+    assert!(units_consumed <= CLAIM_COMPUTE_UNITS, "claim consumed {} compute units, over {}", units_consumed, CLAIM_COMPUTE_UNITS);
+}
+
+#[tokio::test]
+async fn test_double_claim_rejected() {
+    let mut fixture = setup().await;
+    let start_ts = now(&mut fixture).await.unix_timestamp;
+    let creator = fixture.creator;
+    let distributor_state_account = create_distributor(&mut fixture, creator, COLLECTION_SYMBOL, start_ts).await;
+
+    claim(&mut fixture, distributor_state_account).await.unwrap();
+    assert_custom_error(
+        claim(&mut fixture, distributor_state_account).await,
+        DistributorError::TokensAlreadyClaimed as u32,
+    );
+    assert_eq!(token_amount(&mut fixture.context, fixture.claimant_reward_account).await, REWARD_AMOUNT_PER_NFT);
+}
+
+#[tokio::test]
+async fn test_claim_wrong_creator_rejected() {
+    let mut fixture = setup().await;
+    let start_ts = now(&mut fixture).await.unix_timestamp;
+    let distributor_state_account = create_distributor(&mut fixture, Pubkey::new_unique(), COLLECTION_SYMBOL, start_ts).await;
+
+    assert_custom_error(
+        claim(&mut fixture, distributor_state_account).await,
+        MetadataError::CreatorNotFound as u32,
+    );
+}
+
+#[tokio::test]
+async fn test_claim_wrong_symbol_rejected() {
+    let mut fixture = setup().await;
+    let start_ts = now(&mut fixture).await.unix_timestamp;
+    let creator = fixture.creator;
+    let distributor_state_account = create_distributor(&mut fixture, creator, "OTHER", start_ts).await;
+
+    assert_custom_error(
+        claim(&mut fixture, distributor_state_account).await,
+        DistributorError::IncorrectSymbol as u32,
+    );
+}
+
+// NFTs minted without an edition account could claim before edition policies, distributors migrated from then are Both
+#[tokio::test]
+async fn test_claim_without_edition_account() {
+    // the other policies need the edition to tell originals from prints
+    let mut fixture = setup_without_edition().await;
+    let start_ts = now(&mut fixture).await.unix_timestamp;
+    let creator = fixture.creator;
+    let originals_only = CreateTokenDistributorArgs { edition_policy: EditionPolicy::OriginalsOnly, ..distributor_args(COLLECTION_SYMBOL, start_ts) };
+    let distributor_state_account = create_distributor_with_args(&mut fixture, creator, originals_only).await;
+    assert_custom_error(
+        claim(&mut fixture, distributor_state_account).await,
+        DistributorError::InvalidEditionAccount as u32,
+    );
+
+    let mut fixture = setup_without_edition().await;
+    let start_ts = now(&mut fixture).await.unix_timestamp;
+    let creator = fixture.creator;
+    let distributor_state_account = create_distributor(&mut fixture, creator, COLLECTION_SYMBOL, start_ts).await;
+
+    // an empty account at another bump of the edition seeds is not taken for a missing edition
+    let (_nft_edition_account, edition_bump) = find_edition_address(&fixture.nft_mint);
+    let (non_canonical_edition_account, non_canonical_bump) = (0..edition_bump).rev().find_map(|bump| {
+        Pubkey::create_program_address(
+            &[b"metadata", spl_token_metadata::ID.as_ref(), fixture.nft_mint.as_ref(), b"edition", &[bump]],
+            &spl_token_metadata::ID,
+        ).ok().map(|address| (address, bump))
+    }).unwrap();
+    let mut instruction = claim_instruction(&fixture, distributor_state_account, None);
+    let mut args = match ClaimTokenInstruction::try_from_slice(&instruction.data).unwrap() {
+        ClaimTokenInstruction::ClaimTokens(args) => args,
+        _ => unreachable!(),
+    };
+    args.edition_bump = non_canonical_bump;
+    instruction.accounts[7].pubkey = non_canonical_edition_account;
+    instruction.data = ClaimTokenInstruction::ClaimTokens(args).try_to_vec().unwrap();
+    let claimant = Keypair::from_bytes(&fixture.claimant.to_bytes()).unwrap();
+    assert_custom_error(process_as(&mut fixture, instruction, &claimant).await, DistributorError::InvalidEditionAccount as u32);
+
+    // under Both it claims as an original
+    claim(&mut fixture, distributor_state_account).await.unwrap();
+    assert_eq!(token_amount(&mut fixture.context, fixture.claimant_reward_account).await, REWARD_AMOUNT_PER_NFT);
+}
+
+#[tokio::test]
+async fn test_claim_before_start_ts_rejected() {
+    let mut fixture = setup().await;
+    let clock = now(&mut fixture).await;
+    let start_ts = clock.unix_timestamp + 24 * 60 * 60;
+    let creator = fixture.creator;
+    let distributor_state_account = create_distributor(&mut fixture, creator, COLLECTION_SYMBOL, start_ts).await;
+
+    assert_custom_error(
+        claim(&mut fixture, distributor_state_account).await,
+        DistributorError::DistributionNotStarted as u32,
+    );
+
+    // warp the clock to start_ts
+    fixture.context.set_sysvar(&Clock { unix_timestamp: start_ts, ..clock });
+    claim(&mut fixture, distributor_state_account).await.unwrap();
+    assert_eq!(token_amount(&mut fixture.context, fixture.claimant_reward_account).await, REWARD_AMOUNT_PER_NFT);
+}
+
+#[tokio::test]
+async fn test_claim_while_paused_rejected() {
+    let mut fixture = setup().await;
+    let start_ts = now(&mut fixture).await.unix_timestamp;
+    let creator = fixture.creator;
+    let distributor_state_account = create_distributor(&mut fixture, creator, COLLECTION_SYMBOL, start_ts).await;
+
+    set_paused_as_authority(&mut fixture, distributor_state_account, true).await.unwrap();
+    assert_custom_error(
+        claim(&mut fixture, distributor_state_account).await,
+        DistributorError::DistributorPaused as u32,
+    );
+
+    // only the authority can resume
+    let claimant = Keypair::from_bytes(&fixture.claimant.to_bytes()).unwrap();
+    let instruction = set_paused(fixture.program_id, claimant.pubkey(), distributor_state_account, false);
+    assert_custom_error(
+        process_as(&mut fixture, instruction, &claimant).await,
+        DistributorError::UnauthorizedAccount as u32,
+    );
+
+    set_paused_as_authority(&mut fixture, distributor_state_account, false).await.unwrap();
+    claim(&mut fixture, distributor_state_account).await.unwrap();
+    assert_eq!(token_amount(&mut fixture.context, fixture.claimant_reward_account).await, REWARD_AMOUNT_PER_NFT);
+}
+
+#[tokio::test]
+async fn test_close_distributor() {
+    let mut fixture = setup().await;
+    let start_ts = now(&mut fixture).await.unix_timestamp;
+    let creator = fixture.creator;
+    let distributor_state_account = create_distributor(&mut fixture, creator, COLLECTION_SYMBOL, start_ts).await;
+    claim(&mut fixture, distributor_state_account).await.unwrap();
+
+    let authority = Keypair::from_bytes(&fixture.context.payer.to_bytes()).unwrap();
+    let authority_reward_account = Pubkey::new_unique();
+    let reward_mint = TokenAccount::unpack(&fixture.context.banks_client.get_account(fixture.distributor_reward_account).await.unwrap().unwrap().data).unwrap().mint;
+    fixture.context.set_account(&authority_reward_account, &token_account(reward_mint, authority.pubkey(), 0).into());
+    let (pda_account, _bump_seed) = find_distributor_authority(&fixture.program_id, &distributor_state_account);
+    let (program_id, distributor_reward_account) = (fixture.program_id, fixture.distributor_reward_account);
+    let close_instruction = |authority: Pubkey| close_distributor(
+        program_id,
+        authority,
+        distributor_state_account,
+        distributor_reward_account,
+        authority_reward_account,
+        pda_account,
+    );
+
+    // only the authority can close
+    let claimant = Keypair::from_bytes(&fixture.claimant.to_bytes()).unwrap();
+    let instruction = close_instruction(claimant.pubkey());
+    assert_custom_error(
+        process_as(&mut fixture, instruction, &claimant).await,
+        DistributorError::UnauthorizedAccount as u32,
+    );
+
+    let distributor_lamports = fixture.context.banks_client.get_balance(distributor_state_account).await.unwrap();
+    let vault_lamports = fixture.context.banks_client.get_balance(fixture.distributor_reward_account).await.unwrap();
+    let authority_lamports = fixture.context.banks_client.get_balance(authority.pubkey()).await.unwrap();
+    // the claimant pays the fee, so the authority balance only changes by the rent it gets back
+    let transaction = Transaction::new_signed_with_payer(
+        &[close_instruction(authority.pubkey())],
+        Some(&claimant.pubkey()),
+        &[&claimant, &authority],
+        fixture.context.last_blockhash,
+    );
+    fixture.context.banks_client.process_transaction(transaction).await.unwrap();
+
+    // the tokens left go to the authority, the rent of both accounts too
+    assert_eq!(token_amount(&mut fixture.context, authority_reward_account).await, REWARD_AMOUNT_TOTAL - REWARD_AMOUNT_PER_NFT);
+    assert!(fixture.context.banks_client.get_account(fixture.distributor_reward_account).await.unwrap().is_none());
+    assert!(fixture.context.banks_client.get_account(distributor_state_account).await.unwrap().is_none());
+    assert_eq!(
+        fixture.context.banks_client.get_balance(authority.pubkey()).await.unwrap(),
+        authority_lamports + distributor_lamports + vault_lamports
+    );
+}
+
+/// A referrer with a token account for the reward mint
+async fn referrer(fixture: &mut Fixture) -> (Pubkey, Pubkey) {
+    let referrer = Pubkey::new_unique();
+    let referrer_reward_account = Pubkey::new_unique();
+    let reward_mint = TokenAccount::unpack(&fixture.context.banks_client.get_account(fixture.distributor_reward_account).await.unwrap().unwrap().data).unwrap().mint;
+    fixture.context.set_account(&referrer_reward_account, &token_account(reward_mint, referrer, 0).into());
+    (referrer, referrer_reward_account)
+}
+
+async fn referral_record(fixture: &mut Fixture, distributor_state_account: Pubkey, referrer: Pubkey) -> ReferralRecordAccount {
+    let (referral_record_account, _bump_seed) = find_referral_record_address(&fixture.program_id, &distributor_state_account, &referrer);
+    let account = fixture.context.banks_client.get_account(referral_record_account).await.unwrap().unwrap();
+    ReferralRecordAccount::unpack(&account.data).unwrap()
+}
+
+#[tokio::test]
+async fn test_claim_pays_referral_bonus() {
+    let mut fixture = setup().await;
+    let start_ts = now(&mut fixture).await.unix_timestamp;
+    let creator = fixture.creator;
+    // 12.5% of the claimed amount, rounded down
+    let distributor_state_account = create_distributor_with_referral_bps(&mut fixture, creator, COLLECTION_SYMBOL, start_ts, 1_250).await;
+    let (referrer, referrer_reward_account) = referrer(&mut fixture).await;
+
+    claim_with_referral(&mut fixture, distributor_state_account, Some((referrer, referrer_reward_account))).await.unwrap();
+
+    let bonus = REWARD_AMOUNT_PER_NFT * 1_250 / 10_000;
+    assert_eq!(bonus, 12);
+    assert_eq!(token_amount(&mut fixture.context, fixture.claimant_reward_account).await, REWARD_AMOUNT_PER_NFT);
+    assert_eq!(token_amount(&mut fixture.context, referrer_reward_account).await, bonus);
+    assert_eq!(token_amount(&mut fixture.context, fixture.distributor_reward_account).await, REWARD_AMOUNT_TOTAL - REWARD_AMOUNT_PER_NFT - bonus);
+    // the bonus counts against reward_amount_total
+    let distributor_state = fixture.context.banks_client.get_account(distributor_state_account).await.unwrap().unwrap();
+    assert_eq!(DistributorAccount::from_slice(&distributor_state.data).unwrap().amount_claimed, REWARD_AMOUNT_PER_NFT + bonus);
+    let record = referral_record(&mut fixture, distributor_state_account, referrer).await;
+    assert_eq!((record.distributor, record.referrer, record.referred_claims, record.amount_earned), (distributor_state_account, referrer, 1, bonus));
+}
+
+#[tokio::test]
+async fn test_self_referral_rejected() {
+    let mut fixture = setup().await;
+    let start_ts = now(&mut fixture).await.unix_timestamp;
+    let creator = fixture.creator;
+    let distributor_state_account = create_distributor_with_referral_bps(&mut fixture, creator, COLLECTION_SYMBOL, start_ts, 500).await;
+
+    let claimant = fixture.claimant.pubkey();
+    let claimant_reward_account = fixture.claimant_reward_account;
+    assert_custom_error(
+        claim_with_referral(&mut fixture, distributor_state_account, Some((claimant, claimant_reward_account))).await,
+        DistributorError::SelfReferral as u32,
+    );
+    assert_eq!(token_amount(&mut fixture.context, fixture.claimant_reward_account).await, 0);
+}
+
+#[tokio::test]
+async fn test_referral_record_overflow_rejected() {
+    // a record at the limit of its referred claims, then one at the limit of its amount earned
+    for (referred_claims, amount_earned, error) in [
+        (u64::MAX, 0, DistributorError::ClaimCountOverflow),
+        (1, u64::MAX, DistributorError::AmountOverflow),
+    ] {
+        let mut fixture = setup().await;
+        let start_ts = now(&mut fixture).await.unix_timestamp;
+        let creator = fixture.creator;
+        let distributor_state_account = create_distributor_with_referral_bps(&mut fixture, creator, COLLECTION_SYMBOL, start_ts, 500).await;
+        let (referrer, referrer_reward_account) = referrer(&mut fixture).await;
+        let (referral_record_account, _bump_seed) = find_referral_record_address(&fixture.program_id, &distributor_state_account, &referrer);
+        let mut data = vec![0u8; REFERRAL_RECORD_DATA_LENGTH];
+        ReferralRecordAccount {
+            key: AccountKey::ReferralRecord,
+            version: REFERRAL_RECORD_VERSION,
+            is_initialized: true,
+            distributor: distributor_state_account,
+            referrer,
+            referred_claims,
+            amount_earned,
+        }.pack_into_slice(&mut data);
+        let program_id = fixture.program_id;
+        fixture.context.set_account(
+            &referral_record_account,
+            &Account { lamports: 1_000_000_000, data, owner: program_id, executable: false, rent_epoch: 0 }.into(),
+        );
+
+        assert_custom_error(
+            claim_with_referral(&mut fixture, distributor_state_account, Some((referrer, referrer_reward_account))).await,
+            error as u32,
+        );
+        assert_eq!(token_amount(&mut fixture.context, fixture.claimant_reward_account).await, 0);
+    }
+}
@@ -0,0 +1,792 @@
+// Property-based fuzzing of processor::process_instruction in a simulated runtime.
+// Well-formed, mutated and random instructions run over a world of collection NFTs, claimants and a funded
+// distributor. After every instruction the harness checks that the program did not panic, that tokens only
+// left a vault on a successful claim and that amount_claimed <= reward_amount_total.
+// Migration reallocs accounts in place, which needs the BPF account layout, so it is left to the test-bpf suite.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use proptest::{collection::vec, prelude::*};
+use solana_nft_claim_tokens::{
+    client::{
+        self, find_distributor_authority, find_distributor_state_address, find_edition_address, find_metadata_address,
+        find_receipt_address, find_wallet_claim_record_address,
+    },
+    instruction::{ClaimTokenInstruction, CreateTokenDistributorArgs},
+    merkle::{hash_leaf, hash_nodes, verify_proof},
+    processor::process_instruction,
+    state::{AccountKey, DistributorAccount, EditionPolicy},
+    utils::{hash_trait, puffed_out_string, RECEIPT_PREFIX, WALLET_CLAIMS_PREFIX},
+};
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    program_option::COption,
+    program_pack::Pack,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction::{SystemInstruction, MAX_PERMITTED_DATA_LENGTH},
+    system_program,
+    sysvar,
+};
+use spl_token::state::{Account as TokenAccount, AccountState, Mint};
+use spl_token_metadata::state::{
+    Creator, Data, Edition, Key, MasterEditionV2, Metadata, MAX_EDITION_LEN, MAX_MASTER_EDITION_LEN, MAX_METADATA_LEN,
+    MAX_NAME_LENGTH, MAX_SYMBOL_LENGTH, MAX_URI_LENGTH,
+};
+use std::{collections::BTreeMap, sync::Once};
+
+const COLLECTION_SYMBOL: &str = "SYMBOL";
+const CLAIMANTS: usize = 3;
+const LAMPORTS: u64 = 1_000_000_000_000;
+const NOW: i64 = 1_700_000_000;
+
+fn program_id() -> Pubkey {
+    Pubkey::new_from_array([7u8; 32])
+}
+
+/// Cross-program invocations of the simulated runtime: the program signs for its own PDAs only,
+/// spl-token runs natively and the system instructions the program uses are applied to the account infos
+struct Runtime;
+
+impl SyscallStubs for Runtime {
+    fn sol_log(&self, _message: &str) {}
+
+    fn sol_log_data(&self, _fields: &[&[u8]]) {}
+
+    fn sol_invoke_signed(&self, instruction: &Instruction, account_infos: &[AccountInfo], signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+        let pdas = signers_seeds
+            .iter()
+            .map(|seeds| Pubkey::create_program_address(seeds, &program_id()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| ProgramError::InvalidSeeds)?;
+        if !account_infos.iter().any(|info| *info.key == instruction.program_id) {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+        let mut infos = vec![];
+        for meta in &instruction.accounts {
+            let info = account_infos.iter().find(|info| *info.key == meta.pubkey).ok_or(ProgramError::NotEnoughAccountKeys)?;
+            if meta.is_signer && !info.is_signer && !pdas.contains(&meta.pubkey) {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            if meta.is_writable && !info.is_writable {
+                return Err(ProgramError::InvalidArgument);
+            }
+            // accounts listed more than once are signers if any of their metas is
+            let mut info = info.clone();
+            info.is_signer = instruction.accounts.iter().any(|other| other.pubkey == meta.pubkey && other.is_signer);
+            infos.push(info);
+        }
+        if instruction.program_id == spl_token::ID {
+            spl_token::processor::Processor::process(&instruction.program_id, &infos, &instruction.data)
+        } else if instruction.program_id == system_program::ID {
+            process_system_instruction(&infos, &instruction.data)
+        } else {
+            Err(ProgramError::IncorrectProgramId)
+        }
+    }
+}
+
+// the account a system instruction debits, allocates or assigns must be a signing system account
+fn check_system_account(info: &AccountInfo) -> ProgramResult {
+    if !info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if *info.owner != system_program::ID {
+        return Err(ProgramError::InvalidArgument);
+    }
+    Ok(())
+}
+
+fn transfer_lamports(from: &AccountInfo, to: &AccountInfo, lamports: u64) -> ProgramResult {
+    check_system_account(from)?;
+    if !from.data_is_empty() {
+        return Err(ProgramError::InvalidArgument);
+    }
+    if from.lamports() < lamports {
+        return Err(ProgramError::InsufficientFunds);
+    }
+    **from.lamports.borrow_mut() -= lamports;
+    **to.lamports.borrow_mut() += lamports;
+    Ok(())
+}
+
+fn allocate(info: &AccountInfo, space: u64) -> ProgramResult {
+    check_system_account(info)?;
+    if !info.data_is_empty() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    if space > MAX_PERMITTED_DATA_LENGTH {
+        return Err(ProgramError::InvalidArgument);
+    }
+    // the account infos borrow their data, new space outlives the instruction
+    *info.data.borrow_mut() = Box::leak(vec![0u8; space as usize].into_boxed_slice());
+    Ok(())
+}
+
+fn process_system_instruction(infos: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let instruction: SystemInstruction = bincode::deserialize(data).map_err(|_| ProgramError::InvalidInstructionData)?;
+    match (instruction, infos) {
+        (SystemInstruction::Transfer { lamports }, [from, to, ..]) => transfer_lamports(from, to, lamports),
+        (SystemInstruction::Allocate { space }, [account, ..]) => allocate(account, space),
+        (SystemInstruction::Assign { owner }, [account, ..]) => {
+            check_system_account(account)?;
+            account.assign(&owner);
+            Ok(())
+        },
+        (SystemInstruction::CreateAccount { lamports, space, owner }, [from, to, ..]) => {
+            check_system_account(to)?;
+            if to.lamports() > 0 {
+                return Err(ProgramError::AccountAlreadyInitialized);
+            }
+            transfer_lamports(from, to, lamports)?;
+            allocate(to, space)?;
+            to.assign(&owner);
+            Ok(())
+        },
+        _ => Err(ProgramError::InvalidInstructionData),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Account {
+    lamports: u64,
+    data: Vec<u8>,
+    owner: Pubkey,
+    executable: bool,
+}
+
+impl Account {
+    fn new(data: Vec<u8>, owner: Pubkey) -> Self {
+        Account { lamports: LAMPORTS, data, owner, executable: false }
+    }
+}
+
+fn token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> Account {
+    let mut data = vec![0u8; TokenAccount::LEN];
+    TokenAccount { mint, owner, amount, state: AccountState::Initialized, ..TokenAccount::default() }.pack_into_slice(&mut data);
+    Account::new(data, spl_token::ID)
+}
+
+fn mint_account(supply: u64) -> Account {
+    let mut data = vec![0u8; Mint::LEN];
+    Mint { mint_authority: COption::None, supply, decimals: 0, is_initialized: true, freeze_authority: COption::None }.pack_into_slice(&mut data);
+    Account::new(data, spl_token::ID)
+}
+
+fn metadata_account(mint: Pubkey, creator: Pubkey, symbol: &str) -> Account {
+    let mut data = vec![0u8; MAX_METADATA_LEN];
+    Metadata {
+        key: Key::MetadataV1,
+        update_authority: creator,
+        mint,
+        data: Data {
+            name: puffed_out_string("Collection #1", MAX_NAME_LENGTH),
+            symbol: puffed_out_string(symbol, MAX_SYMBOL_LENGTH),
+            uri: puffed_out_string("https://example.com/1.json", MAX_URI_LENGTH),
+            seller_fee_basis_points: 0,
+            creators: Some(vec![Creator { address: creator, verified: true, share: 100 }]),
+        },
+        primary_sale_happened: false,
+        is_mutable: true,
+        edition_nonce: None,
+    }.serialize(&mut &mut data[..]).unwrap();
+    Account::new(data, spl_token_metadata::ID)
+}
+
+fn master_edition_account() -> Account {
+    let mut data = vec![0u8; MAX_MASTER_EDITION_LEN];
+    MasterEditionV2 { key: Key::MasterEditionV2, supply: 0, max_supply: Some(0) }.serialize(&mut &mut data[..]).unwrap();
+    Account::new(data, spl_token_metadata::ID)
+}
+
+fn print_edition_account(master_mint: &Pubkey) -> Account {
+    let mut data = vec![0u8; MAX_EDITION_LEN];
+    Edition { key: Key::EditionV1, parent: find_edition_address(master_mint).0, edition: 1 }.serialize(&mut &mut data[..]).unwrap();
+    Account::new(data, spl_token_metadata::ID)
+}
+
+fn clock_account(unix_timestamp: i64) -> Account {
+    let clock = Clock { unix_timestamp, ..Clock::default() };
+    Account::new(bincode::serialize(&clock).unwrap(), sysvar::ID)
+}
+
+struct Nft {
+    mint: Pubkey,
+    owner: usize,
+    token_account: Pubkey,
+    // holder accounts a claim must not be paid out from
+    empty_account: Pubkey,
+    foreign_account: Pubkey,
+    wrong_mint_account: Pubkey,
+}
+
+impl Nft {
+    fn holder_account(&self, holder: Holder) -> Pubkey {
+        match holder {
+            Holder::Held => self.token_account,
+            Holder::Empty => self.empty_account,
+            Holder::Foreign => self.foreign_account,
+            Holder::WrongMint => self.wrong_mint_account,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Holder {
+    Held,
+    Empty,
+    Foreign,
+    WrongMint,
+}
+
+/// Distributor settings, fuzzed to reach the reward arithmetic edge cases
+#[derive(Debug, Clone)]
+struct Params {
+    reward_amount_total: u64,
+    reward_amount_per_nft: u64,
+    reward_amount_per_print: u64,
+    vault_surplus: u64,
+    start_ts: i64,
+    edition_policy: EditionPolicy,
+    trait_filter: bool,
+    max_claims_per_wallet: Option<u64>,
+    claim_fee_lamports: u64,
+    referral_bps: u16,
+    allowlist: bool,
+}
+
+struct World {
+    accounts: BTreeMap<Pubkey, Account>,
+    claimants: Vec<Pubkey>,
+    // reward token accounts of the claimants, funded so claimants can create distributors of their own
+    reward_accounts: Vec<Pubkey>,
+    nfts: Vec<Nft>,
+    creator: Pubkey,
+    distributor_state: Pubkey,
+    // amount and merkle proof of the allowlisted mints
+    allowlist: BTreeMap<Pubkey, (u64, Vec<[u8; 32]>)>,
+}
+
+/// Root of the allowlist and the proof of each entry, the tree built as the merkle command builds it
+fn allowlist_tree(entries: &[(Pubkey, u64)]) -> ([u8; 32], Vec<Vec<[u8; 32]>>) {
+    let mut level: Vec<[u8; 32]> = entries.iter().map(|(mint, amount)| hash_leaf(mint, *amount)).collect();
+    let mut proofs = vec![vec![]; entries.len()];
+    let mut positions: Vec<usize> = (0..entries.len()).collect();
+    while level.len() > 1 {
+        for (proof, position) in proofs.iter_mut().zip(positions.iter_mut()) {
+            if let Some(sibling) = level.get(*position ^ 1) {
+                proof.push(*sibling);
+            }
+            *position /= 2;
+        }
+        level = level.chunks(2).map(|pair| match pair {
+            [left, right] => hash_nodes(left, right),
+            [node] => *node,
+            _ => unreachable!(),
+        }).collect();
+    }
+    (level[0], proofs)
+}
+
+impl World {
+    fn new(params: &Params) -> Self {
+        let mut accounts = BTreeMap::new();
+        accounts.insert(sysvar::clock::ID, clock_account(NOW));
+        accounts.insert(sysvar::rent::ID, Account::new(bincode::serialize(&Rent::default()).unwrap(), sysvar::ID));
+        for program in [system_program::ID, spl_token::ID, spl_token_metadata::ID, program_id()] {
+            accounts.insert(program, Account { executable: true, ..Account::new(vec![], Pubkey::default()) });
+        }
+
+        let authority = Pubkey::new_unique();
+        let claimants: Vec<Pubkey> = (0..CLAIMANTS).map(|_| Pubkey::new_unique()).collect();
+        for wallet in claimants.iter().chain(std::iter::once(&authority)) {
+            accounts.insert(*wallet, Account::new(vec![], system_program::ID));
+        }
+
+        let reward_mint = Pubkey::new_unique();
+        accounts.insert(reward_mint, mint_account(u64::MAX));
+        let reward_accounts: Vec<Pubkey> = claimants.iter().map(|claimant| {
+            let reward_account = Pubkey::new_unique();
+            accounts.insert(reward_account, token_account(reward_mint, *claimant, 1_000));
+            reward_account
+        }).collect();
+
+        // originals, a print of the first one, then NFTs of another creator and of another symbol
+        let creator = Pubkey::new_unique();
+        let nfts: Vec<Nft> = (0..7).map(|index| {
+            let mint = Pubkey::new_unique();
+            let owner = index % CLAIMANTS;
+            let nft_account = Pubkey::new_unique();
+            let (nft_creator, symbol) = match index {
+                5 => (Pubkey::new_unique(), COLLECTION_SYMBOL),
+                6 => (creator, "OTHER"),
+                _ => (creator, COLLECTION_SYMBOL),
+            };
+            accounts.insert(mint, mint_account(1));
+            accounts.insert(nft_account, token_account(mint, claimants[owner], 1));
+            accounts.insert(find_metadata_address(&mint).0, metadata_account(mint, nft_creator, symbol));
+
+            let (empty_account, foreign_account, wrong_mint_account) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+            let wrong_mint = Pubkey::new_unique();
+            accounts.insert(empty_account, token_account(mint, claimants[owner], 0));
+            accounts.insert(foreign_account, token_account(mint, claimants[(owner + 1) % CLAIMANTS], 1));
+            accounts.insert(wrong_mint, mint_account(1));
+            accounts.insert(wrong_mint_account, token_account(wrong_mint, claimants[owner], 1));
+            Nft { mint, owner, token_account: nft_account, empty_account, foreign_account, wrong_mint_account }
+        }).collect();
+        for (index, nft) in nfts.iter().enumerate() {
+            let edition = if index == 4 { print_edition_account(&nfts[0].mint) } else { master_edition_account() };
+            accounts.insert(find_edition_address(&nft.mint).0, edition);
+        }
+
+        // originals with and without an allowlist amount and the print, the other NFTs are left out
+        let entries: Vec<(Pubkey, u64)> = [(0, 0), (1, 25), (2, 0), (4, u64::MAX)].iter().map(|(index, amount)| (nfts[*index].mint, *amount)).collect();
+        let (merkle_root, proofs) = allowlist_tree(&entries);
+        let allowlist = entries.into_iter().zip(proofs).map(|((mint, amount), proof)| (mint, (amount, proof))).collect();
+
+        let vault = Pubkey::new_unique();
+        accounts.insert(vault, token_account(reward_mint, authority, params.reward_amount_total.saturating_add(params.vault_surplus)));
+
+        let mut world = World {
+            accounts,
+            claimants,
+            reward_accounts,
+            nfts,
+            creator,
+            distributor_state: find_distributor_state_address(&program_id(), &authority, 0).0,
+            allowlist,
+        };
+        let trait_filter = if params.trait_filter {
+            Some(solana_nft_claim_tokens::state::TraitFilter { oracle: world.claimants[0], trait_hash: hash_trait("Background", "Gold") })
+        } else {
+            None
+        };
+        let args = CreateTokenDistributorArgs {
+            distributor_id: 0,
+            reward_amount_total: params.reward_amount_total,
+            reward_amount_per_nft: params.reward_amount_per_nft,
+            start_ts: params.start_ts,
+            collection_symbol: COLLECTION_SYMBOL.to_string(),
+            reward_amount_per_print: params.reward_amount_per_print,
+            edition_policy: params.edition_policy,
+            trait_filter,
+            name_prefix: None,
+            uri_prefix: None,
+            max_claims_per_wallet: params.max_claims_per_wallet,
+            claim_fee_lamports: params.claim_fee_lamports,
+            fee_treasury: world.claimants[CLAIMANTS - 1],
+            referral_bps: params.referral_bps,
+            merkle_root: if params.allowlist { Some(merkle_root) } else { None },
+        };
+        let create = client::create_distributor(&program_id(), &authority, &vault, &creator, args);
+        // invalid settings are rejected, the fuzzed instructions then run against a world without a distributor
+        let _ = world.execute(&create);
+        world
+    }
+
+    fn keys(&self) -> Vec<Pubkey> {
+        self.accounts.keys().copied().collect()
+    }
+
+    fn token_balance(&self, token_account: &Pubkey) -> Option<u64> {
+        let account = self.accounts.get(token_account).filter(|account| account.owner == spl_token::ID)?;
+        TokenAccount::unpack(&account.data).ok().map(|token_account| token_account.amount)
+    }
+
+    fn vault_balance(&self, distributor: &DistributorAccount) -> u64 {
+        self.token_balance(&distributor.reward_token_account).unwrap_or_default()
+    }
+
+    fn distributors(&self) -> Vec<(Pubkey, DistributorAccount)> {
+        self.accounts
+            .iter()
+            .filter(|(_key, account)| account.owner == program_id() && account.data.first() == Some(&(AccountKey::Distributor as u8)))
+            .filter_map(|(key, account)| DistributorAccount::from_slice(&account.data).ok().map(|distributor| (*key, distributor)))
+            .collect()
+    }
+
+    fn receipt_count(&self) -> usize {
+        self.accounts
+            .values()
+            .filter(|account| account.owner == program_id() && account.data.first() == Some(&(AccountKey::ProofOfReceipt as u8)))
+            .count()
+    }
+
+    /// Runs the instruction over copies of the accounts and keeps the changes only if it succeeds, as the runtime does.
+    /// Accounts listed more than once share one AccountInfo, flagged with every flag they were listed with.
+    fn execute(&mut self, instruction: &Instruction) -> ProgramResult {
+        let mut keys: Vec<Pubkey> = vec![];
+        let mut flags: Vec<(bool, bool)> = vec![];
+        for meta in &instruction.accounts {
+            match keys.iter().position(|key| *key == meta.pubkey) {
+                Some(index) => {
+                    flags[index].0 |= meta.is_signer;
+                    flags[index].1 |= meta.is_writable;
+                },
+                None => {
+                    keys.push(meta.pubkey);
+                    flags.push((meta.is_signer, meta.is_writable));
+                },
+            }
+        }
+        let before: Vec<Account> = keys
+            .iter()
+            .map(|key| self.accounts.get(key).cloned().unwrap_or_else(|| Account { lamports: 0, ..Account::new(vec![], system_program::ID) }))
+            .collect();
+        let mut accounts = before.clone();
+        let owners: Vec<Pubkey> = accounts.iter().map(|account| account.owner).collect();
+
+        let (result, after) = {
+            let infos: Vec<AccountInfo> = keys
+                .iter()
+                .zip(&flags)
+                .zip(accounts.iter_mut())
+                .zip(&owners)
+                .map(|(((key, (is_signer, is_writable)), account), owner)| {
+                    AccountInfo::new(key, *is_signer, *is_writable, &mut account.lamports, &mut account.data[..], owner, account.executable, 0)
+                })
+                .collect();
+            let instruction_infos: Vec<AccountInfo> = instruction
+                .accounts
+                .iter()
+                .map(|meta| infos[keys.iter().position(|key| *key == meta.pubkey).unwrap()].clone())
+                .collect();
+            let result = process_instruction(&instruction.program_id, &instruction_infos, &instruction.data);
+            let after: Vec<Account> = infos
+                .iter()
+                .map(|info| Account { lamports: info.lamports(), data: info.data.borrow().to_vec(), owner: *info.owner, executable: info.executable })
+                .collect();
+            (result, after)
+        };
+
+        if result.is_ok() {
+            // the runtime would fail a transaction breaking these rules, the program must not rely on it
+            let total = |accounts: &[Account]| accounts.iter().map(|account| account.lamports as u128).sum::<u128>();
+            assert_eq!(total(&before), total(&after), "lamports were created or destroyed");
+            for ((key, (_is_signer, is_writable)), (before, after)) in keys.iter().zip(&flags).zip(before.iter().zip(&after)) {
+                assert!(*is_writable || before == after, "read-only account {} was modified", key);
+            }
+            for (key, account) in keys.into_iter().zip(after) {
+                self.accounts.insert(key, account);
+            }
+        }
+        result
+    }
+}
+
+fn params() -> impl Strategy<Value = Params> {
+    let amount = prop_oneof![0..=1_000u64, Just(u64::MAX), Just(u64::MAX / 2 + 1)];
+    (
+        (amount.clone(), amount.clone(), amount, 0..=100u64, prop_oneof![4 => Just(0i64), 1 => Just(NOW + 1), 1 => Just(i64::MIN)]),
+        (
+            prop_oneof![
+                Just(EditionPolicy::Both),
+                Just(EditionPolicy::OriginalsOnly),
+                Just(EditionPolicy::PrintsOnly),
+            ],
+            proptest::bool::weighted(0.25),
+            proptest::option::of(0..=3u64),
+            prop_oneof![Just(0u64), Just(5_000u64), Just(u64::MAX)],
+            prop_oneof![2 => Just(0u16), 2 => 1..=10_000u16, 1 => Just(u16::MAX)],
+            proptest::bool::weighted(0.25),
+        ),
+    )
+        .prop_map(|((reward_amount_total, reward_amount_per_nft, reward_amount_per_print, vault_surplus, start_ts), (edition_policy, trait_filter, max_claims_per_wallet, claim_fee_lamports, referral_bps, allowlist))| Params {
+            reward_amount_total,
+            reward_amount_per_nft,
+            reward_amount_per_print,
+            vault_surplus,
+            start_ts,
+            edition_policy,
+            trait_filter,
+            max_claims_per_wallet,
+            claim_fee_lamports,
+            referral_bps,
+            allowlist,
+        })
+}
+
+/// Tampering with a well-formed instruction; indexes wrap around the account list and the world's accounts
+#[derive(Debug, Clone)]
+enum Mutation {
+    Replace { position: usize, with: usize },
+    FlipSigner(usize),
+    FlipWritable(usize),
+    Truncate(usize),
+    Data { position: usize, byte: u8 },
+}
+
+// a claim PDA given at a valid bump below its canonical one
+#[derive(Debug, Clone, Copy)]
+enum ClaimPda {
+    Receipt,
+    WalletClaimRecord,
+}
+
+// the allowlist entry a claim gives: the NFT's own, the NFT's with another amount, or none
+#[derive(Debug, Clone, Copy)]
+enum AllowlistEntry {
+    Own,
+    WrongAmount,
+    Missing,
+}
+
+#[derive(Debug, Clone)]
+enum Op {
+    Claim { nft: usize, holder: Holder, referrer: Option<usize>, non_canonical: Option<ClaimPda>, entry: AllowlistEntry, mutation: Option<Mutation> },
+    GetClaimStatus { nft: usize, mutation: Option<Mutation> },
+    Attest { oracle: usize, nft: usize, traits: usize, mutation: Option<Mutation> },
+    CreateDistributor { creator: usize, distributor_id: u64, symbol: String, reward_amount_total: u64, reward_amount_per_nft: u64, mutation: Option<Mutation> },
+    SetClock(i64),
+    Raw { data: Vec<u8>, accounts: Vec<(usize, bool, bool)> },
+}
+
+fn holder() -> impl Strategy<Value = Holder> {
+    prop_oneof![4 => Just(Holder::Held), 1 => Just(Holder::Empty), 1 => Just(Holder::Foreign), 1 => Just(Holder::WrongMint)]
+}
+
+fn mutation() -> impl Strategy<Value = Option<Mutation>> {
+    proptest::option::weighted(
+        0.5,
+        prop_oneof![
+            (any::<usize>(), any::<usize>()).prop_map(|(position, with)| Mutation::Replace { position, with }),
+            any::<usize>().prop_map(Mutation::FlipSigner),
+            any::<usize>().prop_map(Mutation::FlipWritable),
+            any::<usize>().prop_map(Mutation::Truncate),
+            (any::<usize>(), any::<u8>()).prop_map(|(position, byte)| Mutation::Data { position, byte }),
+        ],
+    )
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        4 => (
+            0..7usize,
+            holder(),
+            proptest::option::of(0..CLAIMANTS),
+            proptest::option::weighted(0.2, prop_oneof![Just(ClaimPda::Receipt), Just(ClaimPda::WalletClaimRecord)]),
+            prop_oneof![4 => Just(AllowlistEntry::Own), 1 => Just(AllowlistEntry::WrongAmount), 1 => Just(AllowlistEntry::Missing)],
+            mutation(),
+        ).prop_map(|(nft, holder, referrer, non_canonical, entry, mutation)| Op::Claim { nft, holder, referrer, non_canonical, entry, mutation }),
+        1 => (0..7usize, mutation()).prop_map(|(nft, mutation)| Op::GetClaimStatus { nft, mutation }),
+        1 => (0..CLAIMANTS, 0..7usize, 0..20usize, mutation())
+            .prop_map(|(oracle, nft, traits, mutation)| Op::Attest { oracle, nft, traits, mutation }),
+        1 => (0..CLAIMANTS, 0..3u64, "\\PC{0,16}", 0..=2_000u64, prop_oneof![0..=100u64, Just(u64::MAX)], mutation())
+            .prop_map(|(creator, distributor_id, symbol, reward_amount_total, reward_amount_per_nft, mutation)| Op::CreateDistributor {
+                creator, distributor_id, symbol, reward_amount_total, reward_amount_per_nft, mutation,
+            }),
+        1 => prop_oneof![Just(0i64), Just(NOW), Just(NOW + 10), Just(i64::MAX)].prop_map(Op::SetClock),
+        1 => (vec(any::<u8>(), 0..64), vec((any::<usize>(), any::<bool>(), any::<bool>()), 0..20))
+            .prop_map(|(data, accounts)| Op::Raw { data, accounts }),
+    ]
+}
+
+fn mutate(instruction: &mut Instruction, mutation: &Option<Mutation>, keys: &[Pubkey]) {
+    let accounts = &mut instruction.accounts;
+    match mutation {
+        None => {},
+        Some(Mutation::Data { position, byte }) if !instruction.data.is_empty() => {
+            let position = position % instruction.data.len();
+            instruction.data[position] = *byte;
+        },
+        Some(Mutation::Data { .. }) => {},
+        Some(_) if accounts.is_empty() => {},
+        Some(Mutation::Replace { position, with }) => {
+            let position = position % accounts.len();
+            accounts[position].pubkey = keys[with % keys.len()];
+        },
+        Some(Mutation::FlipSigner(position)) => {
+            let position = position % accounts.len();
+            accounts[position].is_signer = !accounts[position].is_signer;
+        },
+        Some(Mutation::FlipWritable(position)) => {
+            let position = position % accounts.len();
+            accounts[position].is_writable = !accounts[position].is_writable;
+        },
+        Some(Mutation::Truncate(length)) => accounts.truncate(length % accounts.len()),
+    }
+}
+
+fn build_instruction(world: &World, op: &Op) -> Option<Instruction> {
+    let keys = world.keys();
+    let (mut instruction, mutation) = match op {
+        Op::Claim { nft, holder, referrer, non_canonical, entry, mutation } => {
+            let (distributor_state, distributor) = world.distributors().into_iter().find(|(key, _)| *key == world.distributor_state)?;
+            let nft_info = &world.nfts[*nft];
+            let claimant = world.claimants[nft_info.owner];
+            let referral = referrer.map(|referrer| (world.claimants[referrer], world.reward_accounts[referrer]));
+            let allowlist_entry = match entry {
+                AllowlistEntry::Own => world.allowlist.get(&nft_info.mint).cloned(),
+                AllowlistEntry::WrongAmount => world.allowlist.get(&nft_info.mint).map(|(amount, proof)| (amount.wrapping_add(1), proof.clone())),
+                AllowlistEntry::Missing => None,
+            };
+            let mut instruction = client::claim_tokens(
+                &program_id(),
+                &distributor_state,
+                &distributor,
+                &claimant,
+                &world.reward_accounts[nft_info.owner],
+                &nft_info.holder_account(*holder),
+                &nft_info.mint,
+                referral,
+                allowlist_entry,
+            );
+            if let Some(pda) = non_canonical {
+                let (position, seeds): (usize, [&[u8]; 3]) = match pda {
+                    ClaimPda::Receipt => (9, [RECEIPT_PREFIX.as_bytes(), nft_info.mint.as_ref(), distributor_state.as_ref()]),
+                    ClaimPda::WalletClaimRecord => (10, [WALLET_CLAIMS_PREFIX.as_bytes(), distributor_state.as_ref(), claimant.as_ref()]),
+                };
+                let canonical_bump = Pubkey::find_program_address(&seeds, &program_id()).1;
+                let address = (0..canonical_bump).rev().find_map(|bump| {
+                    let bump_seed = [bump];
+                    let mut seeds = seeds.to_vec();
+                    seeds.push(&bump_seed);
+                    Pubkey::create_program_address(&seeds, &program_id()).ok()
+                })?;
+                instruction.accounts[position].pubkey = address;
+            }
+            (instruction, mutation)
+        },
+        Op::GetClaimStatus { nft, mutation } => {
+            let (distributor_state, distributor) = world.distributors().into_iter().find(|(key, _)| *key == world.distributor_state)?;
+            let nft_info = &world.nfts[*nft];
+            let allowlist_entry = world.allowlist.get(&nft_info.mint).cloned();
+            (client::get_claim_status(&program_id(), &distributor_state, &distributor, &nft_info.mint, Some(&nft_info.token_account), allowlist_entry), mutation)
+        },
+        Op::Attest { oracle, nft, traits, mutation } => {
+            let trait_hashes = (0..*traits).map(|index| hash_trait("Background", if index == 0 { "Gold" } else { "Silver" })).collect();
+            (client::attest_attributes(&program_id(), &world.claimants[*oracle], &world.nfts[*nft].mint, trait_hashes), mutation)
+        },
+        Op::CreateDistributor { creator, distributor_id, symbol, reward_amount_total, reward_amount_per_nft, mutation } => {
+            let args = CreateTokenDistributorArgs {
+                distributor_id: *distributor_id,
+                reward_amount_total: *reward_amount_total,
+                reward_amount_per_nft: *reward_amount_per_nft,
+                start_ts: 0,
+                collection_symbol: symbol.clone(),
+                reward_amount_per_print: *reward_amount_per_nft,
+                edition_policy: EditionPolicy::Both,
+                trait_filter: None,
+                name_prefix: None,
+                uri_prefix: None,
+                max_claims_per_wallet: None,
+                claim_fee_lamports: 0,
+                fee_treasury: world.claimants[*creator],
+                referral_bps: 0,
+                merkle_root: None,
+            };
+            let instruction = client::create_distributor(&program_id(), &world.claimants[*creator], &world.reward_accounts[*creator], &world.creator, args);
+            (instruction, mutation)
+        },
+        Op::SetClock(_) => return None,
+        Op::Raw { data, accounts } => {
+            let accounts = accounts
+                .iter()
+                .map(|(index, is_signer, is_writable)| AccountMeta { pubkey: keys[index % keys.len()], is_signer: *is_signer, is_writable: *is_writable })
+                .collect();
+            return Some(Instruction { program_id: program_id(), accounts, data: data.clone() });
+        },
+    };
+    mutate(&mut instruction, mutation, &keys);
+    Some(instruction)
+}
+
+/// Runs the op and checks the invariants against the state before it
+fn step(world: &mut World, op: &Op) {
+    if let Op::SetClock(unix_timestamp) = op {
+        world.accounts.insert(sysvar::clock::ID, clock_account(*unix_timestamp));
+        return;
+    }
+    let instruction = match build_instruction(world, op) {
+        Some(instruction) => instruction,
+        None => return,
+    };
+
+    let vaults_before: BTreeMap<Pubkey, (u64, u64)> = world
+        .distributors()
+        .into_iter()
+        .map(|(key, distributor)| (key, (world.vault_balance(&distributor), distributor.amount_claimed)))
+        .collect();
+    let receipts_before = world.receipt_count();
+    let result = world.execute(&instruction);
+    let is_claim = matches!(ClaimTokenInstruction::try_from_slice(&instruction.data), Ok(ClaimTokenInstruction::ClaimTokens(_)));
+
+    for (distributor_state, distributor) in world.distributors() {
+        assert!(
+            distributor.amount_claimed <= distributor.reward_amount_total,
+            "distributor {} claimed {} of {}", distributor_state, distributor.amount_claimed, distributor.reward_amount_total
+        );
+        assert_eq!(find_distributor_authority(&program_id(), &distributor_state).1, distributor.authority_bump);
+
+        // tokens leave the vault only through a successful claim, and the pool counts every token paid out
+        let (vault_before, claimed_before) = match vaults_before.get(&distributor_state) {
+            Some(before) => *before,
+            None => continue,
+        };
+        let paid_out = vault_before.saturating_sub(world.vault_balance(&distributor));
+        assert_eq!(paid_out, distributor.amount_claimed - claimed_before, "vault payouts and amount_claimed drifted");
+        if paid_out > 0 {
+            assert!(result.is_ok() && is_claim, "tokens left the vault outside of a claim: {:?}", op);
+            assert_eq!(world.receipt_count(), receipts_before + 1, "a claim paid out without a new receipt");
+
+            // and only to a claimant whose NFT account actually holds the NFT the metadata describes
+            let (claimant, nft_account, metadata) = (instruction.accounts[0].pubkey, instruction.accounts[5].pubkey, instruction.accounts[6].pubkey);
+            let holding = TokenAccount::unpack(&world.accounts[&nft_account].data).expect("paid out against a non-token NFT account");
+            assert_eq!(holding.owner, claimant, "paid out against an NFT account of another wallet: {:?}", op);
+            assert!(holding.amount >= 1, "paid out against an empty NFT account: {:?}", op);
+            assert_eq!(find_metadata_address(&holding.mint).0, metadata, "paid out against an NFT account of another mint: {:?}", op);
+
+            // with the receipt and wallet record at their canonical bumps - any other bump would allow a second claim
+            let (receipt, wallet_claim_record) = (instruction.accounts[9].pubkey, instruction.accounts[10].pubkey);
+            assert_eq!(find_receipt_address(&program_id(), &holding.mint, &distributor_state).0, receipt, "paid out with a second receipt: {:?}", op);
+            if distributor.max_claims_per_wallet.is_some() {
+                assert_eq!(
+                    find_wallet_claim_record_address(&program_id(), &distributor_state, &claimant).0, wallet_claim_record,
+                    "paid out with a second wallet claim record: {:?}", op
+                );
+            }
+
+            // for an allowlisted mint only, with the amount it is allowlisted with
+            if let Some(merkle_root) = distributor.merkle_root {
+                let args = match ClaimTokenInstruction::try_from_slice(&instruction.data) {
+                    Ok(ClaimTokenInstruction::ClaimTokens(args)) => args,
+                    _ => unreachable!(),
+                };
+                assert!(
+                    verify_proof(&merkle_root, &holding.mint, args.allowlist_amount, &args.merkle_proof),
+                    "paid out to a mint outside the allowlist: {:?}", op
+                );
+                assert!(args.allowlist_amount == 0 || paid_out >= args.allowlist_amount, "paid out less than the allowlist amount: {:?}", op);
+            }
+        }
+    }
+}
+
+fn setup_runtime() {
+    static RUNTIME: Once = Once::new();
+    RUNTIME.call_once(|| {
+        set_syscall_stubs(Box::new(Runtime));
+    });
+}
+
+proptest! {
+    #[test]
+    fn fuzz_process_instruction(params in params(), ops in vec(op(), 1..24)) {
+        setup_runtime();
+        let mut world = World::new(&params);
+        for op in &ops {
+            step(&mut world, op);
+        }
+    }
+
+    #[test]
+    fn fuzz_puffed_out_string(s in "\\PC{0,40}", size in 0..32usize) {
+        let puffed = puffed_out_string(&s, size);
+        prop_assert!(puffed.starts_with(&s));
+        prop_assert_eq!(puffed.len(), size.max(s.len()));
+    }
+}